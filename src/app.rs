@@ -1,18 +1,35 @@
 use crate::{
-    config::ChatConfig,
-    types::{Message, MessageContent, Role},
+    backend::{self, ChatBackend},
+    config::{ChatConfig, SamplingParams},
+    context::{self, ContextPlan},
+    error::ApiError,
+    export::{self, ExportFormat},
+    import,
+    sessions::{self, SessionInfo, SessionStore},
+    streaming,
+    tokenizer::TokenCounter,
+    types::{Message, MessageContent, Role, Session, Usage, SESSION_FORMAT_VERSION},
 };
-use openai_api_rs::v1::{
-    api::Client as OpenAIClient,
-    chat_completion::{ChatCompletionMessage, ChatCompletionRequest, MessageRole},
+use chrono::{DateTime, Local};
+use openai_api_rs::v1::chat_completion::{
+    ChatCompletionMessage, ChatCompletionRequest, MessageRole,
 };
 use regex::Regex;
 use std::{
+    borrow::{Borrow, Cow},
+    cell::{Cell, RefCell},
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
     fs,
+    hash::{Hash, Hasher},
+    ops::Range,
+    path::Path,
     sync::Arc,
-    time::Instant,
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
 };
-use tokio::sync::mpsc;
 
 #[derive(Clone)]
 pub enum ScrollState {
@@ -36,58 +53,311 @@ pub enum StatusType {
 
 #[derive(Debug)]
 pub enum ApiMessage {
-    Response(String),
-    Error(String),
+    /// A chunk of assistant text received from the stream
+    Delta(String),
+    /// A chunk of another answer to the same request, when `n` asks for several
+    OtherChoice {
+        index: i64,
+        delta: String,
+        finish_reason: Option<String>,
+    },
+    /// Token usage reported for the request
+    Usage(Usage),
+    /// The stream finished, with the provider's finish reason if one was sent
+    Done(Option<String>),
+    /// A retryable error occurred and the request will be sent again after `delay`
+    Retrying {
+        attempt: u32,
+        max_retries: u32,
+        delay: Duration,
+        error: ApiError,
+    },
+    /// The model is cold-starting; the request is resubmitted once it is ready
+    ModelLoading {
+        estimated_time: Duration,
+    },
+    /// The summary requested by a compaction
+    Summary(String),
+    Error(ApiError),
+}
+
+/// Most tokens a compaction summary may take
+const SUMMARY_MAX_TOKENS: i64 = 512;
+
+/// Instructions for turning a span of the conversation into a summary note
+const SUMMARY_PROMPT: &str = "Summarise the conversation below so it can replace the original \
+messages in your context. Keep every decision made, fact established, file, function and \
+command mentioned, open question and preference the user stated. Use terse bullet points \
+and reply with the summary only.";
+
+/// A summary request in flight, and where its note goes once it arrives
+pub struct PendingCompaction {
+    /// Index the summary note is inserted at, right after the last summarised message
+    pub insert_at: usize,
+    /// Messages the note stands in for, including those of any earlier summary
+    pub covers: usize,
+    /// Send the conversation once the note is in place (automatic compaction)
+    pub resend: bool,
+    /// `App::generation` when the summary was requested; `insert_at` is only valid while
+    /// it is unchanged
+    pub generation: u64,
+}
+
+/// A branch started by editing a prompt or regenerating an answer. Until its reply
+/// arrives, a failed or cancelled request puts the previous branch back.
+pub struct PendingBranch {
+    /// Where the new branch starts on the active path
+    pub at: usize,
+    /// Every other version of the message at `at`, the previously active one included
+    pub siblings: Vec<Vec<Message>>,
+    /// Position of the previously active branch in `siblings`
+    pub previous: usize,
+}
+
+/// Most answers `/retry` asks for in one go
+pub const MAX_CANDIDATES: usize = 8;
+
+/// Answers still to generate for `/retry`, one after the other as siblings
+pub struct CandidateRun {
+    /// Index of the answer being regenerated
+    pub at: usize,
+    pub done: usize,
+    pub count: usize,
+    /// Sampling parameters for these answers only, e.g. a higher temperature
+    pub params: SamplingParams,
+}
+
+/// An automatic retry that is waiting to fire, shown as a countdown in the status bar
+pub struct RetryStatus {
+    pub attempt: u32,
+    pub max_retries: u32,
+    pub resume_at: Instant,
+    pub reason: String,
+}
+
+/// Progress of a Hugging Face model cold start
+pub struct ModelLoadingStatus {
+    pub started: Instant,
+    pub ready_at: Instant,
+}
+
+impl ModelLoadingStatus {
+    /// Fraction of the estimated load time that has elapsed, in `0.0..=1.0`
+    pub fn progress(&self) -> f64 {
+        let total = self.ready_at.saturating_duration_since(self.started);
+        if total.is_zero() {
+            return 1.0;
+        }
+        (self.started.elapsed().as_secs_f64() / total.as_secs_f64()).min(1.0)
+    }
+}
+
+/// State of the `/model` picker popup
+pub struct ModelPicker {
+    /// Models reported by the endpoint; `None` while the list is being fetched
+    pub models: Option<Vec<String>>,
+    pub filter: String,
+    pub selected: usize,
+    receiver: Option<oneshot::Receiver<Result<Vec<String>, ApiError>>>,
+}
+
+impl ModelPicker {
+    /// Models whose name contains the filter, ignoring case
+    pub fn filtered(&self) -> Vec<&str> {
+        let filter = self.filter.to_lowercase();
+        self.models
+            .iter()
+            .flatten()
+            .filter(|model| model.to_lowercase().contains(&filter))
+            .map(String::as_str)
+            .collect()
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        let count = self.filtered().len();
+        if self.selected + 1 < count {
+            self.selected += 1;
+        }
+    }
+
+    pub fn push_filter(&mut self, c: char) {
+        self.filter.push(c);
+        self.selected = 0;
+    }
+
+    pub fn pop_filter(&mut self) {
+        self.filter.pop();
+        self.selected = 0;
+    }
+
+    /// The highlighted model, or the typed filter when nothing matches it
+    pub fn choice(&self) -> Option<String> {
+        match self.filtered().get(self.selected) {
+            Some(model) => Some(model.to_string()),
+            None => Some(self.filter.trim().to_string()).filter(|name| !name.is_empty()),
+        }
+    }
+}
+
+/// State of the `/sessions` popup
+pub struct SessionBrowser {
+    pub sessions: Vec<SessionInfo>,
+    pub selected: usize,
+    /// New title being typed for the selected session
+    pub renaming: Option<String>,
+    /// `d` was pressed once; pressing it again deletes the selected session
+    pub confirm_delete: bool,
+}
+
+impl SessionBrowser {
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+        self.confirm_delete = false;
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.sessions.len() {
+            self.selected += 1;
+        }
+        self.confirm_delete = false;
+    }
+
+    pub fn selected(&self) -> Option<&SessionInfo> {
+        self.sessions.get(self.selected)
+    }
 }
 
 pub struct App {
-    pub client: Arc<OpenAIClient>,
+    pub backend: Arc<dyn ChatBackend>,
     pub config: ChatConfig,
+    pub token_counter: TokenCounter,
+    /// Token counts by hash of the message text, so the context plan drawn every frame
+    /// only tokenizes messages that are new or changed
+    token_cache: RefCell<HashMap<u64, usize>>,
+    /// Settings before any profile was applied, so profiles can be switched cleanly
+    pub base_config: ChatConfig,
     pub messages: Vec<Message>,
     pub input: String,
     pub input_mode: InputMode,
     pub show_help: bool,
+    /// Lines the help popup is scrolled down by
+    pub help_scroll: u16,
+    /// Largest useful `help_scroll` for the frame last drawn, set while drawing
+    pub help_max_scroll: Cell<u16>,
     pub show_thinking: bool,
+    pub show_error_details: bool,
+    pub show_params: bool,
+    pub show_context: bool,
+    pub last_error: Option<ApiError>,
     pub status_message: Option<(String, StatusType)>,
     pub is_loading: bool,
+    pub is_streaming: bool,
     pub loading_frame: usize,
     pub scroll_offset: usize,
     pub scroll_state: ScrollState,
     pub api_receiver: Option<mpsc::UnboundedReceiver<ApiMessage>>,
+    pub request_handle: Option<JoinHandle<()>>,
+    /// When the current request was sent, for the answer's latency
+    pub request_started: Option<Instant>,
+    pub retry_status: Option<RetryStatus>,
+    pub model_loading: Option<ModelLoadingStatus>,
+    pub model_picker: Option<ModelPicker>,
+    pub compaction: Option<PendingCompaction>,
+    /// Bumped whenever messages are inserted, removed or swapped for another branch, so
+    /// indices taken earlier can be told apart from current ones
+    pub generation: u64,
+    /// Shown instead of the success message once the current response is done
+    pub turn_warning: Option<String>,
+    /// Index of the earlier prompt being edited; sending forks a new branch there
+    pub editing: Option<usize>,
+    pub pending_branch: Option<PendingBranch>,
+    pub candidates: Option<CandidateRun>,
+    /// Answers beyond the first to the request in flight, by choice index; they become
+    /// siblings of the first once the stream is done
+    pub other_choices: BTreeMap<i64, Message>,
+    /// Where conversations are autosaved; `None` when no data directory could be found
+    pub session_store: Option<SessionStore>,
+    /// ID of the stored session this conversation is saved as, assigned on first save
+    pub session_id: Option<String>,
+    pub session_title: Option<String>,
+    pub session_created: Option<DateTime<Local>>,
+    /// Tokens billed per model in this session, whichever branch they went into
+    pub usage: BTreeMap<String, Usage>,
+    pub session_browser: Option<SessionBrowser>,
 }
 
 impl App {
-    pub fn new(config: ChatConfig) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(base_config: ChatConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let app = Self::headless(base_config)?;
+        let config = &app.config;
+
         println!("\n🔧 API Configuration:");
+        if let Some(profile) = &config.profile {
+            println!("  Profile: {}", profile);
+        }
         println!("  URL: {}", config.base_url);
+        println!("  Backend: {:?}", config.backend);
         println!("  Model: {}", config.model);
-        println!(
-            "  Token: {}...\n",
-            &config.token[..10.min(config.token.len())]
-        );
+        println!("  Token: {}\n", config.token);
 
-        let client = Arc::new(OpenAIClient::new_with_endpoint(
-            config.base_url.clone(),
-            config.token.clone(),
-        ));
+        Ok(app)
+    }
+
+    /// Builds the app without printing the configuration banner, for non-interactive use
+    pub fn headless(base_config: ChatConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let config = base_config.resolve_profile()?;
+        let backend = backend::from_config(&config)?;
+        let token_counter = TokenCounter::from_config(&config)?;
 
         let mut app = Self {
-            client,
+            backend,
             config: config.clone(),
+            token_counter,
+            token_cache: RefCell::default(),
+            base_config,
             messages: Vec::new(),
             input: String::new(),
             input_mode: InputMode::Normal,
             show_help: false,
+            help_scroll: 0,
+            help_max_scroll: Cell::default(),
             show_thinking: false,
+            show_error_details: false,
+            show_params: false,
+            show_context: false,
+            last_error: None,
             status_message: Some((
                 "Welcome! Press 'i' to start typing, 'h' for help, 'q' to quit".to_string(),
                 StatusType::Info,
             )),
             is_loading: false,
+            is_streaming: false,
             loading_frame: 0,
             scroll_offset: 0,
             scroll_state: ScrollState::Bottom,
             api_receiver: None,
+            request_handle: None,
+            request_started: None,
+            retry_status: None,
+            model_loading: None,
+            model_picker: None,
+            compaction: None,
+            generation: 0,
+            turn_warning: None,
+            editing: None,
+            pending_branch: None,
+            candidates: None,
+            other_choices: BTreeMap::new(),
+            session_store: SessionStore::from_config(&config),
+            session_id: None,
+            session_title: None,
+            session_created: None,
+            usage: BTreeMap::new(),
+            session_browser: None,
         };
 
         if let Some(system_prompt) = &config.system_prompt {
@@ -104,54 +374,103 @@ impl App {
             MessageContent::Text(content)
         };
 
-        self.messages.push(Message {
-            role,
-            content: message_content,
-            timestamp: Instant::now(),
-            datetime: chrono::Local::now(),
-        });
+        self.messages.push(Message::new(role, message_content));
         self.scroll_state = ScrollState::Bottom;
         self.scroll_offset = 0;
     }
 
     pub fn estimate_tokens(&self, text: &str) -> usize {
-        text.len() / 4
+        self.token_counter.count(text)
     }
 
-    pub fn prepare_api_messages(&self) -> Vec<ChatCompletionMessage> {
-        let max_messages = self.config.max_context_messages;
-
-        let messages_to_send = if self.messages.len() > max_messages {
-            let mut result = Vec::new();
+    /// Token usage per model over the session, including answers on other branches and
+    /// compaction summaries
+    pub fn usage_by_model(&self) -> &BTreeMap<String, Usage> {
+        &self.usage
+    }
 
-            if let Some(first) = self.messages.first() {
-                if first.role == Role::System {
-                    result.push(first.clone());
-                }
+    /// Total usage of the session and, when any of its models has a price, the cost in USD
+    pub fn session_usage(&self) -> (Usage, Option<f64>) {
+        let mut total = Usage::default();
+        let mut cost = None;
+        for (model, &usage) in self.usage_by_model() {
+            total += usage;
+            if let Some(price) = self.config.prices.get(model) {
+                *cost.get_or_insert(0.0) += price.cost(&usage);
             }
+        }
+        (total, cost)
+    }
 
-            let start_idx = if result.is_empty() {
-                self.messages.len().saturating_sub(max_messages)
-            } else {
-                self.messages.len().saturating_sub(max_messages - 1).max(1)
-            };
+    /// Which messages fit into the next request's token budget
+    pub fn context_plan(&self) -> ContextPlan {
+        let tokens = self.message_tokens(&self.messages);
+        context::plan_with_tokens(&self.messages, tokens, &self.config)
+    }
 
-            result.extend(self.messages[start_idx..].iter().cloned());
-            result
-        } else {
-            self.messages.clone()
+    /// The plan for the next request as if the prompt being typed were sent now, together
+    /// with the conversation it was made for and whether its last message is that draft.
+    /// A prompt being edited replaces the one it was taken from and everything after it.
+    pub fn draft_context_plan(&self) -> (Vec<Cow<'_, Message>>, ContextPlan, bool) {
+        let draft = self.input.trim();
+        let has_draft = !draft.is_empty() && !draft.starts_with('/');
+        let kept = match self.editing {
+            Some(at) if has_draft => at.min(self.messages.len()),
+            _ => self.messages.len(),
         };
+        let mut messages: Vec<Cow<'_, Message>> =
+            self.messages[..kept].iter().map(Cow::Borrowed).collect();
+        if has_draft {
+            messages.push(Cow::Owned(Message::new(
+                Role::User,
+                MessageContent::Text(self.input.clone()),
+            )));
+        }
+        let tokens = self.message_tokens(&messages);
+        let plan = context::plan_with_tokens(&messages, tokens, &self.config);
+        (messages, plan, has_draft)
+    }
 
-        messages_to_send
+    /// Token counts of `messages`, from the cache where the text is unchanged. Only the
+    /// counts used here are kept, so the cache never outgrows the conversation.
+    fn message_tokens<M: Borrow<Message>>(&self, messages: &[M]) -> Vec<usize> {
+        let mut cache = self.token_cache.borrow_mut();
+        let mut used = HashMap::with_capacity(messages.len());
+        let tokens = messages
             .iter()
             .map(|msg| {
-                let text_content = match &msg.content {
-                    MessageContent::Text(text) => text.clone(),
-                    MessageContent::WithThinking { output, .. } => output.clone(),
+                let msg = msg.borrow();
+                let mut hasher = DefaultHasher::new();
+                msg.content.output().hash(&mut hasher);
+                let key = hasher.finish();
+                let tokens = match cache.get(&key) {
+                    Some(&tokens) => tokens,
+                    None => self.token_counter.count_message(msg),
+                };
+                used.insert(key, tokens);
+                tokens
+            })
+            .collect();
+        *cache = used;
+        tokens
+    }
+
+    pub fn prepare_api_messages(&self) -> Vec<ChatCompletionMessage> {
+        self.context_plan()
+            .included
+            .iter()
+            .map(|&i| {
+                let msg = &self.messages[i];
+                let content = match msg.summarizes {
+                    Some(_) => format!(
+                        "Summary of the earlier conversation:\n{}",
+                        msg.content.output()
+                    ),
+                    None => msg.content.output().to_string(),
                 };
                 ChatCompletionMessage {
                     role: msg.role.clone().into(),
-                    content: text_content,
+                    content,
                     name: None,
                     function_call: None,
                 }
@@ -166,167 +485,1375 @@ impl App {
             return Ok(());
         }
 
+        // A second turn would take over the one still streaming, so the draft waits in the
+        // input until the response is in or cancelled
+        if self.is_loading && !self.input.starts_with('/') {
+            self.status_message = Some((
+                "⚠ Wait for the current response before sending, or cancel it with Esc in normal mode".to_string(),
+                StatusType::Warning,
+            ));
+            return Ok(());
+        }
+
         let user_input = self.input.clone();
         self.input.clear();
         self.input_mode = InputMode::Normal;
 
-
         if let Some(command) = user_input.strip_prefix('/') {
             self.handle_command(command).await?;
             return Ok(());
         }
 
-        self.add_message(Role::User, user_input.clone());
+        match self.editing.take() {
+            Some(at) => {
+                self.branch_off(at);
+                self.add_message(Role::User, user_input.clone());
+            }
+            None => self.add_message(Role::User, user_input.clone()),
+        }
         self.is_loading = true;
-        self.status_message = Some(("Sending message...".to_string(), StatusType::Info));
+        self.turn_warning = None;
+        let plan = self.context_plan();
+        if self.config.compaction && !plan.dropped.is_empty() {
+            let span = plan.dropped[0]..plan.dropped[plan.dropped.len() - 1] + 1;
+            self.start_compaction(span, true);
+            return Ok(());
+        }
+        self.status_message = Some(if plan.over_budget() {
+            (
+                format!(
+                    "⚠ Message is ~{} tokens, over the {} token budget - the endpoint may reject it",
+                    plan.total, plan.budget
+                ),
+                StatusType::Warning,
+            )
+        } else if !plan.dropped.is_empty() {
+            (
+                format!(
+                    "Sending message... ({} older messages left out to fit {} tokens)",
+                    plan.dropped.len(),
+                    plan.budget
+                ),
+                StatusType::Info,
+            )
+        } else {
+            ("Sending message...".to_string(), StatusType::Info)
+        });
+        self.start_request();
+
+        Ok(())
+    }
 
+    /// Sends the conversation to the backend in a background task, feeding `api_receiver`
+    pub fn start_request(&mut self) {
         let api_messages = self.prepare_api_messages();
+        self.request_started = Some(Instant::now());
 
         let mut req = ChatCompletionRequest::new(self.config.model.clone(), api_messages);
-        if let Some(max_tokens) = self.config.max_tokens {
-            req.max_tokens = Some(max_tokens);
-        }
-        if let Some(temperature) = self.config.temperature {
-            req.temperature = Some(temperature);
+        match &self.candidates {
+            Some(run) => run.params.apply(&mut req),
+            None => self.config.params.apply(&mut req),
         }
 
         let (tx, rx) = mpsc::unbounded_channel();
         self.api_receiver = Some(rx);
 
-        let client = self.client.clone();
+        let backend = self.backend.clone();
+        let retry = self.config.retry.clone();
 
-        tokio::spawn(async move {
-            match client.chat_completion(req) {
+        let handle = tokio::spawn(async move {
+            if let Err(e) = streaming::stream_with_retry(backend.as_ref(), &req, &tx, &retry).await
+            {
+                let _ = tx.send(ApiMessage::Error(e));
+            }
+        });
+        // A request nobody listens to any more would still be generating, and billed
+        if let Some(previous) = self.request_handle.replace(handle) {
+            previous.abort();
+        }
+    }
+
+    /// Asks the model to summarise the messages in `span`, together with any earlier
+    /// summary, into a note that replaces them in later requests.
+    ///
+    /// Only the most recent part of a span too long for the context is shown to the model.
+    pub fn start_compaction(&mut self, span: Range<usize>, resend: bool) {
+        let previous = self.messages[..span.start]
+            .iter()
+            .rev()
+            .find(|m| m.summarizes.is_some());
+        let covers = span.len() + previous.and_then(|m| m.summarizes).unwrap_or(0);
+
+        // Room for the transcript: the context minus the summary, the instructions and
+        // the previous summary
+        let mut used = SUMMARY_MAX_TOKENS as usize + self.token_counter.count(SUMMARY_PROMPT);
+        if let Some(previous) = previous {
+            used += self.token_counter.count_message(previous);
+        }
+        let limit = self.config.context_length;
+        let mut turns = Vec::new();
+        // Pinned messages are sent anyway and excluded ones must never reach the model
+        for msg in self.messages[span.clone()]
+            .iter()
+            .rev()
+            .filter(|m| !m.pinned && !m.excluded)
+        {
+            used += self.token_counter.count_message(msg);
+            if used > limit && !turns.is_empty() {
+                break;
+            }
+            let speaker = match msg.role {
+                Role::User => "User",
+                Role::Assistant => "Assistant",
+                Role::System => "System",
+            };
+            turns.push(format!("{}: {}", speaker, msg.content.output()));
+        }
+        turns.reverse();
+        let mut transcript = String::new();
+        if let Some(previous) = previous {
+            transcript.push_str(&format!(
+                "Summary of what came before:\n{}\n\n",
+                previous.content.output()
+            ));
+        }
+        transcript.push_str(&turns.join("\n\n"));
+
+        let mut req = ChatCompletionRequest::new(
+            self.config.model.clone(),
+            vec![
+                ChatCompletionMessage {
+                    role: MessageRole::system,
+                    content: SUMMARY_PROMPT.to_string(),
+                    name: None,
+                    function_call: None,
+                },
+                ChatCompletionMessage {
+                    role: MessageRole::user,
+                    content: transcript,
+                    name: None,
+                    function_call: None,
+                },
+            ],
+        );
+        self.config.params.apply(&mut req);
+        req.max_tokens = Some(SUMMARY_MAX_TOKENS);
+        req.n = None;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.api_receiver = Some(rx);
+        let backend = self.backend.clone();
+        let handle = tokio::spawn(async move {
+            let _ = tx.send(match backend.send(&req).await {
                 Ok(response) => {
-                    if let Some(choice) = response.choices.first() {
-                        if let Some(content) = choice.message.content.as_ref() {
-                            let _ = tx.send(ApiMessage::Response(content.clone()));
-                        } else {
-                            let _ = tx.send(ApiMessage::Error("No content received".to_string()));
-                        }
-                    } else {
-                        let _ = tx.send(ApiMessage::Error("No response received".to_string()));
+                    if let Some(usage) = response.usage {
+                        let _ = tx.send(ApiMessage::Usage(usage));
                     }
+                    ApiMessage::Summary(response.content)
                 }
-                Err(e) => {
-                    let error_msg = if e.to_string().contains("404") {
-                        format!("Error 404: API endpoint not found. Check WORKING_CONFIGS.md for valid configurations.")
-                    } else if e.to_string().contains("401") {
-                        format!("Error 401: Invalid API key. Please check your token.")
-                    } else if e.to_string().contains("429") {
-                        format!("Error 429: Rate limit exceeded. Please wait and try again.")
-                    } else if e.to_string().contains("Connection refused")
-                        || e.to_string().contains("connection")
-                    {
-                        format!("Connection Error: Cannot reach API. Check if service is running and HF_BASE_URL is correct.")
-                    } else {
-                        format!("API Error: {}. See WORKING_CONFIGS.md for help.", e)
-                    };
+                Err(e) => ApiMessage::Error(e),
+            });
+        });
+        if let Some(previous) = self.request_handle.replace(handle) {
+            previous.abort();
+        }
+        self.is_loading = true;
+        self.compaction = Some(PendingCompaction {
+            insert_at: span.end,
+            covers,
+            resend,
+            generation: self.generation,
+        });
+        self.status_message = Some((
+            format!("📝 Summarising {} earlier messages...", span.len()),
+            StatusType::Info,
+        ));
+    }
 
-                    let _ = tx.send(ApiMessage::Error(error_msg));
-                }
+    /// Puts a finished summary into the transcript and, for automatic compaction,
+    /// sends the conversation it was made for
+    fn finish_compaction(&mut self, compaction: PendingCompaction, summary: String) {
+        let summary = parse_thinking_tokens(summary.trim())
+            .output()
+            .trim()
+            .to_string();
+        if summary.is_empty() {
+            self.compaction_failed(
+                compaction,
+                "the model returned an empty summary".to_string(),
+            );
+            return;
+        }
+
+        if compaction.generation != self.generation {
+            self.compaction_failed(
+                compaction,
+                "the conversation changed while it was being summarised".to_string(),
+            );
+            return;
+        }
+        let mut note = Message::new(Role::System, MessageContent::Text(summary));
+        note.model = Some(self.config.model.clone());
+        note.summarizes = Some(compaction.covers);
+        self.messages.insert(compaction.insert_at, note);
+        self.generation += 1;
+        if let Some(pending) = &mut self.pending_branch {
+            if compaction.insert_at <= pending.at {
+                pending.at += 1;
             }
-        });
+        }
+        self.status_message = Some((
+            format!("📝 Summarised {} earlier messages", compaction.covers),
+            StatusType::Success,
+        ));
+        self.end_request();
+        if compaction.resend {
+            self.start_request();
+        } else {
+            self.is_loading = false;
+        }
+    }
 
-        Ok(())
+    /// Automatic compaction falls back to plain trimming; a manual one just reports
+    fn compaction_failed(&mut self, compaction: PendingCompaction, reason: String) {
+        self.end_request();
+        if compaction.resend {
+            self.start_request();
+            let warning = format!(
+                "⚠ Could not summarise older messages ({}) - sent without them",
+                reason
+            );
+            self.status_message = Some((warning.clone(), StatusType::Warning));
+            self.turn_warning = Some(warning);
+        } else {
+            self.is_loading = false;
+            self.status_message = Some((
+                format!("✗ Could not summarise: {}", reason),
+                StatusType::Error,
+            ));
+        }
+    }
+
+    /// Aborts the in-flight request and puts the user's prompt back into the input box
+    pub fn cancel_request(&mut self) {
+        if !self.is_loading {
+            return;
+        }
+
+        // Aborting the task drops the connection, stopping the server-side generation too
+        if let Some(handle) = self.request_handle.take() {
+            handle.abort();
+        }
+        self.end_request();
+
+        if self.is_streaming {
+            self.messages.pop();
+            self.generation += 1;
+        }
+        self.is_loading = false;
+        self.is_streaming = false;
+        self.restore_prompt();
+
+        self.status_message = Some((
+            "⊘ Request cancelled - prompt restored to input".to_string(),
+            StatusType::Warning,
+        ));
+        self.autosave();
     }
 
     pub fn process_api_response(&mut self) {
-        if let Some(receiver) = &mut self.api_receiver {
+        while let Some(receiver) = &mut self.api_receiver {
             match receiver.try_recv() {
-                Ok(msg) => {
-                    self.is_loading = false;
-                    match msg {
-                        ApiMessage::Response(content) => {
-                            self.add_message(Role::Assistant, content);
-                            self.status_message = Some((
-                                "✓ Message sent successfully".to_string(),
-                                StatusType::Success,
-                            ));
-                        }
-                        ApiMessage::Error(error_msg) => {
-                            if let Some(last_msg) = self.messages.last() {
-                                if last_msg.role == Role::User {
-                                    self.messages.pop();
-                                }
-                            }
-                            self.status_message =
-                                Some((format!("✗ {}", error_msg), StatusType::Error));
+                Ok(ApiMessage::Delta(delta)) => {
+                    self.retry_status = None;
+                    self.model_loading = None;
+                    self.append_stream_delta(&delta);
+                }
+                Ok(ApiMessage::OtherChoice {
+                    index,
+                    delta,
+                    finish_reason,
+                }) => {
+                    let model = self.config.model.clone();
+                    let msg = self.other_choices.entry(index).or_insert_with(|| {
+                        let mut msg =
+                            Message::new(Role::Assistant, MessageContent::Text(String::new()));
+                        msg.model = Some(model);
+                        msg
+                    });
+                    if let MessageContent::Text(text) = &mut msg.content {
+                        text.push_str(&delta);
+                    }
+                    if finish_reason.is_some() {
+                        msg.finish_reason = finish_reason;
+                    }
+                }
+                Ok(ApiMessage::Summary(summary)) => {
+                    if let Some(compaction) = self.compaction.take() {
+                        self.finish_compaction(compaction, summary);
+                    }
+                }
+                Ok(ApiMessage::Error(error)) if self.compaction.is_some() => {
+                    if let Some(compaction) = self.compaction.take() {
+                        self.compaction_failed(compaction, error.hint());
+                    }
+                    self.last_error = Some(error);
+                }
+                Ok(ApiMessage::Usage(usage)) => {
+                    let mut model = None;
+                    if self.is_streaming {
+                        if let Some(msg) = self.messages.last_mut() {
+                            msg.usage = Some(usage);
+                            model = msg.model.clone();
                         }
                     }
-                    self.api_receiver = None;
+                    // Billed whether or not the answer stays on the branch being shown
+                    let model = model.unwrap_or_else(|| self.config.model.clone());
+                    *self.usage.entry(model).or_default() += usage;
                 }
-                Err(mpsc::error::TryRecvError::Empty) => {}
-                Err(mpsc::error::TryRecvError::Disconnected) => {
+                Ok(ApiMessage::Done(_)) if !self.is_streaming => {
                     self.is_loading = false;
+                    self.restore_prompt();
+                    self.status_message =
+                        Some(("✗ No content received".to_string(), StatusType::Error));
+                    self.end_request();
+                    self.autosave();
+                }
+                Ok(ApiMessage::Done(finish_reason)) => {
+                    let run = self.candidates.take();
+                    self.finish_stream();
+                    if let Some(msg) = self.messages.last_mut() {
+                        msg.finish_reason = finish_reason.clone();
+                    }
+                    let versions = self.add_other_choices();
+                    self.status_message = if finish_reason.as_deref() == Some("length") {
+                        Some((
+                            "⚠ Response truncated: max_tokens reached".to_string(),
+                            StatusType::Warning,
+                        ))
+                    } else if let Some(warning) = self.turn_warning.take() {
+                        Some((warning, StatusType::Warning))
+                    } else if let Some((shown, versions)) = versions {
+                        Some((
+                            format!(
+                                "✓ Showing answer {}/{} - ←/→ to compare, /keep to drop the others",
+                                shown, versions
+                            ),
+                            StatusType::Success,
+                        ))
+                    } else {
+                        Some((
+                            "✓ Message sent successfully".to_string(),
+                            StatusType::Success,
+                        ))
+                    };
+                    self.end_request();
+                    self.autosave();
+                    if let Some(run) = run {
+                        self.next_candidate(run);
+                    }
+                }
+                Ok(ApiMessage::Retrying {
+                    attempt,
+                    max_retries,
+                    delay,
+                    error,
+                }) => {
+                    let reason = match error.status_code() {
+                        Some(429) => "Rate limited (429)".to_string(),
+                        Some(code) => format!("Server error ({})", code),
+                        None => "Request failed".to_string(),
+                    };
+                    self.retry_status = Some(RetryStatus {
+                        attempt,
+                        max_retries,
+                        resume_at: Instant::now() + delay,
+                        reason,
+                    });
+                    self.last_error = Some(error);
+                }
+                Ok(ApiMessage::ModelLoading { estimated_time }) => {
+                    let now = Instant::now();
+                    let started = self.model_loading.as_ref().map_or(now, |m| m.started);
+                    self.model_loading = Some(ModelLoadingStatus {
+                        started,
+                        ready_at: now + estimated_time,
+                    });
+                    self.status_message = Some((
+                        format!(
+                            "⏳ Model is loading on the endpoint - about {}s left (Esc to cancel)",
+                            estimated_time.as_secs()
+                        ),
+                        StatusType::Info,
+                    ));
+                }
+                Ok(ApiMessage::Error(error)) => {
+                    let hint = error.hint();
+                    let retries = match &self.retry_status {
+                        Some(retry) => format!(" (gave up after {} retries)", retry.attempt),
+                        None => String::new(),
+                    };
+                    let details = if error.raw_body().is_some() {
+                        " - press 'e' for details"
+                    } else {
+                        ""
+                    };
+                    if self.is_streaming {
+                        self.finish_stream();
+                        self.status_message = Some((
+                            format!("✗ {} (partial response kept){}", hint, details),
+                            StatusType::Error,
+                        ));
+                    } else {
+                        self.is_loading = false;
+                        self.restore_prompt();
+                        self.status_message = Some((
+                            format!("✗ {}{}{}", hint, retries, details),
+                            StatusType::Error,
+                        ));
+                    }
+                    self.last_error = Some(error);
+                    self.end_request();
+                    self.autosave();
+                }
+                Err(mpsc::error::TryRecvError::Empty) => break,
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    if !self.is_streaming {
+                        self.restore_prompt();
+                    }
+                    self.finish_stream();
                     self.status_message =
                         Some(("✗ API connection lost".to_string(), StatusType::Error));
-                    self.api_receiver = None;
+                    self.end_request();
+                    self.autosave();
                 }
             }
         }
     }
 
-    pub fn save_conversation(&self, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let json = serde_json::to_string_pretty(&self.messages)?;
-        fs::write(filename, json)?;
-        Ok(())
+    /// Drops all state tied to the request that just finished
+    fn end_request(&mut self) {
+        self.api_receiver = None;
+        self.request_handle = None;
+        self.compaction = None;
+        self.candidates = None;
+        self.other_choices.clear();
+        self.retry_status = None;
+        self.model_loading = None;
     }
 
-    pub fn load_conversation(&mut self, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let json = fs::read_to_string(filename)?;
-        self.messages = serde_json::from_str(&json)?;
-        for msg in &mut self.messages {
-            msg.timestamp = Instant::now();
+    /// Moves an unanswered trailing user message back into the input box so it can be resent,
+    /// and puts back the branch an edit or regeneration replaced
+    fn restore_prompt(&mut self) {
+        let pending = self.pending_branch.take();
+        let edited = pending
+            .as_ref()
+            .is_some_and(|pending| pending.at + 1 == self.messages.len());
+        let unanswered = pending.is_none() || edited;
+        if unanswered && self.messages.last().is_some_and(|m| m.role == Role::User) {
+            if let Some(Message {
+                content: MessageContent::Text(prompt),
+                ..
+            }) = self.messages.pop()
+            {
+                self.generation += 1;
+                if self.input.is_empty() {
+                    self.input = prompt;
+                }
+                self.input_mode = InputMode::Editing;
+            }
+        }
+        if let Some(pending) = pending {
+            if edited {
+                self.editing = Some(pending.at);
+            }
+            self.messages.truncate(pending.at);
+            self.generation += 1;
+            let PendingBranch {
+                mut siblings,
+                previous,
+                ..
+            } = pending;
+            let mut branch = siblings.remove(previous);
+            branch[0].siblings = siblings;
+            branch[0].branch = previous;
+            self.messages.extend(branch);
         }
-        Ok(())
     }
 
-    pub async fn handle_command(
-        &mut self,
-        command: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let parts: Vec<&str> = command.split_whitespace().collect();
-        let cmd = parts.first().unwrap_or(&"");
+    /// Moves the message at `at` and everything after it aside, so a new version of that
+    /// message can be added in its place. The new version becomes the last sibling once
+    /// its reply is in.
+    fn branch_off(&mut self, at: usize) {
+        let mut branch = self.messages.split_off(at);
+        self.generation += 1;
+        let mut siblings = std::mem::take(&mut branch[0].siblings);
+        let previous = branch[0].branch;
+        siblings.insert(previous, branch);
+        self.pending_branch = Some(PendingBranch {
+            at,
+            siblings,
+            previous,
+        });
+    }
 
-        match *cmd {
-            "help" | "h" => {
-                self.show_help = !self.show_help;
-                self.status_message = Some(("Help toggled".to_string(), StatusType::Info));
+    /// Makes the new version at the start of the pending branch a sibling of the others
+    fn commit_branch(&mut self) {
+        if let Some(pending) = self.pending_branch.take() {
+            if let Some(msg) = self.messages.get_mut(pending.at) {
+                msg.branch = pending.siblings.len();
+                msg.siblings = pending.siblings;
             }
-            "clear" | "c" => {
-                self.messages.clear();
-                self.scroll_offset = 0;
-                self.status_message =
-                    Some(("Conversation cleared".to_string(), StatusType::Success));
+        }
+    }
+
+    /// Shows another version of the message at `at`, together with the conversation that
+    /// followed it
+    pub fn switch_branch(&mut self, at: usize, to: usize) {
+        let Some(msg) = self.messages.get(at) else {
+            return;
+        };
+        if to == msg.branch || to >= msg.versions() {
+            return;
+        }
+        let mut branch = self.messages.split_off(at);
+        self.generation += 1;
+        let mut siblings = std::mem::take(&mut branch[0].siblings);
+        siblings.insert(branch[0].branch, branch);
+        let mut chosen = siblings.remove(to);
+        chosen[0].siblings = siblings;
+        chosen[0].branch = to;
+        self.messages.extend(chosen);
+    }
+
+    /// Steps through the versions of the nearest message at or above the focused one
+    /// that has any, e.g. from "< 2/3 >" to "< 3/3 >"
+    pub fn switch_sibling(&mut self, forward: bool) {
+        if self.is_loading {
+            return;
+        }
+        let Some(focused) = self.focused_message() else {
+            return;
+        };
+        let Some(at) = (0..=focused)
+            .rev()
+            .find(|&i| !self.messages[i].siblings.is_empty())
+        else {
+            self.status_message = Some((
+                "No other versions of this message - edit a prompt with 'E' to branch".to_string(),
+                StatusType::Info,
+            ));
+            return;
+        };
+        let msg = &self.messages[at];
+        let versions = msg.versions();
+        let to = if forward {
+            (msg.branch + 1) % versions
+        } else {
+            (msg.branch + versions - 1) % versions
+        };
+        self.switch_branch(at, to);
+        self.status_message = Some((
+            format!(
+                "Showing version {}/{} of message {}",
+                to + 1,
+                versions,
+                at + 1
+            ),
+            StatusType::Info,
+        ));
+        self.autosave();
+    }
+
+    /// Puts the nearest prompt at or above the focused message into the input box; sending
+    /// it starts a new branch there and keeps the old one as a sibling
+    pub fn start_edit(&mut self) {
+        if self.is_loading {
+            return;
+        }
+        let Some(focused) = self.focused_message() else {
+            return;
+        };
+        let Some(at) = (0..=focused)
+            .rev()
+            .find(|&i| self.messages[i].role == Role::User)
+        else {
+            self.status_message = Some((
+                "No prompt to edit at or above this message".to_string(),
+                StatusType::Warning,
+            ));
+            return;
+        };
+        self.input = self.messages[at].content.output().to_string();
+        self.input_mode = InputMode::Editing;
+        self.editing = Some(at);
+        self.status_message = Some((
+            format!(
+                "✎ Editing message {} - Enter sends it as a new branch, Esc cancels",
+                at + 1
+            ),
+            StatusType::Info,
+        ));
+    }
+
+    pub fn cancel_edit(&mut self) {
+        self.editing = None;
+        self.input.clear();
+        self.input_mode = InputMode::Normal;
+        self.status_message = Some(("Edit cancelled".to_string(), StatusType::Info));
+    }
+
+    /// Asks again for the answer to the last prompt, `count` times. The answers already
+    /// there are kept, so all of them can be compared with ←/→ before going on.
+    pub fn retry(&mut self, count: usize, params: SamplingParams) {
+        if self.is_loading {
+            self.status_message = Some((
+                "⚠ Wait for the current response before retrying".to_string(),
+                StatusType::Warning,
+            ));
+            return;
+        }
+        let Some(prompt) = self.messages.iter().rposition(|m| m.role == Role::User) else {
+            self.status_message = Some(("Nothing to retry yet".to_string(), StatusType::Warning));
+            return;
+        };
+        self.editing = None;
+        self.start_candidate(CandidateRun {
+            at: prompt + 1,
+            done: 0,
+            count,
+            params,
+        });
+    }
+
+    /// Requests the next answer of a run: a sibling of the answer at `run.at`, or the
+    /// first answer when the prompt has none yet
+    fn start_candidate(&mut self, run: CandidateRun) {
+        let (at, number, count) = (run.at, run.done + 1, run.count);
+        // Set first so the request goes out with the run's parameters
+        self.candidates = Some(run);
+        if at < self.messages.len() {
+            self.regenerate(at);
+            if !self.is_loading {
+                self.candidates = None;
+                self.status_message = Some((
+                    format!("⚠ Cannot retry: message {} is not an answer", at + 1),
+                    StatusType::Warning,
+                ));
+                return;
             }
-            "stats" | "s" => {
-                let total = self.messages.len();
-                let user_count = self.messages.iter().filter(|m| m.role == Role::User).count();
-                let assistant_count = self
-                    .messages
-                    .iter()
-                    .filter(|m| m.role == Role::Assistant)
-                    .count();
-                let total_chars: usize = self
-                    .messages
+        } else {
+            self.is_loading = true;
+            self.turn_warning = None;
+            self.scroll_to_bottom();
+            self.start_request();
+        }
+        self.status_message = Some((
+            if count > 1 {
+                format!("⟳ Generating answer {}/{}...", number, count)
+            } else {
+                "⟳ Generating another answer...".to_string()
+            },
+            StatusType::Info,
+        ));
+    }
+
+    /// Continues a `/retry` run after one of its answers came in
+    fn next_candidate(&mut self, mut run: CandidateRun) {
+        run.done += 1;
+        if run.done < run.count {
+            self.start_candidate(run);
+            return;
+        }
+        if let Some(answer) = self.messages.get(run.at) {
+            if answer.versions() > 1 {
+                self.status_message = Some((
+                    format!(
+                        "✓ Showing answer {}/{} - ←/→ to compare, /keep to drop the others",
+                        answer.branch + 1,
+                        answer.versions()
+                    ),
+                    StatusType::Success,
+                ));
+            }
+        }
+    }
+
+    /// Drops every other version of the nearest message at or above the focused one that
+    /// has any, leaving only the version shown
+    pub fn keep_branch(&mut self) {
+        if self.is_loading {
+            return;
+        }
+        let Some(focused) = self.focused_message() else {
+            return;
+        };
+        let Some(at) = (0..=focused)
+            .rev()
+            .find(|&i| !self.messages[i].siblings.is_empty())
+        else {
+            self.status_message = Some((
+                "Nothing to drop - this message has no other versions".to_string(),
+                StatusType::Info,
+            ));
+            return;
+        };
+        let msg = &mut self.messages[at];
+        let dropped = msg.siblings.len();
+        msg.siblings.clear();
+        msg.branch = 0;
+        self.status_message = Some((
+            format!(
+                "✓ Kept the version shown of message {}, dropped {} others",
+                at + 1,
+                dropped
+            ),
+            StatusType::Success,
+        ));
+        self.autosave();
+    }
+
+    /// Asks for a new version of the assistant message at `at`, from the conversation
+    /// before it; the current version is kept as a sibling
+    pub fn regenerate(&mut self, at: usize) {
+        if self.is_loading
+            || self
+                .messages
+                .get(at)
+                .is_none_or(|msg| msg.role != Role::Assistant)
+        {
+            return;
+        }
+        self.branch_off(at);
+        self.is_loading = true;
+        self.turn_warning = None;
+        self.scroll_to_bottom();
+        self.status_message = Some(("Regenerating answer...".to_string(), StatusType::Info));
+        self.start_request();
+    }
+
+    /// Appends streamed text to the in-progress assistant message, creating it on the first delta
+    fn append_stream_delta(&mut self, delta: &str) {
+        if !self.is_streaming {
+            self.add_message(Role::Assistant, String::new());
+            if let Some(msg) = self.messages.last_mut() {
+                msg.model = Some(self.config.model.clone());
+            }
+            self.is_streaming = true;
+            self.status_message = Some((
+                "Receiving response... (Esc to cancel)".to_string(),
+                StatusType::Info,
+            ));
+        }
+
+        if let Some(Message {
+            content: MessageContent::Text(text),
+            ..
+        }) = self.messages.last_mut()
+        {
+            text.push_str(delta);
+        }
+    }
+
+    /// Ends the current turn and re-parses the streamed text for thinking tokens
+    fn finish_stream(&mut self) {
+        if self.is_streaming {
+            self.commit_branch();
+            if let Some(msg) = self.messages.last_mut() {
+                if let MessageContent::Text(text) = &msg.content {
+                    msg.content = parse_thinking_tokens(text);
+                }
+                msg.latency_ms = self
+                    .request_started
+                    .map(|started| started.elapsed().as_millis() as u64);
+            }
+        }
+        self.is_loading = false;
+        self.is_streaming = false;
+    }
+
+    /// Turns the other answers to a request with `n > 1` into siblings of the answer just
+    /// streamed, returning its position among all versions and their number
+    fn add_other_choices(&mut self) -> Option<(usize, usize)> {
+        let others = std::mem::take(&mut self.other_choices);
+        if others.is_empty() {
+            return None;
+        }
+        let latency_ms = self
+            .request_started
+            .map(|started| started.elapsed().as_millis() as u64);
+        let answer = self
+            .messages
+            .last_mut()
+            .filter(|msg| msg.role == Role::Assistant)?;
+        for mut msg in others.into_values() {
+            if let MessageContent::Text(text) = &msg.content {
+                msg.content = parse_thinking_tokens(text);
+            }
+            msg.latency_ms = latency_ms;
+            answer.siblings.push(vec![msg]);
+        }
+        Some((answer.branch + 1, answer.versions()))
+    }
+
+    /// Opens the model picker and fetches the endpoint's model list in the background
+    pub fn open_model_picker(&mut self) {
+        let (tx, rx) = oneshot::channel();
+        let backend = self.backend.clone();
+        tokio::spawn(async move {
+            let _ = tx.send(backend.list_models().await);
+        });
+
+        self.model_picker = Some(ModelPicker {
+            models: None,
+            filter: String::new(),
+            selected: 0,
+            receiver: Some(rx),
+        });
+        self.status_message = Some((
+            "Type to filter, Enter to switch, Esc to close".to_string(),
+            StatusType::Info,
+        ));
+    }
+
+    /// Fills the model picker once the endpoint has answered
+    pub fn poll_model_list(&mut self) {
+        let Some(picker) = &mut self.model_picker else {
+            return;
+        };
+        let Some(receiver) = &mut picker.receiver else {
+            return;
+        };
+
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(oneshot::error::TryRecvError::Empty) => return,
+            Err(oneshot::error::TryRecvError::Closed) => Err(ApiError::Transport(
+                "model listing was interrupted".to_string(),
+            )),
+        };
+        picker.receiver = None;
+
+        match result {
+            Ok(mut models) => {
+                models.sort();
+                picker.selected = models
                     .iter()
-                    .map(|m| match &m.content {
-                        MessageContent::Text(t) => t.len(),
-                        MessageContent::WithThinking { thinking, output } => {
-                            thinking.len() + output.len()
-                        }
-                    })
-                    .sum();
-                let estimated_tokens = self.estimate_tokens(&total_chars.to_string()) * total;
+                    .position(|model| *model == self.config.model)
+                    .unwrap_or(0);
+                picker.models = Some(models);
+            }
+            Err(error) => {
+                // Endpoints without a model listing can still be switched by typing a name
+                picker.models = Some(Vec::new());
                 self.status_message = Some((
                     format!(
-                        "Messages: {} (U:{} A:{}) | ~{} tokens",
-                        total, user_count, assistant_count, estimated_tokens
+                        "⚠ Could not list models: {} - type a model name and press Enter",
+                        error.hint()
                     ),
-                    StatusType::Info,
+                    StatusType::Warning,
                 ));
+                self.last_error = Some(error);
             }
+        }
+    }
+
+    /// Switches to the model highlighted in the picker
+    pub fn confirm_model_picker(&mut self) {
+        let Some(model) = self.model_picker.as_ref().and_then(ModelPicker::choice) else {
+            return;
+        };
+        self.model_picker = None;
+        self.switch_model(model);
+    }
+
+    /// Uses `model` for all following requests
+    pub fn switch_model(&mut self, model: String) {
+        if self.is_loading {
+            self.status_message = Some((
+                "⚠ Wait for the current response before switching models".to_string(),
+                StatusType::Warning,
+            ));
+            return;
+        }
+        self.status_message = Some((
+            format!("✓ Switched model to {}", model),
+            StatusType::Success,
+        ));
+        self.config.model = model;
+    }
+
+    /// Switches endpoint, model and parameters to profile `name`, keeping the conversation
+    pub fn switch_profile(&mut self, name: &str) {
+        if self.is_loading {
+            self.status_message = Some((
+                "⚠ Wait for the current response before switching profiles".to_string(),
+                StatusType::Warning,
+            ));
+            return;
+        }
+
+        let config = match self.base_config.with_profile(name) {
+            Ok(config) => config,
+            Err(e) => {
+                self.status_message = Some((format!("✗ {}", e), StatusType::Error));
+                return;
+            }
+        };
+        let token_counter = match TokenCounter::from_config(&config) {
+            Ok(token_counter) => token_counter,
+            Err(e) => {
+                self.status_message = Some((format!("✗ {}", e), StatusType::Error));
+                return;
+            }
+        };
+        match backend::from_config(&config) {
+            Ok(backend) => {
+                self.status_message = Some((
+                    format!(
+                        "✓ Switched to profile {} ({} via {})",
+                        name,
+                        config.model,
+                        backend.name()
+                    ),
+                    StatusType::Success,
+                ));
+                self.backend = backend;
+                self.config = config;
+                self.token_counter = token_counter;
+                self.token_cache.borrow_mut().clear();
+            }
+            Err(e) => {
+                self.status_message = Some((format!("✗ {}", e.hint()), StatusType::Error));
+            }
+        }
+    }
+
+    /// The conversation as it is written to disk
+    fn session(&self) -> Session {
+        let created = self
+            .session_created
+            .or(self.messages.first().map(|m| m.datetime))
+            .unwrap_or_else(Local::now);
+        Session {
+            version: SESSION_FORMAT_VERSION,
+            title: self.session_title.clone(),
+            model: Some(self.config.model.clone()),
+            params: Some(self.config.params.clone()),
+            created: Some(created),
+            updated: Some(Local::now()),
+            usage: self.usage.clone(),
+            messages: self.messages.clone(),
+        }
+    }
+
+    pub fn save_conversation(&self, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let json = serde_json::to_string_pretty(&self.session())?;
+        fs::write(filename, json)?;
+        Ok(())
+    }
+
+    /// Writes the conversation for reading elsewhere; it cannot be loaded back
+    pub fn export_conversation(
+        &self,
+        filename: &str,
+        format: ExportFormat,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.messages.is_empty() {
+            return Err("the conversation is empty".into());
+        }
+        fs::write(filename, export::export(&self.session(), format))?;
+        Ok(())
+    }
+
+    /// Loads a conversation file; autosave keeps it as a new session, leaving the file alone
+    pub fn load_conversation(&mut self, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let session = sessions::read_session(Path::new(filename))?;
+        self.replace_conversation(session);
+        self.session_id = None;
+        Ok(())
+    }
+
+    fn replace_conversation(&mut self, session: Session) {
+        if let Some(params) = session.params {
+            self.config.params = params;
+        }
+        self.messages = session.messages;
+        self.generation += 1;
+        self.session_title = session.title;
+        self.session_created = session.created;
+        self.usage = session.usage;
+        self.editing = None;
+        self.pending_branch = None;
+        for msg in &mut self.messages {
+            msg.timestamp = Instant::now();
+        }
+        self.scroll_to_bottom();
+    }
+
+    /// Writes the conversation to the session store, giving it an ID on first save.
+    /// Nothing is stored until the user has said something.
+    pub fn save_session(&mut self) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let Some(store) = &self.session_store else {
+            return Err("no sessions directory; set sessions_dir".into());
+        };
+        if !self.messages.iter().any(|m| m.role == Role::User) {
+            return Ok(None);
+        }
+        let id = self
+            .session_id
+            .get_or_insert_with(SessionStore::new_id)
+            .clone();
+        store.save(&id, &self.session())?;
+        Ok(Some(id))
+    }
+
+    /// Saves the session after every turn unless `autosave` is off
+    pub fn autosave(&mut self) {
+        if !self.config.autosave {
+            return;
+        }
+        if let Err(e) = self.save_session() {
+            self.status_message = Some((format!("⚠ Autosave failed: {}", e), StatusType::Warning));
+        }
+    }
+
+    /// Replaces the conversation with a stored session, which later turns keep saving to
+    pub fn open_session(&mut self, id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(store) = &self.session_store else {
+            return Err("no sessions directory; set sessions_dir".into());
+        };
+        let session = store.load(id)?;
+        let title = sessions::title_of(&session);
+        self.replace_conversation(session);
+        self.session_id = Some(id.to_string());
+        self.status_message = Some((
+            format!(
+                "✓ Opened session {} ({} messages)",
+                title,
+                self.messages.len()
+            ),
+            StatusType::Success,
+        ));
+        Ok(())
+    }
+
+    /// Lists the stored sessions in a popup, most recent first
+    pub fn open_session_browser(&mut self) {
+        let Some(store) = &self.session_store else {
+            self.status_message = Some((
+                "✗ No sessions directory; set sessions_dir".to_string(),
+                StatusType::Error,
+            ));
+            return;
+        };
+        let sessions = store.list();
+        let selected = self
+            .session_id
+            .as_ref()
+            .and_then(|id| sessions.iter().position(|info| &info.id == id))
+            .unwrap_or(0);
+        self.session_browser = Some(SessionBrowser {
+            sessions,
+            selected,
+            renaming: None,
+            confirm_delete: false,
+        });
+    }
+
+    pub fn confirm_session_browser(&mut self) {
+        if self.is_loading {
+            self.status_message = Some((
+                "⚠ Wait for the current response before opening a session".to_string(),
+                StatusType::Warning,
+            ));
+            return;
+        }
+        let Some(id) = self
+            .session_browser
+            .as_ref()
+            .and_then(|browser| browser.selected())
+            .map(|info| info.id.clone())
+        else {
+            return;
+        };
+        self.session_browser = None;
+        if let Err(e) = self.open_session(&id) {
+            self.status_message = Some((
+                format!("✗ Cannot open session {}: {}", id, e),
+                StatusType::Error,
+            ));
+        }
+    }
+
+    /// Gives the selected session the title typed in the browser; an empty title
+    /// goes back to the first prompt
+    pub fn rename_selected_session(&mut self) {
+        let (Some(store), Some(browser)) = (&self.session_store, &mut self.session_browser) else {
+            return;
+        };
+        let title = browser.renaming.take().unwrap_or_default();
+        let Some(info) = browser.sessions.get(browser.selected) else {
+            return;
+        };
+        let id = info.id.clone();
+        if let Err(e) = store.rename(&id, &title) {
+            self.status_message = Some((format!("✗ Rename failed: {}", e), StatusType::Error));
+            return;
+        }
+        if self.session_id.as_ref() == Some(&id) {
+            self.session_title = Some(title.trim().to_string()).filter(|t| !t.is_empty());
+        }
+        browser.sessions = store.list();
+        browser.selected = browser
+            .sessions
+            .iter()
+            .position(|info| info.id == id)
+            .unwrap_or(0);
+        self.status_message = Some(("✓ Session renamed".to_string(), StatusType::Success));
+    }
+
+    /// Deletes the selected session from disk. The open conversation is kept and saved
+    /// again as a new session if it was the one deleted.
+    pub fn delete_selected_session(&mut self) {
+        let (Some(store), Some(browser)) = (&self.session_store, &mut self.session_browser) else {
+            return;
+        };
+        browser.confirm_delete = false;
+        let Some(info) = browser.sessions.get(browser.selected) else {
+            return;
+        };
+        let (id, title) = (info.id.clone(), info.title.clone());
+        if let Err(e) = store.delete(&id) {
+            self.status_message = Some((format!("✗ Delete failed: {}", e), StatusType::Error));
+            return;
+        }
+        browser.sessions.remove(browser.selected);
+        browser.selected = browser
+            .selected
+            .min(browser.sessions.len().saturating_sub(1));
+        if self.session_id.as_ref() == Some(&id) {
+            self.session_id = None;
+        }
+        self.status_message = Some((format!("✓ Deleted session {}", title), StatusType::Success));
+    }
+
+    pub async fn handle_command(
+        &mut self,
+        command: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        let cmd = parts.first().unwrap_or(&"");
+
+        match *cmd {
+            "help" | "h" => {
+                self.toggle_help();
+                self.status_message = Some(("Help toggled".to_string(), StatusType::Info));
+            }
+            "error" | "e" => match self.last_error {
+                Some(_) => self.show_error_details = !self.show_error_details,
+                None => {
+                    self.status_message =
+                        Some(("No API error to show".to_string(), StatusType::Info));
+                }
+            },
+            "clear" | "c" if self.wait_for_response("clearing") => {}
+            "clear" | "c" => {
+                self.messages.clear();
+                self.generation += 1;
+                self.scroll_offset = 0;
+                self.editing = None;
+                // The old conversation stays in the store; what follows is a new session
+                self.session_id = None;
+                self.session_title = None;
+                self.session_created = None;
+                self.usage.clear();
+                self.status_message =
+                    Some(("Conversation cleared".to_string(), StatusType::Success));
+            }
+            "stats" | "s" => {
+                let total = self.messages.len();
+                let user_count = self.messages.iter().filter(|m| m.role == Role::User).count();
+                let assistant_count = self
+                    .messages
+                    .iter()
+                    .filter(|m| m.role == Role::Assistant)
+                    .count();
+                let plan = self.context_plan();
+                let approx = if self.token_counter.is_exact() {
+                    ""
+                } else {
+                    "~"
+                };
+                let mut stats = format!(
+                    "Messages: {} (U:{} A:{}) | context {}{} / {} tokens",
+                    total, user_count, assistant_count, approx, plan.total, plan.budget
+                );
+                if !plan.dropped.is_empty() {
+                    stats.push_str(&format!(" ({} messages dropped)", plan.dropped.len()));
+                }
+                if !plan.excluded.is_empty() {
+                    stats.push_str(&format!(" ({} excluded)", plan.excluded.len()));
+                }
+
+                let by_model = self.usage_by_model().clone();
+                let (usage, cost) = self.session_usage();
+                if usage.total() > 0 {
+                    stats.push_str(&format!(
+                        " | used {} in / {} out",
+                        usage.prompt_tokens, usage.completion_tokens
+                    ));
+                    if let Some(cost) = cost {
+                        stats.push_str(&format!(" (${:.4})", cost));
+                    }
+                }
+                if by_model.len() > 1 {
+                    for (model, usage) in by_model {
+                        stats.push_str(&format!(
+                            " | {}: {}/{}",
+                            model, usage.prompt_tokens, usage.completion_tokens
+                        ));
+                    }
+                }
+                self.status_message = Some((stats, StatusType::Info));
+            }
+            "compact" => match parts.get(1).copied() {
+                Some("on") => {
+                    self.config.compaction = true;
+                    self.status_message = Some((
+                        "✓ Compaction on - messages that no longer fit are summarised".to_string(),
+                        StatusType::Success,
+                    ));
+                }
+                Some("off") => {
+                    self.config.compaction = false;
+                    self.status_message = Some((
+                        "✓ Compaction off - messages that no longer fit are dropped".to_string(),
+                        StatusType::Success,
+                    ));
+                }
+                Some(_) => {
+                    self.status_message =
+                        Some(("Usage: /compact [on|off]".to_string(), StatusType::Warning));
+                }
+                None if self.is_loading => {
+                    self.status_message = Some((
+                        "⚠ Wait for the current response before compacting".to_string(),
+                        StatusType::Warning,
+                    ));
+                }
+                None => {
+                    // Everything since the system prompt or the latest summary, except the
+                    // last exchange
+                    let start = match self.messages.iter().rposition(|m| m.summarizes.is_some()) {
+                        Some(summary) => summary + 1,
+                        None => usize::from(
+                            self.messages
+                                .first()
+                                .is_some_and(|m| m.role == Role::System),
+                        ),
+                    };
+                    let end = self.messages.len().saturating_sub(2);
+                    if end > start {
+                        self.start_compaction(start..end, false);
+                    } else {
+                        self.status_message =
+                            Some(("Nothing to compact yet".to_string(), StatusType::Info));
+                    }
+                }
+            },
+            "model" | "m" => match parts.get(1) {
+                Some(model) => self.switch_model(model.to_string()),
+                None => self.open_model_picker(),
+            },
+            "profile" => match parts.get(1) {
+                Some(name) => self.switch_profile(name),
+                None if self.base_config.profiles.is_empty() => {
+                    self.status_message = Some((
+                        "No profiles configured - add [profiles.<name>] to your config file"
+                            .to_string(),
+                        StatusType::Info,
+                    ));
+                }
+                None => {
+                    let names: Vec<String> = self
+                        .base_config
+                        .profiles
+                        .keys()
+                        .map(|name| {
+                            if self.config.profile.as_deref() == Some(name.as_str()) {
+                                format!("{}*", name)
+                            } else {
+                                name.clone()
+                            }
+                        })
+                        .collect();
+                    self.status_message = Some((
+                        format!("Profiles: {} - /profile <name> to switch", names.join(", ")),
+                        StatusType::Info,
+                    ));
+                }
+            },
+            "set" => {
+                let args = command.trim_start()[cmd.len()..].trim_start();
+                match args.split_once(char::is_whitespace) {
+                    Some((key, value)) => match self.config.params.set(key, value) {
+                        Ok(()) => {
+                            let shown = self
+                                .config
+                                .params
+                                .get(key)
+                                .unwrap_or_else(|| "server default".to_string());
+                            self.status_message =
+                                Some((format!("✓ {} = {}", key, shown), StatusType::Success));
+                        }
+                        Err(e) => {
+                            self.status_message =
+                                Some((format!("✗ /set {}: {}", key, e), StatusType::Error));
+                        }
+                    },
+                    None => {
+                        self.status_message = Some((
+                            format!(
+                                "Usage: /set <key> <value|none> - keys: {}",
+                                SamplingParams::KEYS.join(", ")
+                            ),
+                            StatusType::Warning,
+                        ));
+                    }
+                }
+            }
+            "params" | "p" => self.show_params = !self.show_params,
+            "context" | "ctx" => self.show_context = !self.show_context,
+            "sessions" => self.open_session_browser(),
+            "retry" | "r" => {
+                let mut count = 1;
+                let mut params = self.config.params.clone();
+                for arg in &parts[1..] {
+                    let parsed = match arg.split_once('=') {
+                        None | Some(("n", _)) => arg
+                            .trim_start_matches("n=")
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|n| (1..=MAX_CANDIDATES).contains(n))
+                            .map(|n| count = n)
+                            .ok_or(format!("answers must be 1 to {}", MAX_CANDIDATES)),
+                        Some((key, value)) => params.set(key, value),
+                    };
+                    if let Err(e) = parsed {
+                        self.status_message = Some((
+                            format!(
+                                "✗ /retry {}: {} - usage: /retry [n] [key=value ...]",
+                                arg, e
+                            ),
+                            StatusType::Error,
+                        ));
+                        return Ok(());
+                    }
+                }
+                self.retry(count, params);
+            }
+            "keep" => self.keep_branch(),
             "save" => {
                 let filename = parts.get(1).unwrap_or(&"conversation.json");
                 match self.save_conversation(filename) {
@@ -342,6 +1869,72 @@ impl App {
                     }
                 }
             }
+            "import" if self.wait_for_response("importing") => {}
+            "import" => {
+                let index = match parts.get(2) {
+                    Some(n) => n.parse::<usize>().ok().filter(|&n| n >= 1),
+                    None => Some(1),
+                };
+                let (Some(filename), Some(index)) = (parts.get(1), index) else {
+                    self.status_message = Some((
+                        "Usage: /import <file> [n] - HF chat-ui, ChatGPT or OpenAI messages JSON"
+                            .to_string(),
+                        StatusType::Warning,
+                    ));
+                    return Ok(());
+                };
+                match import::import_file(Path::new(filename), index) {
+                    Ok(imported) => {
+                        let mut status = format!(
+                            "✓ Imported {} messages from {}",
+                            imported.session.messages.len(),
+                            imported.format
+                        );
+                        if imported.conversations > 1 {
+                            status.push_str(&format!(
+                                " (conversation {} of {}, newest first; /import {} <n> for another)",
+                                index, imported.conversations, filename
+                            ));
+                        }
+                        if let Some(skipped) = imported.skipped_summary() {
+                            status.push_str(&format!(" - skipped {}", skipped));
+                        }
+                        self.replace_conversation(imported.session);
+                        self.session_id = None;
+                        self.status_message = Some((status, StatusType::Success));
+                    }
+                    Err(e) => {
+                        self.status_message =
+                            Some((format!("Failed to import: {}", e), StatusType::Error));
+                    }
+                }
+            }
+            "export" => {
+                let Some(format) = parts.get(1).and_then(|name| ExportFormat::parse(name)) else {
+                    self.status_message = Some((
+                        "Usage: /export md|html [file]".to_string(),
+                        StatusType::Warning,
+                    ));
+                    return Ok(());
+                };
+                let filename = parts
+                    .get(2)
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|| format!("conversation.{}", format.extension()));
+                match self.export_conversation(&filename, format) {
+                    Ok(_) => {
+                        self.status_message = Some((
+                            format!("Exported conversation to {}", filename),
+                            StatusType::Success,
+                        ));
+                    }
+                    Err(e) => {
+                        self.status_message =
+                            Some((format!("Failed to export: {}", e), StatusType::Error));
+                    }
+                }
+            }
+            "load" if self.wait_for_response("loading a conversation") => {}
             "load" => {
                 let filename = parts.get(1).unwrap_or(&"conversation.json");
                 match self.load_conversation(filename) {
@@ -367,6 +1960,89 @@ impl App {
         Ok(())
     }
 
+    /// Commands that replace the conversation wait for the request in flight, whose answer
+    /// or summary would otherwise land in the new one. Returns whether it had to wait.
+    fn wait_for_response(&mut self, before: &str) -> bool {
+        if self.is_loading {
+            self.status_message = Some((
+                format!(
+                    "⚠ Wait for the current response before {}, or cancel it with Esc in normal mode",
+                    before
+                ),
+                StatusType::Warning,
+            ));
+        }
+        self.is_loading
+    }
+
+    /// The message pin and exclude act on: the one at the top of the view when scrolled,
+    /// otherwise the newest
+    pub fn focused_message(&self) -> Option<usize> {
+        let last = self.messages.len().checked_sub(1)?;
+        Some(match self.scroll_state {
+            ScrollState::Bottom => last,
+            ScrollState::Fixed(_) => self.scroll_offset.min(last),
+        })
+    }
+
+    /// Pins the focused message so it is always sent, or unpins it
+    pub fn toggle_pinned(&mut self) {
+        let Some(index) = self.focused_message() else {
+            return;
+        };
+        let total = self.messages.len();
+        let msg = &mut self.messages[index];
+        msg.pinned = !msg.pinned;
+        msg.excluded = false;
+        self.status_message = Some(if msg.pinned {
+            (
+                format!("📌 Message {}/{} pinned: always sent", index + 1, total),
+                StatusType::Success,
+            )
+        } else {
+            (
+                format!("Message {}/{} unpinned", index + 1, total),
+                StatusType::Info,
+            )
+        });
+        self.autosave();
+    }
+
+    /// Excludes the focused message from every request, or includes it again
+    pub fn toggle_excluded(&mut self) {
+        let Some(index) = self.focused_message() else {
+            return;
+        };
+        let total = self.messages.len();
+        let msg = &mut self.messages[index];
+        msg.excluded = !msg.excluded;
+        msg.pinned = false;
+        self.status_message = Some(if msg.excluded {
+            (
+                format!("⊘ Message {}/{} excluded: never sent", index + 1, total),
+                StatusType::Success,
+            )
+        } else {
+            (
+                format!("Message {}/{} included again", index + 1, total),
+                StatusType::Info,
+            )
+        });
+        self.autosave();
+    }
+
+    /// Opens the help popup at the top, or closes it
+    pub fn toggle_help(&mut self) {
+        self.show_help = !self.show_help;
+        self.help_scroll = 0;
+    }
+
+    /// Scrolls the help popup by `lines`, negative to go up
+    pub fn scroll_help(&mut self, lines: i32) {
+        let max = i32::from(self.help_max_scroll.get());
+        self.help_scroll = (i32::from(self.help_scroll) + lines).clamp(0, max) as u16;
+    }
+
     pub fn scroll_up(&mut self) {
         self.scroll_state = match self.scroll_state {
             ScrollState::Bottom => ScrollState::Fixed(self.scroll_offset),
@@ -392,7 +2068,7 @@ impl App {
     }
 }
 
-fn parse_thinking_tokens(content: &str) -> MessageContent {
+pub(crate) fn parse_thinking_tokens(content: &str) -> MessageContent {
     let thinking_regex = Regex::new(
         r"(?s)(<thinking>.*?</thinking>|\[THINKING\].*?\[/THINKING\]|🤔\s*Thinking:.*?(?:\n\n|$))",
     )
@@ -433,12 +2109,579 @@ impl From<MessageRole> for Role {
     }
 }
 
-impl Into<MessageRole> for Role {
-    fn into(self) -> MessageRole {
-        match self {
+impl From<Role> for MessageRole {
+    fn from(role: Role) -> Self {
+        match role {
             Role::User => MessageRole::user,
             Role::Assistant => MessageRole::assistant,
             Role::System => MessageRole::system,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{stub_server, Reply};
+    use std::sync::Mutex;
+
+    fn app() -> App {
+        App::headless(ChatConfig {
+            autosave: false,
+            ..ChatConfig::default()
+        })
+        .unwrap()
+    }
+
+    fn text(role: Role, text: &str) -> Message {
+        Message::new(role, MessageContent::Text(text.to_string()))
+    }
+
+    /// Hands `messages` to the app as if they came from the request in flight
+    fn receive(app: &mut App, messages: Vec<ApiMessage>) -> mpsc::UnboundedSender<ApiMessage> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        for msg in messages {
+            tx.send(msg).unwrap();
+        }
+        app.api_receiver = Some(rx);
+        app.process_api_response();
+        tx
+    }
+
+    fn usage(prompt_tokens: u64, completion_tokens: u64) -> Usage {
+        Usage {
+            prompt_tokens,
+            completion_tokens,
+        }
+    }
+
+    fn outputs(messages: &[Message]) -> Vec<&str> {
+        messages.iter().map(|m| m.content.output()).collect()
+    }
+
+    fn answered(app: &mut App, answer: &str) {
+        let _tx = receive(
+            app,
+            vec![
+                ApiMessage::Delta(answer.to_string()),
+                ApiMessage::Done(Some("stop".to_string())),
+            ],
+        );
+    }
+
+    #[tokio::test]
+    async fn regenerating_keeps_the_old_answer_as_a_sibling() {
+        let mut app = app();
+        app.messages.push(text(Role::User, "question"));
+        app.messages.push(text(Role::Assistant, "one"));
+
+        app.regenerate(1);
+        assert_eq!(outputs(&app.messages), ["question"]);
+        answered(&mut app, "two");
+        assert_eq!(outputs(&app.messages), ["question", "two"]);
+        assert_eq!(app.messages[1].versions(), 2);
+        assert_eq!(app.messages[1].branch, 1);
+        assert!(app.pending_branch.is_none());
+
+        app.switch_branch(1, 0);
+        assert_eq!(outputs(&app.messages), ["question", "one"]);
+        assert_eq!(app.messages[1].branch, 0);
+        assert_eq!(outputs(&app.messages[1].siblings[0]), ["two"]);
+
+        app.switch_sibling(true);
+        assert_eq!(outputs(&app.messages), ["question", "two"]);
+    }
+
+    #[tokio::test]
+    async fn a_failed_regeneration_puts_the_old_answer_back() {
+        let mut app = app();
+        app.messages.push(text(Role::User, "question"));
+        app.messages.push(text(Role::Assistant, "one"));
+
+        app.regenerate(1);
+        let _tx = receive(
+            &mut app,
+            vec![ApiMessage::Error(ApiError::Transport(
+                "refused".to_string(),
+            ))],
+        );
+        assert_eq!(outputs(&app.messages), ["question", "one"]);
+        assert!(app.messages[1].siblings.is_empty());
+        assert!(app.input.is_empty());
+    }
+
+    #[tokio::test]
+    async fn sending_an_edited_prompt_branches_off_there() {
+        let mut app = app();
+        for (role, content) in [
+            (Role::User, "first"),
+            (Role::Assistant, "one"),
+            (Role::User, "second"),
+            (Role::Assistant, "two"),
+        ] {
+            app.messages.push(text(role, content));
+        }
+
+        app.scroll_state = ScrollState::Fixed(1);
+        app.scroll_offset = 1;
+        app.start_edit();
+        assert_eq!(app.editing, Some(0));
+        assert_eq!(app.input, "first");
+        app.input = "first, reworded".to_string();
+        app.send_message().await.unwrap();
+        answered(&mut app, "three");
+
+        assert_eq!(outputs(&app.messages), ["first, reworded", "three"]);
+        assert_eq!(app.messages[0].branch, 1);
+        assert_eq!(
+            outputs(&app.messages[0].siblings[0]),
+            ["first", "one", "second", "two"]
+        );
+
+        // /keep drops the versions not shown
+        app.scroll_state = ScrollState::Bottom;
+        app.keep_branch();
+        assert!(app.messages[0].siblings.is_empty());
+        assert_eq!(app.messages[0].branch, 0);
+    }
+
+    #[tokio::test]
+    async fn retry_collects_every_answer() {
+        let mut app = app();
+        app.messages.push(text(Role::User, "question"));
+        app.messages.push(text(Role::Assistant, "one"));
+
+        app.retry(2, app.config.params.clone());
+        answered(&mut app, "two");
+        answered(&mut app, "three");
+        assert!(!app.is_loading);
+        assert_eq!(outputs(&app.messages), ["question", "three"]);
+        assert_eq!(app.messages[1].versions(), 3);
+        assert_eq!(outputs(&app.messages[1].siblings[0]), ["one"]);
+        assert_eq!(outputs(&app.messages[1].siblings[1]), ["two"]);
+    }
+
+    #[test]
+    fn the_draft_replaces_the_prompt_being_edited() {
+        let mut app = app();
+        for (role, content) in [
+            (Role::User, "first"),
+            (Role::Assistant, "one"),
+            (Role::User, "second"),
+            (Role::Assistant, "two"),
+        ] {
+            app.messages.push(text(role, content));
+        }
+
+        let (messages, plan, draft) = app.draft_context_plan();
+        assert!(!draft);
+        assert_eq!(messages.len(), 4);
+        assert_eq!(plan.included, vec![0, 1, 2, 3]);
+
+        app.input = "second, reworded".to_string();
+        app.editing = Some(2);
+        let (messages, plan, draft) = app.draft_context_plan();
+        assert!(draft);
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[2].content.output(), "second, reworded");
+        assert_eq!(plan.included, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn token_counts_follow_changed_messages() {
+        let mut app = app();
+        app.messages.push(text(Role::User, "short"));
+        let before = app.context_plan().tokens[0];
+        assert_eq!(app.token_cache.borrow().len(), 1);
+
+        app.messages[0] = text(Role::User, "a good deal longer than it was before");
+        let after = app.context_plan().tokens[0];
+        assert!(after > before);
+        // The count of the old text is not kept around
+        assert_eq!(app.token_cache.borrow().len(), 1);
+    }
+
+    #[test]
+    fn usage_outlives_the_message_it_was_billed_for() {
+        let mut app = app();
+        app.messages.push(text(Role::User, "hi"));
+        let mut answer = text(Role::Assistant, "hello");
+        answer.model = Some("answer-model".to_string());
+        app.messages.push(answer);
+        app.is_streaming = true;
+        let _tx = receive(&mut app, vec![ApiMessage::Usage(usage(10, 5))]);
+        assert_eq!(app.messages[1].usage, Some(usage(10, 5)));
+
+        // A summary is not streamed and goes to the model it was asked of
+        app.is_streaming = false;
+        let _tx = receive(&mut app, vec![ApiMessage::Usage(usage(40, 8))]);
+
+        // Switching to another branch hides the answer, not what it cost
+        app.messages.pop();
+        let by_model = app.usage_by_model();
+        assert_eq!(by_model["answer-model"], usage(10, 5));
+        assert_eq!(by_model[&app.config.model], usage(40, 8));
+        assert_eq!(app.session_usage().0, usage(50, 13));
+        assert_eq!(app.session().usage, *by_model);
+    }
+
+    #[tokio::test]
+    async fn retry_that_sends_nothing_leaves_no_run_behind() {
+        let mut app = app();
+        app.messages.push(text(Role::User, "question"));
+        app.messages.push(text(Role::System, "note"));
+
+        app.retry(2, app.config.params.clone());
+        assert!(!app.is_loading);
+        assert!(app.candidates.is_none());
+        let (status, kind) = app.status_message.clone().unwrap();
+        assert_eq!(status, "⚠ Cannot retry: message 2 is not an answer");
+        assert!(matches!(kind, StatusType::Warning));
+        assert_eq!(outputs(&app.messages), ["question", "note"]);
+    }
+
+    #[tokio::test]
+    async fn cancelling_puts_the_prompt_back() {
+        let mut app = app();
+        app.input = "question".to_string();
+        app.send_message().await.unwrap();
+        assert!(app.is_loading);
+        assert!(app.input.is_empty());
+        assert!(app.api_receiver.is_some());
+
+        app.cancel_request();
+        assert!(!app.is_loading);
+        assert!(app.messages.is_empty());
+        assert_eq!(app.input, "question");
+        assert!(app.input_mode == InputMode::Editing);
+        assert!(app.api_receiver.is_none());
+        assert!(app.request_handle.is_none());
+        let (status, kind) = app.status_message.clone().unwrap();
+        assert_eq!(status, "⊘ Request cancelled - prompt restored to input");
+        assert!(matches!(kind, StatusType::Warning));
+    }
+
+    #[tokio::test]
+    async fn cancelling_drops_the_partial_answer() {
+        let mut app = app();
+        app.input = "question".to_string();
+        app.send_message().await.unwrap();
+        let _tx = receive(&mut app, vec![ApiMessage::Delta("Half an ans".to_string())]);
+        assert_eq!(outputs(&app.messages), ["question", "Half an ans"]);
+
+        app.cancel_request();
+        assert!(app.messages.is_empty());
+        assert!(!app.is_streaming);
+        assert_eq!(app.input, "question");
+        assert!(app.api_receiver.is_none());
+    }
+
+    /// An app whose endpoint answers summary requests with `summary` and chat requests
+    /// with "answer", recording the body of every chat request
+    async fn compacting_app(summary: &str) -> (App, Arc<Mutex<Vec<String>>>) {
+        let chats = Arc::new(Mutex::new(Vec::new()));
+        let sent = chats.clone();
+        let summary = format!(
+            r#"{{"choices": [{{"index": 0, "message": {{"content": {:?}}}, "finish_reason": "stop"}}]}}"#,
+            summary
+        );
+        let url = stub_server(move |_, body| {
+            if body.contains("\"stream\":true") {
+                sent.lock().unwrap().push(body.to_string());
+                Reply::stream(&[
+                    "data: {\"choices\": [{\"index\": 0, \"delta\": {\"content\": \"answer\"}, \"finish_reason\": \"stop\"}]}\n\n",
+                    "data: [DONE]\n\n",
+                ])
+            } else {
+                Reply::json(200, &summary)
+            }
+        })
+        .await;
+        let mut app = App::headless(ChatConfig {
+            base_url: format!("{}/v1", url),
+            autosave: false,
+            ..ChatConfig::default()
+        })
+        .unwrap();
+        for (role, content) in [
+            (Role::User, "first"),
+            (Role::Assistant, "one"),
+            (Role::User, "second"),
+            (Role::Assistant, "two"),
+            (Role::User, "third"),
+        ] {
+            app.messages.push(text(role, content));
+        }
+        (app, chats)
+    }
+
+    /// Handles responses until the request in flight is over
+    async fn settle(app: &mut App) {
+        for _ in 0..500 {
+            app.process_api_response();
+            if !app.is_loading {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("the request never finished");
+    }
+
+    #[tokio::test]
+    async fn a_summary_goes_right_after_the_messages_it_covers() {
+        let (mut app, chats) = compacting_app("- asked about first and second").await;
+        app.start_compaction(0..4, false);
+        settle(&mut app).await;
+
+        assert_eq!(
+            outputs(&app.messages),
+            [
+                "first",
+                "one",
+                "second",
+                "two",
+                "- asked about first and second",
+                "third"
+            ]
+        );
+        assert!(app.messages[4].role == Role::System);
+        assert_eq!(app.messages[4].summarizes, Some(4));
+        assert!(chats.lock().unwrap().is_empty());
+
+        // A later summary takes in the earlier one and what it stood for
+        app.start_compaction(5..6, false);
+        settle(&mut app).await;
+        assert_eq!(app.messages[6].summarizes, Some(5));
+    }
+
+    #[tokio::test]
+    async fn automatic_compaction_sends_the_conversation_with_the_summary() {
+        let (mut app, chats) = compacting_app("- asked about first and second").await;
+        app.start_compaction(0..4, true);
+        settle(&mut app).await;
+
+        assert_eq!(app.messages[4].summarizes, Some(4));
+        assert_eq!(outputs(&app.messages[5..]), ["third", "answer"]);
+        let chats = chats.lock().unwrap();
+        assert_eq!(chats.len(), 1);
+        assert!(
+            chats[0].contains("- asked about first and second"),
+            "{}",
+            chats[0]
+        );
+        assert!(app.turn_warning.is_none());
+    }
+
+    #[tokio::test]
+    async fn an_empty_summary_sends_the_conversation_without_one() {
+        let (mut app, chats) = compacting_app(" \n").await;
+        app.start_compaction(0..4, true);
+        settle(&mut app).await;
+
+        assert!(app.messages.iter().all(|m| m.summarizes.is_none()));
+        assert_eq!(outputs(&app.messages[4..]), ["third", "answer"]);
+        assert_eq!(chats.lock().unwrap().len(), 1);
+        let (status, _) = app.status_message.clone().unwrap();
+        assert!(status.contains("empty summary"), "{}", status);
+    }
+
+    #[tokio::test]
+    async fn a_summary_for_a_conversation_since_changed_is_dropped() {
+        let (mut app, _) = compacting_app("- asked about first and second").await;
+        app.messages[2].siblings = vec![vec![text(Role::User, "second, reworded")]];
+        app.start_compaction(0..4, false);
+        // Same length as before, but a different conversation
+        app.switch_branch(2, 1);
+        app.messages.push(text(Role::Assistant, "two, again"));
+        app.messages.push(text(Role::User, "third"));
+        settle(&mut app).await;
+
+        assert!(app.messages.iter().all(|m| m.summarizes.is_none()));
+        let (status, _) = app.status_message.clone().unwrap();
+        assert!(
+            status.contains("changed while it was being summarised"),
+            "{}",
+            status
+        );
+    }
+
+    #[tokio::test]
+    async fn several_answers_to_one_request_become_versions() {
+        let mut app = app();
+        app.messages.push(text(Role::User, "question"));
+        app.is_loading = true;
+        let other = |index, delta: &str, finish_reason: Option<&str>| ApiMessage::OtherChoice {
+            index,
+            delta: delta.to_string(),
+            finish_reason: finish_reason.map(str::to_string),
+        };
+        let _tx = receive(
+            &mut app,
+            vec![
+                ApiMessage::Delta("one".to_string()),
+                other(2, "thr", None),
+                other(1, "two", Some("stop")),
+                other(2, "ee", Some("length")),
+                ApiMessage::Done(Some("stop".to_string())),
+            ],
+        );
+
+        assert_eq!(outputs(&app.messages), ["question", "one"]);
+        let answer = &app.messages[1];
+        assert_eq!(answer.versions(), 3);
+        assert_eq!(answer.branch, 0);
+        assert_eq!(outputs(&answer.siblings[0]), ["two"]);
+        assert_eq!(outputs(&answer.siblings[1]), ["three"]);
+        assert_eq!(
+            answer.siblings[1][0].finish_reason.as_deref(),
+            Some("length")
+        );
+        assert!(app.other_choices.is_empty());
+        let (status, _) = app.status_message.clone().unwrap();
+        assert!(status.contains("answer 1/3"), "{}", status);
+
+        app.switch_sibling(true);
+        assert_eq!(outputs(&app.messages), ["question", "two"]);
+    }
+
+    fn picker(models: &[&str]) -> ModelPicker {
+        ModelPicker {
+            models: Some(models.iter().map(|model| model.to_string()).collect()),
+            filter: String::new(),
+            selected: 0,
+            receiver: None,
+        }
+    }
+
+    #[test]
+    fn the_model_picker_filters_and_keeps_the_selection_in_range() {
+        let mut picker = picker(&["Qwen/Qwen2.5-Coder-32B", "llama3.2", "qwen2.5-coder:7b"]);
+        picker.select_previous();
+        assert_eq!(picker.selected, 0);
+        picker.select_next();
+        picker.select_next();
+        picker.select_next();
+        assert_eq!(picker.selected, 2);
+
+        // Typing starts over at the first match, ignoring case
+        picker.push_filter('Q');
+        picker.push_filter('w');
+        assert_eq!(picker.selected, 0);
+        assert_eq!(
+            picker.filtered(),
+            ["Qwen/Qwen2.5-Coder-32B", "qwen2.5-coder:7b"]
+        );
+        picker.select_next();
+        picker.select_next();
+        assert_eq!(picker.selected, 1);
+        assert_eq!(picker.choice().as_deref(), Some("qwen2.5-coder:7b"));
+
+        picker.pop_filter();
+        assert_eq!(picker.selected, 0);
+        assert_eq!(picker.filtered().len(), 2);
+    }
+
+    #[test]
+    fn the_model_picker_falls_back_to_the_typed_name() {
+        let mut picker = picker(&["llama3.2"]);
+        for c in " mistral-nemo ".chars() {
+            picker.push_filter(c);
+        }
+        assert!(picker.filtered().is_empty());
+        assert_eq!(picker.choice().as_deref(), Some("mistral-nemo"));
+
+        // Nothing listed and nothing typed leaves nothing to pick
+        assert_eq!(self::picker(&[]).choice(), None);
+    }
+
+    #[test]
+    fn confirming_the_model_picker_switches_model() {
+        let mut app = app();
+        app.model_picker = Some(picker(&["llama3.2", "qwen2.5-coder"]));
+        app.model_picker.as_mut().unwrap().select_next();
+        app.confirm_model_picker();
+        assert!(app.model_picker.is_none());
+        assert_eq!(app.config.model, "qwen2.5-coder");
+
+        let mut typed = picker(&["llama3.2"]);
+        typed.filter = "my-org/finetune".to_string();
+        app.model_picker = Some(typed);
+        app.confirm_model_picker();
+        assert_eq!(app.config.model, "my-org/finetune");
+
+        // With nothing to switch to the picker stays open
+        app.model_picker = Some(picker(&[]));
+        app.confirm_model_picker();
+        assert!(app.model_picker.is_some());
+        assert_eq!(app.config.model, "my-org/finetune");
+    }
+
+    #[test]
+    fn the_model_list_arrives_sorted_with_the_current_model_selected() {
+        let mut app = app();
+        let (tx, rx) = oneshot::channel();
+        let mut picker = picker(&[]);
+        picker.models = None;
+        picker.receiver = Some(rx);
+        app.model_picker = Some(picker);
+
+        app.poll_model_list();
+        assert!(app.model_picker.as_ref().unwrap().models.is_none());
+        tx.send(Ok(vec![
+            "qwen2.5-coder".to_string(),
+            app.config.model.clone(),
+            "gemma2".to_string(),
+        ]))
+        .unwrap();
+        app.poll_model_list();
+        let picker = app.model_picker.as_ref().unwrap();
+        assert_eq!(
+            picker.models.as_deref().unwrap(),
+            ["gemma2", "llama3.2", "qwen2.5-coder"]
+        );
+        assert_eq!(picker.selected, 1);
+    }
+
+    #[test]
+    fn help_scrolls_to_its_last_line_in_a_small_terminal() {
+        use ratatui::{backend::TestBackend, Terminal};
+
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        let mut screen = |app: &App| {
+            let frame = terminal.draw(|f| crate::ui::draw(f, app)).unwrap();
+            let buffer = frame.buffer;
+            (0..buffer.area.height)
+                .map(|y| {
+                    (0..buffer.area.width)
+                        .map(|x| buffer[(x, y)].symbol())
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let mut app = app();
+        app.toggle_help();
+        let top = screen(&app);
+        assert!(top.contains("Help & Commands"), "{}", top);
+        assert!(!top.contains("scroll position"), "{}", top);
+
+        app.scroll_help(1000);
+        assert!(app.help_scroll > 0);
+        assert_eq!(app.help_scroll, app.help_max_scroll.get());
+        let bottom = screen(&app);
+        assert!(
+            bottom.contains("Character counter & scroll position"),
+            "{}",
+            bottom
+        );
+        assert!(bottom.contains("/import <file>"), "{}", bottom);
+
+        app.scroll_help(-1000);
+        assert_eq!(app.help_scroll, 0);
+        // Reopening starts at the top again
+        app.scroll_help(5);
+        app.toggle_help();
+        app.toggle_help();
+        assert_eq!(app.help_scroll, 0);
+    }
+}
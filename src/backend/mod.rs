@@ -0,0 +1,182 @@
+pub mod ollama;
+pub mod openai;
+pub mod tgi;
+
+use crate::{
+    app::ApiMessage,
+    config::{BackendKind, ChatConfig, Token},
+    error::{parse_retry_after, ApiError},
+    types::Usage,
+};
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use openai_api_rs::v1::chat_completion::{ChatCompletionRequest, MessageRole};
+use serde::de::DeserializeOwned;
+use std::{ops::ControlFlow, sync::Arc, time::Duration};
+use tokio::sync::mpsc;
+
+/// A complete, non-streamed answer
+#[derive(Debug, Clone)]
+pub struct ChatResponse {
+    pub content: String,
+    pub finish_reason: Option<String>,
+    pub usage: Option<Usage>,
+}
+
+/// A chat API the app can talk to.
+///
+/// Requests are expressed in the OpenAI chat-completions shape and each backend
+/// translates them to its own wire format.
+#[async_trait]
+pub trait ChatBackend: Send + Sync {
+    /// Short name shown in the UI
+    fn name(&self) -> &'static str;
+
+    /// Sends a request and waits for the whole answer
+    async fn send(&self, req: &ChatCompletionRequest) -> Result<ChatResponse, ApiError>;
+
+    /// Sends a request and forwards content to `tx` as [`ApiMessage::Delta`]s, followed by
+    /// [`ApiMessage::Usage`] when the endpoint reports it and finally [`ApiMessage::Done`].
+    /// Choices beyond the first, asked for with `n`, arrive as [`ApiMessage::OtherChoice`].
+    /// Returns early without an error once `tx` is closed.
+    async fn stream(
+        &self,
+        req: &ChatCompletionRequest,
+        tx: &mpsc::UnboundedSender<ApiMessage>,
+    ) -> Result<(), ApiError>;
+
+    /// Models the endpoint can serve
+    async fn list_models(&self) -> Result<Vec<String>, ApiError>;
+
+    /// Checks that the endpoint is up and accepts our credentials, returning the model list
+    /// when the check fetched it anyway
+    async fn health(&self) -> Result<Option<Vec<String>>, ApiError>;
+}
+
+/// Builds the backend selected by `config.backend` on top of a shared, pooled HTTP client
+pub fn from_config(config: &ChatConfig) -> Result<Arc<dyn ChatBackend>, ApiError> {
+    let http = http_client(config)?;
+
+    Ok(match config.backend {
+        BackendKind::OpenAi => Arc::new(openai::OpenAiBackend::new(config, http)),
+        BackendKind::Ollama => Arc::new(ollama::OllamaBackend::new(config, http)),
+        BackendKind::Tgi => Arc::new(tgi::TgiBackend::new(config, http)),
+    })
+}
+
+/// HTTP client with the configured timeouts and connection pooling
+pub fn http_client(config: &ChatConfig) -> Result<reqwest::Client, ApiError> {
+    Ok(reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(config.http.connect_timeout_secs))
+        .read_timeout(Duration::from_secs(config.http.read_timeout_secs))
+        .pool_idle_timeout(Duration::from_secs(90))
+        .build()?)
+}
+
+/// Adds the bearer token unless the endpoint doesn't need one
+fn authorize(request: reqwest::RequestBuilder, token: &Token) -> reqwest::RequestBuilder {
+    match token.expose() {
+        "" | "unused" => request,
+        secret => request.bearer_auth(secret),
+    }
+}
+
+/// Sends a request and turns any non-2xx status into an [`ApiError`] carrying the body
+async fn send_checked(request: reqwest::RequestBuilder) -> Result<reqwest::Response, ApiError> {
+    let response = request.send().await?;
+    if response.status().is_success() {
+        return Ok(response);
+    }
+
+    let code = response.status().as_u16();
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_retry_after);
+    let body = response.text().await.unwrap_or_default();
+    Err(ApiError::from_response(code, body, retry_after))
+}
+
+/// Reads a whole response body as JSON
+async fn read_json<T: DeserializeOwned>(response: reqwest::Response) -> Result<T, ApiError> {
+    let body = response.text().await?;
+    serde_json::from_str(&body)
+        .map_err(|e| ApiError::decode(format!("invalid response body: {}", e), body))
+}
+
+/// Calls `f` with every non-empty line of a streamed body until it breaks or the body ends
+async fn for_each_line(
+    response: reqwest::Response,
+    mut f: impl FnMut(&str) -> Result<ControlFlow<()>, ApiError> + Send,
+) -> Result<(), ApiError> {
+    let mut chunks = response.bytes_stream();
+    let mut buffer = Vec::new();
+
+    loop {
+        let chunk = chunks.next().await.transpose()?;
+        let at_end = chunk.is_none();
+        if let Some(chunk) = chunk {
+            buffer.extend_from_slice(&chunk);
+        }
+
+        while let Some(newline) = buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim();
+            if !line.is_empty() && f(line)?.is_break() {
+                return Ok(());
+            }
+        }
+
+        if at_end {
+            let line = String::from_utf8_lossy(&buffer);
+            let line = line.trim();
+            if !line.is_empty() {
+                let _ = f(line)?;
+            }
+            return Ok(());
+        }
+    }
+}
+
+/// Native APIs live at the server root, but users often configure the OpenAI `/v1` URL
+fn strip_v1(base_url: &str) -> String {
+    let trimmed = base_url.trim_end_matches('/');
+    trimmed.strip_suffix("/v1").unwrap_or(trimmed).to_string()
+}
+
+fn role_name(role: &MessageRole) -> &'static str {
+    match role {
+        MessageRole::user => "user",
+        MessageRole::assistant => "assistant",
+        MessageRole::system => "system",
+        MessageRole::function => "function",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn native_apis_drop_the_v1_suffix() {
+        assert_eq!(
+            strip_v1("http://localhost:11434/v1"),
+            "http://localhost:11434"
+        );
+        assert_eq!(
+            strip_v1("http://localhost:11434/v1/"),
+            "http://localhost:11434"
+        );
+        assert_eq!(strip_v1("http://localhost:11434"), "http://localhost:11434");
+        assert_eq!(
+            strip_v1("http://localhost:11434/"),
+            "http://localhost:11434"
+        );
+        assert_eq!(
+            strip_v1("https://example.com/models/v1beta"),
+            "https://example.com/models/v1beta"
+        );
+    }
+}
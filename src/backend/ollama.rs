@@ -0,0 +1,338 @@
+use super::{
+    authorize, for_each_line, read_json, role_name, send_checked, strip_v1, ChatBackend,
+    ChatResponse,
+};
+use crate::{
+    app::ApiMessage,
+    config::{ChatConfig, Token},
+    error::ApiError,
+    types::Usage,
+};
+use async_trait::async_trait;
+use openai_api_rs::v1::chat_completion::ChatCompletionRequest;
+use serde::{Deserialize, Serialize};
+use std::ops::ControlFlow;
+use tokio::sync::mpsc;
+
+/// Ollama's native `/api/chat` API
+pub struct OllamaBackend {
+    http: reqwest::Client,
+    base_url: String,
+    token: Token,
+    keep_alive: Option<String>,
+}
+
+#[derive(Serialize)]
+struct OllamaChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<OllamaMessage<'a>>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<&'a str>,
+    options: OllamaOptions<'a>,
+}
+
+#[derive(Serialize)]
+struct OllamaMessage<'a> {
+    role: &'static str,
+    content: &'a str,
+}
+
+#[derive(Serialize)]
+struct OllamaOptions<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f64>,
+}
+
+/// One line of an NDJSON stream, or a complete non-streamed answer
+#[derive(Deserialize)]
+struct OllamaChatChunk {
+    message: Option<OllamaChunkMessage>,
+    #[serde(default)]
+    done: bool,
+    done_reason: Option<String>,
+    prompt_eval_count: Option<u64>,
+    eval_count: Option<u64>,
+    error: Option<serde_json::Value>,
+}
+
+impl OllamaChatChunk {
+    /// Token counts, sent on the final chunk only
+    fn usage(&self) -> Option<Usage> {
+        if self.prompt_eval_count.is_none() && self.eval_count.is_none() {
+            return None;
+        }
+        Some(Usage {
+            prompt_tokens: self.prompt_eval_count.unwrap_or(0),
+            completion_tokens: self.eval_count.unwrap_or(0),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct OllamaChunkMessage {
+    #[serde(default)]
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct OllamaTags {
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Deserialize)]
+struct OllamaModel {
+    name: String,
+}
+
+impl OllamaBackend {
+    pub fn new(config: &ChatConfig, http: reqwest::Client) -> Self {
+        Self {
+            http,
+            base_url: strip_v1(&config.base_url),
+            token: config.token.clone(),
+            keep_alive: config.backend_options.keep_alive.clone(),
+        }
+    }
+
+    fn chat_request(&self, req: &ChatCompletionRequest, stream: bool) -> reqwest::RequestBuilder {
+        let body = OllamaChatRequest {
+            model: &req.model,
+            messages: req
+                .messages
+                .iter()
+                .map(|msg| OllamaMessage {
+                    role: role_name(&msg.role),
+                    content: &msg.content,
+                })
+                .collect(),
+            stream,
+            keep_alive: self.keep_alive.as_deref(),
+            options: OllamaOptions {
+                num_predict: req.max_tokens,
+                temperature: req.temperature,
+                top_p: req.top_p,
+                stop: req.stop.as_deref(),
+                seed: req.seed,
+                presence_penalty: req.presence_penalty,
+                frequency_penalty: req.frequency_penalty,
+            },
+        };
+        let url = format!("{}/api/chat", self.base_url);
+        authorize(self.http.post(url), &self.token).json(&body)
+    }
+}
+
+#[async_trait]
+impl ChatBackend for OllamaBackend {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    async fn send(&self, req: &ChatCompletionRequest) -> Result<ChatResponse, ApiError> {
+        let response = send_checked(self.chat_request(req, false)).await?;
+        let chunk: OllamaChatChunk = read_json(response).await?;
+        if let Some(error) = chunk.error {
+            return Err(ApiError::from_stream_error(error));
+        }
+        Ok(ChatResponse {
+            usage: chunk.usage(),
+            content: chunk.message.map(|m| m.content).unwrap_or_default(),
+            finish_reason: chunk.done_reason,
+        })
+    }
+
+    async fn stream(
+        &self,
+        req: &ChatCompletionRequest,
+        tx: &mpsc::UnboundedSender<ApiMessage>,
+    ) -> Result<(), ApiError> {
+        let response = send_checked(self.chat_request(req, true)).await?;
+        let mut finish_reason = None;
+
+        for_each_line(response, |line| {
+            let mut chunk: OllamaChatChunk = serde_json::from_str(line)
+                .map_err(|e| ApiError::decode(format!("invalid stream chunk: {}", e), line))?;
+            if let Some(error) = chunk.error {
+                return Err(ApiError::from_stream_error(error));
+            }
+            if let Some(message) = chunk.message.take() {
+                if !message.content.is_empty() {
+                    let _ = tx.send(ApiMessage::Delta(message.content));
+                }
+            }
+            if chunk.done {
+                if let Some(usage) = chunk.usage() {
+                    let _ = tx.send(ApiMessage::Usage(usage));
+                }
+                finish_reason = chunk.done_reason;
+                return Ok(ControlFlow::Break(()));
+            }
+            Ok(if tx.is_closed() {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            })
+        })
+        .await?;
+
+        let _ = tx.send(ApiMessage::Done(finish_reason));
+        Ok(())
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, ApiError> {
+        let url = format!("{}/api/tags", self.base_url);
+        let response = send_checked(authorize(self.http.get(url), &self.token)).await?;
+        let tags: OllamaTags = read_json(response).await?;
+        Ok(tags.models.into_iter().map(|model| model.name).collect())
+    }
+
+    async fn health(&self) -> Result<Option<Vec<String>>, ApiError> {
+        let url = format!("{}/api/version", self.base_url);
+        send_checked(authorize(self.http.get(url), &self.token))
+            .await
+            .map(|_| None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{deltas, stream, stub_server, Reply};
+    use openai_api_rs::v1::chat_completion::{ChatCompletionMessage, MessageRole};
+
+    /// A backend configured with the OpenAI-style `/v1` URL, as users often do
+    fn backend(url: &str) -> OllamaBackend {
+        let config = ChatConfig {
+            base_url: format!("{}/v1/", url),
+            ..ChatConfig::default()
+        };
+        OllamaBackend::new(&config, reqwest::Client::new())
+    }
+
+    fn request() -> ChatCompletionRequest {
+        let mut req = ChatCompletionRequest::new(
+            "llama3.2".to_string(),
+            vec![ChatCompletionMessage {
+                role: MessageRole::user,
+                content: "Hi".to_string(),
+                name: None,
+                function_call: None,
+            }],
+        );
+        req.max_tokens = Some(64);
+        req
+    }
+
+    #[tokio::test]
+    async fn streams_newline_delimited_json() {
+        let url = stub_server(|target, body| {
+            let expected = r#""messages":[{"role":"user","content":"Hi"}],"stream":true"#;
+            if target != "POST /api/chat" || !body.contains(expected) {
+                return Reply::json(400, r#"{"error": "unexpected request"}"#);
+            }
+            Reply::stream(&[
+                "{\"message\": {\"role\": \"assistant\", \"content\": \"Hel\"}, \"done\": false}\n{\"message\": ",
+                "{\"content\": \"lo\"}, \"done\": false}\n",
+                "{\"message\": {\"content\": \"\"}, \"done\": true, \"done_reason\": \"length\", \
+                 \"prompt_eval_count\": 9, \"eval_count\": 2}",
+            ])
+        })
+        .await;
+
+        let (result, messages) = stream(&backend(&url), &request()).await;
+        result.unwrap();
+        assert_eq!(deltas(&messages), "Hello");
+        assert!(matches!(
+            messages[messages.len() - 2],
+            ApiMessage::Usage(Usage {
+                prompt_tokens: 9,
+                completion_tokens: 2
+            })
+        ));
+        assert!(
+            matches!(messages.last(), Some(ApiMessage::Done(Some(reason))) if reason == "length")
+        );
+    }
+
+    #[tokio::test]
+    async fn fails_on_an_error_line() {
+        let url = stub_server(|_, _| {
+            Reply::stream(&[
+                "{\"message\": {\"content\": \"Par\"}, \"done\": false}\n",
+                "{\"error\": \"model runner has unexpectedly stopped\"}\n",
+            ])
+        })
+        .await;
+
+        let (result, messages) = stream(&backend(&url), &request()).await;
+        let error = result.unwrap_err();
+        assert!(
+            matches!(&error, ApiError::Provider { message, .. } if message.contains("runner")),
+            "{:?}",
+            error
+        );
+        assert_eq!(deltas(&messages), "Par");
+    }
+
+    #[tokio::test]
+    async fn sends_options_and_reads_a_whole_answer() {
+        let url = stub_server(|target, body| {
+            if target != "POST /api/chat"
+                || !body.contains(r#""stream":false"#)
+                || !body.contains(r#""num_predict":64"#)
+            {
+                return (400, r#"{"error": "unexpected request"}"#);
+            }
+            (
+                200,
+                r#"{"message": {"role": "assistant", "content": "OK"}, "done": true,
+                    "done_reason": "stop", "prompt_eval_count": 3, "eval_count": 1}"#,
+            )
+        })
+        .await;
+
+        let response = backend(&url).send(&request()).await.unwrap();
+        assert_eq!(response.content, "OK");
+        assert_eq!(response.finish_reason.as_deref(), Some("stop"));
+        assert_eq!(
+            response.usage,
+            Some(Usage {
+                prompt_tokens: 3,
+                completion_tokens: 1
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn lists_local_models() {
+        let url = stub_server(|target, _| match target {
+            "GET /api/tags" => (
+                200,
+                r#"{"models": [{"name": "llama3.2:latest"}, {"name": "qwen2.5-coder:7b"}]}"#,
+            ),
+            "GET /api/version" => (200, r#"{"version": "0.5.1"}"#),
+            _ => (404, "404 page not found"),
+        })
+        .await;
+
+        let backend = backend(&url);
+        assert_eq!(
+            backend.list_models().await.unwrap(),
+            ["llama3.2:latest", "qwen2.5-coder:7b"]
+        );
+        backend.health().await.unwrap();
+    }
+}
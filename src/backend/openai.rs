@@ -0,0 +1,459 @@
+use super::{authorize, for_each_line, read_json, send_checked, ChatBackend, ChatResponse};
+use crate::{
+    app::ApiMessage,
+    config::{ChatConfig, Token},
+    error::ApiError,
+    types::Usage,
+};
+use async_trait::async_trait;
+use openai_api_rs::v1::chat_completion::ChatCompletionRequest;
+use serde::{Deserialize, Serialize};
+use std::{
+    ops::ControlFlow,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use tokio::sync::mpsc;
+
+/// OpenAI-compatible `/chat/completions` API
+pub struct OpenAiBackend {
+    http: reqwest::Client,
+    base_url: String,
+    token: Token,
+    /// Whether streamed requests ask for usage; cleared once the server rejects that
+    stream_usage: AtomicBool,
+}
+
+/// A streamed request that can ask for a final chunk carrying token usage
+#[derive(Serialize)]
+struct StreamingRequest<'a> {
+    #[serde(flatten)]
+    request: &'a ChatCompletionRequest,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+#[derive(Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+/// A `chat.completion.chunk` event, or a full `chat.completion` body
+#[derive(Deserialize)]
+struct ChatCompletionChunk {
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    usage: Option<Usage>,
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct ChunkChoice {
+    #[serde(default)]
+    index: i64,
+    #[serde(default)]
+    delta: Option<ChunkDelta>,
+    #[serde(default)]
+    message: Option<ChunkDelta>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChunkDelta {
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+impl OpenAiBackend {
+    pub fn new(config: &ChatConfig, http: reqwest::Client) -> Self {
+        Self {
+            http,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            token: config.token.clone(),
+            stream_usage: AtomicBool::new(true),
+        }
+    }
+
+    fn chat_request(&self, body: &impl Serialize) -> reqwest::RequestBuilder {
+        let url = format!("{}/chat/completions", self.base_url);
+        authorize(self.http.post(url), &self.token).json(body)
+    }
+
+    fn stream_request(
+        &self,
+        req: &ChatCompletionRequest,
+        include_usage: bool,
+    ) -> reqwest::RequestBuilder {
+        let body = StreamingRequest {
+            request: req,
+            stream_options: include_usage.then_some(StreamOptions {
+                include_usage: true,
+            }),
+        };
+        self.chat_request(&body)
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+    }
+}
+
+#[async_trait]
+impl ChatBackend for OpenAiBackend {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    async fn send(&self, req: &ChatCompletionRequest) -> Result<ChatResponse, ApiError> {
+        let mut req = req.clone();
+        req.stream = Some(false);
+        let response = send_checked(self.chat_request(&req)).await?;
+        let chunk: ChatCompletionChunk = read_json(response).await?;
+        if let Some(error) = chunk.error {
+            return Err(ApiError::from_stream_error(error));
+        }
+        let choice = chunk
+            .choices
+            .into_iter()
+            .next()
+            .ok_or_else(|| ApiError::decode("response has no choices", String::new()))?;
+        Ok(ChatResponse {
+            usage: chunk.usage,
+            content: choice
+                .message
+                .or(choice.delta)
+                .and_then(|message| message.content)
+                .unwrap_or_default(),
+            finish_reason: choice.finish_reason,
+        })
+    }
+
+    async fn stream(
+        &self,
+        req: &ChatCompletionRequest,
+        tx: &mpsc::UnboundedSender<ApiMessage>,
+    ) -> Result<(), ApiError> {
+        let mut req = req.clone();
+        req.stream = Some(true);
+        let include_usage = self.stream_usage.load(Ordering::Relaxed);
+        let response = match send_checked(self.stream_request(&req, include_usage)).await {
+            // Strict servers reject `stream_options` as an unknown field; go without usage
+            Err(error) if include_usage && matches!(error.status_code(), Some(400 | 422)) => {
+                let response = send_checked(self.stream_request(&req, false)).await?;
+                self.stream_usage.store(false, Ordering::Relaxed);
+                response
+            }
+            result => result?,
+        };
+
+        let mut finish_reason = None;
+        let mut saw_event = false;
+        let mut plain_body = String::new();
+
+        for_each_line(response, |line| {
+            let Some(data) = line.strip_prefix("data:") else {
+                // Servers that ignore `stream: true` answer with a plain JSON body
+                if !line.starts_with(':') {
+                    plain_body.push_str(line);
+                }
+                return Ok(ControlFlow::Continue(()));
+            };
+
+            let data = data.trim();
+            if data == "[DONE]" {
+                return Ok(ControlFlow::Break(()));
+            }
+            saw_event = true;
+
+            let chunk: ChatCompletionChunk = serde_json::from_str(data)
+                .map_err(|e| ApiError::decode(format!("invalid stream chunk: {}", e), data))?;
+            if let Some(reason) = forward_chunk(chunk, tx)? {
+                finish_reason = Some(reason);
+            }
+            Ok(if tx.is_closed() {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            })
+        })
+        .await?;
+
+        if tx.is_closed() {
+            return Ok(());
+        }
+
+        if !saw_event && !plain_body.is_empty() {
+            let chunk: ChatCompletionChunk = serde_json::from_str(&plain_body).map_err(|e| {
+                ApiError::decode(format!("invalid response body: {}", e), plain_body.clone())
+            })?;
+            finish_reason = forward_chunk(chunk, tx)?;
+        }
+
+        let _ = tx.send(ApiMessage::Done(finish_reason));
+        Ok(())
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, ApiError> {
+        let url = format!("{}/models", self.base_url);
+        let response = send_checked(authorize(self.http.get(url), &self.token)).await?;
+        let models: ModelList = read_json(response).await?;
+        Ok(models.data.into_iter().map(|model| model.id).collect())
+    }
+
+    async fn health(&self) -> Result<Option<Vec<String>>, ApiError> {
+        self.list_models().await.map(Some)
+    }
+}
+
+/// Sends the content of a chunk to the UI and returns its finish reason, if any
+fn forward_chunk(
+    chunk: ChatCompletionChunk,
+    tx: &mpsc::UnboundedSender<ApiMessage>,
+) -> Result<Option<String>, ApiError> {
+    if let Some(error) = chunk.error {
+        return Err(ApiError::from_stream_error(error));
+    }
+    // With `n > 1` the stream interleaves every choice; the first one is the answer
+    let mut finish_reason = None;
+    for choice in chunk.choices {
+        let content = choice
+            .delta
+            .or(choice.message)
+            .and_then(|delta| delta.content)
+            .unwrap_or_default();
+        if choice.index == 0 {
+            if !content.is_empty() {
+                let _ = tx.send(ApiMessage::Delta(content));
+            }
+            finish_reason = choice.finish_reason;
+        } else if !content.is_empty() || choice.finish_reason.is_some() {
+            let _ = tx.send(ApiMessage::OtherChoice {
+                index: choice.index,
+                delta: content,
+                finish_reason: choice.finish_reason,
+            });
+        }
+    }
+
+    // Usage comes on the last chunk, often one without any choices
+    if let Some(usage) = chunk.usage {
+        let _ = tx.send(ApiMessage::Usage(usage));
+    }
+    Ok(finish_reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{deltas, stream, stub_server, Reply};
+    use std::sync::{atomic::AtomicUsize, Arc};
+
+    fn backend(url: &str) -> OpenAiBackend {
+        let config = ChatConfig {
+            base_url: format!("{}/v1", url),
+            ..ChatConfig::default()
+        };
+        OpenAiBackend::new(&config, reqwest::Client::new())
+    }
+
+    fn request() -> ChatCompletionRequest {
+        ChatCompletionRequest::new("llama3.2".to_string(), Vec::new())
+    }
+
+    /// Streams from a server that answers every chat request with `chunks`
+    async fn stream_chunks(
+        chunks: &'static [&'static str],
+    ) -> (Result<(), ApiError>, Vec<ApiMessage>) {
+        let url = stub_server(move |_, _| Reply::stream(chunks)).await;
+        stream(&backend(&url), &request()).await
+    }
+
+    #[tokio::test]
+    async fn forwards_other_choices_separately() {
+        let url = stub_server(|_, _| {
+            Reply::stream(&[
+                "data: {\"choices\": [{\"index\": 0, \"delta\": {\"content\": \"one\"}}, {\"index\": 1, \"delta\": {\"content\": \"two\"}}]}\n\n",
+                "data: {\"choices\": [{\"index\": 1, \"delta\": {}, \"finish_reason\": \"stop\"}]}\n\n",
+                "data: {\"choices\": [{\"index\": 0, \"delta\": {}, \"finish_reason\": \"stop\"}]}\n\n",
+                "data: [DONE]\n\n",
+            ])
+        })
+        .await;
+        let mut req = request();
+        req.n = Some(2);
+
+        let (result, messages) = stream(&backend(&url), &req).await;
+        result.unwrap();
+        assert_eq!(deltas(&messages), "one");
+        let others: Vec<_> = messages
+            .iter()
+            .filter_map(|message| match message {
+                ApiMessage::OtherChoice {
+                    index,
+                    delta,
+                    finish_reason,
+                } => Some((*index, delta.as_str(), finish_reason.as_deref())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(others, [(1, "two", None), (1, "", Some("stop"))]);
+        assert!(
+            matches!(messages.last(), Some(ApiMessage::Done(Some(reason))) if reason == "stop")
+        );
+    }
+
+    #[tokio::test]
+    async fn drops_stream_options_when_the_server_rejects_them() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let url = stub_server(move |_, body| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            if body.contains("stream_options") {
+                Reply::json(
+                    400,
+                    r#"{"error": {"message": "Extra inputs are not permitted: stream_options"}}"#,
+                )
+            } else {
+                Reply::stream(&[
+                    "data: {\"choices\": [{\"index\": 0, \"delta\": {\"content\": \"Hi\"}}]}\n\n",
+                ])
+            }
+        })
+        .await;
+        let backend = backend(&url);
+
+        let (result, messages) = stream(&backend, &request()).await;
+        result.unwrap();
+        assert_eq!(deltas(&messages), "Hi");
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // Later requests leave the option out straight away
+        let (result, _) = stream(&backend, &request()).await;
+        result.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn keeps_the_error_of_a_request_that_fails_either_way() {
+        let url =
+            stub_server(|_, _| (400, r#"{"error": {"message": "temperature too high"}}"#)).await;
+        let backend = backend(&url);
+        let (result, _) = stream(&backend, &request()).await;
+        assert_eq!(result.unwrap_err().status_code(), Some(400));
+        assert!(backend.stream_usage.load(Ordering::SeqCst));
+    }
+
+    fn finish_reason(messages: &[ApiMessage]) -> Option<&str> {
+        match messages.last() {
+            Some(ApiMessage::Done(reason)) => reason.as_deref(),
+            other => panic!("expected the stream to end with Done, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn joins_an_event_split_across_reads() {
+        let (result, messages) = stream_chunks(&[
+            "data: {\"choices\": [{\"index\": 0, \"delta\": {\"content\": \"Hel",
+            "lo\"}}]}\n\ndata: {\"choices\": [{\"index\": 0, \"delta\": {\"content\": \" world\"}, \
+             \"finish_reason\": \"stop\"}]}\n\n",
+            "data: [DONE]\n\n",
+        ])
+        .await;
+        result.unwrap();
+        assert_eq!(deltas(&messages), "Hello world");
+        assert_eq!(finish_reason(&messages), Some("stop"));
+    }
+
+    #[tokio::test]
+    async fn stops_reading_at_done() {
+        let (result, messages) = stream_chunks(&[
+            ": keep-alive\n\ndata: {\"choices\": [{\"index\": 0, \"delta\": {\"content\": \"Hi\"}}]}\n\n",
+            "data: [DONE]\n\n",
+            "data: not json\n\n",
+        ])
+        .await;
+        result.unwrap();
+        assert_eq!(deltas(&messages), "Hi");
+        assert_eq!(finish_reason(&messages), None);
+    }
+
+    #[tokio::test]
+    async fn reads_a_plain_json_answer_to_a_streamed_request() {
+        let url = stub_server(|_, _| {
+            (
+                200,
+                r#"{"choices": [{"index": 0, "message": {"content": "OK"}, "finish_reason": "length"}],
+                    "usage": {"prompt_tokens": 12, "completion_tokens": 1}}"#,
+            )
+        })
+        .await;
+        let (result, messages) = stream(&backend(&url), &request()).await;
+        result.unwrap();
+        assert_eq!(deltas(&messages), "OK");
+        assert!(messages.iter().any(|message| matches!(
+            message,
+            ApiMessage::Usage(Usage {
+                prompt_tokens: 12,
+                completion_tokens: 1
+            })
+        )));
+        assert_eq!(finish_reason(&messages), Some("length"));
+    }
+
+    #[tokio::test]
+    async fn reports_usage_from_a_final_chunk_without_choices() {
+        let (result, messages) = stream_chunks(&[
+            "data: {\"choices\": [{\"index\": 0, \"delta\": {\"content\": \"Hi\"}, \"finish_reason\": \"stop\"}]}\n\n",
+            "data: {\"choices\": [], \"usage\": {\"prompt_tokens\": 7, \"completion_tokens\": 2}}\n\n",
+            "data: [DONE]\n\n",
+        ])
+        .await;
+        result.unwrap();
+        assert_eq!(deltas(&messages), "Hi");
+        assert!(matches!(
+            messages[messages.len() - 2],
+            ApiMessage::Usage(Usage {
+                prompt_tokens: 7,
+                completion_tokens: 2
+            })
+        ));
+        // The chunk without choices does not reset the finish reason
+        assert_eq!(finish_reason(&messages), Some("stop"));
+    }
+
+    #[tokio::test]
+    async fn fails_on_an_error_sent_mid_stream() {
+        let (result, messages) = stream_chunks(&[
+            "data: {\"choices\": [{\"index\": 0, \"delta\": {\"content\": \"Par\"}}]}\n\n",
+            "data: {\"error\": {\"message\": \"overloaded\", \"type\": \"server_error\"}}\n\n",
+        ])
+        .await;
+        let error = result.unwrap_err();
+        assert!(
+            matches!(&error, ApiError::Provider { message, .. } if message == "overloaded"),
+            "{:?}",
+            error
+        );
+        // What arrived before the error is kept, but the stream never finished
+        assert_eq!(deltas(&messages), "Par");
+        assert!(!messages
+            .iter()
+            .any(|message| matches!(message, ApiMessage::Done(_))));
+    }
+
+    #[tokio::test]
+    async fn reads_a_last_line_without_a_newline() {
+        let (result, messages) = stream_chunks(&[
+            "data: {\"choices\": [{\"index\": 0, \"delta\": {\"content\": \"Bye\"}, \"finish_reason\": \"stop\"}]}",
+        ])
+        .await;
+        result.unwrap();
+        assert_eq!(deltas(&messages), "Bye");
+        assert_eq!(finish_reason(&messages), Some("stop"));
+    }
+}
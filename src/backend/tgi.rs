@@ -0,0 +1,495 @@
+use super::{
+    authorize, for_each_line, read_json, role_name, send_checked, strip_v1, ChatBackend,
+    ChatResponse,
+};
+use crate::{
+    app::ApiMessage,
+    config::{ChatConfig, PromptFormat, Token},
+    error::ApiError,
+    types::Usage,
+};
+use async_trait::async_trait;
+use openai_api_rs::v1::chat_completion::{ChatCompletionMessage, ChatCompletionRequest};
+use serde::{Deserialize, Serialize};
+use std::ops::ControlFlow;
+use tokio::sync::mpsc;
+
+/// Text Generation Inference `/generate` and `/generate_stream` APIs.
+///
+/// Messages are flattened into a raw prompt with the configured chat template.
+pub struct TgiBackend {
+    http: reqwest::Client,
+    base_url: String,
+    token: Token,
+    prompt_format: PromptFormat,
+    grammar: Option<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+struct GenerateRequest<'a> {
+    inputs: String,
+    parameters: GenerateParameters<'a>,
+}
+
+#[derive(Serialize)]
+struct GenerateParameters<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_new_tokens: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grammar: Option<&'a serde_json::Value>,
+    do_sample: bool,
+    details: bool,
+    return_full_text: bool,
+}
+
+/// `/generate` answers with an object; HF serverless wraps it in an array
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GenerateResponse {
+    Single(GeneratedText),
+    Batch(Vec<GeneratedText>),
+}
+
+#[derive(Deserialize)]
+struct GeneratedText {
+    generated_text: String,
+    details: Option<GenerateDetails>,
+}
+
+#[derive(Deserialize)]
+struct GenerateDetails {
+    finish_reason: Option<String>,
+    generated_tokens: Option<u64>,
+}
+
+impl GenerateDetails {
+    /// TGI only reports generated tokens; prompt tokens would need the full prefill
+    fn usage(&self) -> Option<Usage> {
+        self.generated_tokens.map(|completion_tokens| Usage {
+            prompt_tokens: 0,
+            completion_tokens,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct StreamEvent {
+    token: Option<StreamToken>,
+    details: Option<GenerateDetails>,
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct StreamToken {
+    text: String,
+    #[serde(default)]
+    special: bool,
+}
+
+#[derive(Deserialize)]
+struct TgiInfo {
+    model_id: String,
+}
+
+impl TgiBackend {
+    pub fn new(config: &ChatConfig, http: reqwest::Client) -> Self {
+        Self {
+            http,
+            base_url: strip_v1(&config.base_url),
+            token: config.token.clone(),
+            prompt_format: config.backend_options.prompt_format,
+            grammar: config.backend_options.grammar.clone(),
+        }
+    }
+
+    fn generate_request(&self, req: &ChatCompletionRequest, path: &str) -> reqwest::RequestBuilder {
+        let (inputs, mut stop) = format_prompt(self.prompt_format, &req.messages);
+        stop.extend(req.stop.iter().flatten().cloned());
+
+        // TGI rejects temperature <= 0 and top_p outside (0, 1)
+        let temperature = req.temperature.filter(|t| *t > 0.0);
+        let body = GenerateRequest {
+            inputs,
+            parameters: GenerateParameters {
+                max_new_tokens: req.max_tokens,
+                temperature,
+                top_p: req.top_p.filter(|p| *p > 0.0 && *p < 1.0),
+                stop,
+                seed: req.seed,
+                frequency_penalty: req.frequency_penalty,
+                grammar: self.grammar.as_ref(),
+                do_sample: temperature.is_some(),
+                details: true,
+                return_full_text: false,
+            },
+        };
+        let url = format!("{}{}", self.base_url, path);
+        authorize(self.http.post(url), &self.token).json(&body)
+    }
+}
+
+#[async_trait]
+impl ChatBackend for TgiBackend {
+    fn name(&self) -> &'static str {
+        "tgi"
+    }
+
+    async fn send(&self, req: &ChatCompletionRequest) -> Result<ChatResponse, ApiError> {
+        let response = send_checked(self.generate_request(req, "/generate")).await?;
+        let generated = match read_json(response).await? {
+            GenerateResponse::Single(generated) => generated,
+            GenerateResponse::Batch(batch) => batch
+                .into_iter()
+                .next()
+                .ok_or_else(|| ApiError::decode("empty generation batch", "[]"))?,
+        };
+        let details = generated.details;
+        Ok(ChatResponse {
+            content: generated.generated_text,
+            usage: details.as_ref().and_then(GenerateDetails::usage),
+            finish_reason: details
+                .and_then(|details| details.finish_reason)
+                .map(normalize_finish_reason),
+        })
+    }
+
+    async fn stream(
+        &self,
+        req: &ChatCompletionRequest,
+        tx: &mpsc::UnboundedSender<ApiMessage>,
+    ) -> Result<(), ApiError> {
+        let request = self
+            .generate_request(req, "/generate_stream")
+            .header(reqwest::header::ACCEPT, "text/event-stream");
+        let response = send_checked(request).await?;
+        let mut finish_reason = None;
+
+        for_each_line(response, |line| {
+            let Some(data) = line.strip_prefix("data:") else {
+                return Ok(ControlFlow::Continue(()));
+            };
+            let data = data.trim();
+            let event: StreamEvent = serde_json::from_str(data)
+                .map_err(|e| ApiError::decode(format!("invalid stream event: {}", e), data))?;
+            if let Some(error) = event.error {
+                return Err(ApiError::from_stream_error(error));
+            }
+            if let Some(token) = event.token {
+                if !token.special && !token.text.is_empty() {
+                    let _ = tx.send(ApiMessage::Delta(token.text));
+                }
+            }
+            if let Some(details) = event.details {
+                if let Some(usage) = details.usage() {
+                    let _ = tx.send(ApiMessage::Usage(usage));
+                }
+                finish_reason = details.finish_reason.map(normalize_finish_reason);
+                return Ok(ControlFlow::Break(()));
+            }
+            Ok(if tx.is_closed() {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            })
+        })
+        .await?;
+
+        let _ = tx.send(ApiMessage::Done(finish_reason));
+        Ok(())
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, ApiError> {
+        let url = format!("{}/info", self.base_url);
+        let response = send_checked(authorize(self.http.get(url), &self.token)).await?;
+        let info: TgiInfo = read_json(response).await?;
+        Ok(vec![info.model_id])
+    }
+
+    async fn health(&self) -> Result<Option<Vec<String>>, ApiError> {
+        let url = format!("{}/health", self.base_url);
+        send_checked(authorize(self.http.get(url), &self.token))
+            .await
+            .map(|_| None)
+    }
+}
+
+/// Maps TGI finish reasons onto the OpenAI vocabulary used by the rest of the app
+fn normalize_finish_reason(reason: String) -> String {
+    match reason.as_str() {
+        "eos_token" | "stop_sequence" => "stop".to_string(),
+        _ => reason,
+    }
+}
+
+/// Renders messages with a chat template, returning the prompt and its stop sequences
+fn format_prompt(
+    format: PromptFormat,
+    messages: &[ChatCompletionMessage],
+) -> (String, Vec<String>) {
+    let mut prompt = String::new();
+    match format {
+        PromptFormat::ChatMl => {
+            for msg in messages {
+                prompt.push_str(&format!(
+                    "<|im_start|>{}\n{}<|im_end|>\n",
+                    role_name(&msg.role),
+                    msg.content
+                ));
+            }
+            prompt.push_str("<|im_start|>assistant\n");
+            (prompt, vec!["<|im_end|>".to_string()])
+        }
+        PromptFormat::Llama3 => {
+            prompt.push_str("<|begin_of_text|>");
+            for msg in messages {
+                prompt.push_str(&format!(
+                    "<|start_header_id|>{}<|end_header_id|>\n\n{}<|eot_id|>",
+                    role_name(&msg.role),
+                    msg.content
+                ));
+            }
+            prompt.push_str("<|start_header_id|>assistant<|end_header_id|>\n\n");
+            (prompt, vec!["<|eot_id|>".to_string()])
+        }
+        PromptFormat::Mistral => {
+            // Mistral has no system role; fold it into the next user turn
+            let mut system = String::new();
+            prompt.push_str("<s>");
+            for msg in messages {
+                match role_name(&msg.role) {
+                    "system" => {
+                        system.push_str(&msg.content);
+                        system.push_str("\n\n");
+                    }
+                    "assistant" => prompt.push_str(&format!(" {}</s>", msg.content)),
+                    _ => {
+                        prompt.push_str(&format!("[INST] {}{} [/INST]", system, msg.content));
+                        system.clear();
+                    }
+                }
+            }
+            (prompt, Vec::new())
+        }
+        PromptFormat::Plain => {
+            for msg in messages {
+                let label = match role_name(&msg.role) {
+                    "system" => "System",
+                    "assistant" => "Assistant",
+                    _ => "User",
+                };
+                prompt.push_str(&format!("{}: {}\n\n", label, msg.content));
+            }
+            prompt.push_str("Assistant:");
+            (prompt, vec!["\nUser:".to_string()])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{deltas, stream, stub_server, Reply};
+    use openai_api_rs::v1::chat_completion::MessageRole;
+
+    fn message(role: MessageRole, content: &str) -> ChatCompletionMessage {
+        ChatCompletionMessage {
+            role,
+            content: content.to_string(),
+            name: None,
+            function_call: None,
+        }
+    }
+
+    fn conversation() -> Vec<ChatCompletionMessage> {
+        vec![
+            message(MessageRole::system, "Be brief."),
+            message(MessageRole::user, "Hi"),
+            message(MessageRole::assistant, "Hello!"),
+            message(MessageRole::user, "Bye"),
+        ]
+    }
+
+    fn backend(url: &str, prompt_format: PromptFormat) -> TgiBackend {
+        let mut config = ChatConfig {
+            base_url: format!("{}/v1", url),
+            backend: crate::config::BackendKind::Tgi,
+            ..ChatConfig::default()
+        };
+        config.backend_options.prompt_format = prompt_format;
+        TgiBackend::new(&config, reqwest::Client::new())
+    }
+
+    fn request(temperature: f64) -> ChatCompletionRequest {
+        let mut req = ChatCompletionRequest::new("tgi".to_string(), conversation());
+        req.temperature = Some(temperature);
+        req.stop = Some(vec!["END".to_string()]);
+        req
+    }
+
+    #[test]
+    fn chat_ml_prompt() {
+        let (prompt, stop) = format_prompt(PromptFormat::ChatMl, &conversation());
+        assert_eq!(
+            prompt,
+            "<|im_start|>system\nBe brief.<|im_end|>\n<|im_start|>user\nHi<|im_end|>\n\
+             <|im_start|>assistant\nHello!<|im_end|>\n<|im_start|>user\nBye<|im_end|>\n\
+             <|im_start|>assistant\n"
+        );
+        assert_eq!(stop, ["<|im_end|>"]);
+    }
+
+    #[test]
+    fn llama3_prompt() {
+        let (prompt, stop) = format_prompt(PromptFormat::Llama3, &conversation());
+        assert_eq!(
+            prompt,
+            "<|begin_of_text|><|start_header_id|>system<|end_header_id|>\n\nBe brief.<|eot_id|>\
+             <|start_header_id|>user<|end_header_id|>\n\nHi<|eot_id|>\
+             <|start_header_id|>assistant<|end_header_id|>\n\nHello!<|eot_id|>\
+             <|start_header_id|>user<|end_header_id|>\n\nBye<|eot_id|>\
+             <|start_header_id|>assistant<|end_header_id|>\n\n"
+        );
+        assert_eq!(stop, ["<|eot_id|>"]);
+    }
+
+    #[test]
+    fn mistral_prompt_folds_the_system_prompt_into_the_next_turn() {
+        let (prompt, stop) = format_prompt(PromptFormat::Mistral, &conversation());
+        assert_eq!(
+            prompt,
+            "<s>[INST] Be brief.\n\nHi [/INST] Hello!</s>[INST] Bye [/INST]"
+        );
+        assert!(stop.is_empty());
+    }
+
+    #[test]
+    fn plain_prompt() {
+        let (prompt, stop) = format_prompt(PromptFormat::Plain, &conversation());
+        assert_eq!(
+            prompt,
+            "System: Be brief.\n\nUser: Hi\n\nAssistant: Hello!\n\nUser: Bye\n\nAssistant:"
+        );
+        assert_eq!(stop, ["\nUser:"]);
+    }
+
+    #[test]
+    fn finish_reasons_use_the_openai_names() {
+        assert_eq!(normalize_finish_reason("eos_token".to_string()), "stop");
+        assert_eq!(normalize_finish_reason("stop_sequence".to_string()), "stop");
+        assert_eq!(normalize_finish_reason("length".to_string()), "length");
+    }
+
+    #[tokio::test]
+    async fn streams_generated_tokens_without_special_ones() {
+        let url = stub_server(|target, body| {
+            let sampled = body.contains(r#""temperature":0.5"#) && body.contains(r#""do_sample":true"#);
+            let stops = body.contains(r#""stop":["<|im_end|>","END"]"#);
+            if target != "POST /generate_stream" || !sampled || !stops {
+                return Reply::json(422, r#"{"error": "unexpected request"}"#);
+            }
+            Reply::stream(&[
+                "data:{\"token\": {\"id\": 1, \"text\": \"Good\", \"special\": false}}\n\n",
+                "data:{\"token\": {\"id\": 2, \"text\": \"bye\", \"special\": false}}\n\ndata:{\"token\": ",
+                "{\"id\": 3, \"text\": \"<|im_end|>\", \"special\": true}, \"generated_text\": \"Goodbye\", \
+                 \"details\": {\"finish_reason\": \"eos_token\", \"generated_tokens\": 3}}\n\n",
+            ])
+        })
+        .await;
+
+        let (result, messages) = stream(&backend(&url, PromptFormat::ChatMl), &request(0.5)).await;
+        result.unwrap();
+        assert_eq!(deltas(&messages), "Goodbye");
+        assert!(matches!(
+            messages[messages.len() - 2],
+            ApiMessage::Usage(Usage {
+                prompt_tokens: 0,
+                completion_tokens: 3
+            })
+        ));
+        assert!(
+            matches!(messages.last(), Some(ApiMessage::Done(Some(reason))) if reason == "stop")
+        );
+    }
+
+    #[tokio::test]
+    async fn fails_on_an_error_event() {
+        let url = stub_server(|_, _| {
+            Reply::stream(&[
+                "data:{\"error\": \"Request failed during generation: Server error: CUDA out of memory\", \
+                 \"error_type\": \"generation\"}\n\n",
+            ])
+        })
+        .await;
+
+        let (result, _) = stream(&backend(&url, PromptFormat::ChatMl), &request(0.5)).await;
+        let error = result.unwrap_err();
+        assert!(
+            matches!(&error, ApiError::Provider { message, .. } if message.contains("CUDA")),
+            "{:?}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn reads_a_single_or_batched_generation() {
+        let url = stub_server(|target, body| {
+            // Greedy decoding: TGI rejects a temperature of 0
+            let greedy = !body.contains("temperature") && body.contains(r#""do_sample":false"#);
+            match target {
+                "POST /generate" if greedy => (
+                    200,
+                    r#"{"generated_text": "OK", "details": {"finish_reason": "length", "generated_tokens": 2}}"#,
+                ),
+                "POST /generate" => (
+                    200,
+                    r#"[{"generated_text": "Sure", "details": {"finish_reason": "stop_sequence"}}]"#,
+                ),
+                _ => (404, r#"{"error": "not found"}"#),
+            }
+        })
+        .await;
+        let backend = backend(&url, PromptFormat::Llama3);
+
+        let response = backend.send(&request(0.0)).await.unwrap();
+        assert_eq!(response.content, "OK");
+        assert_eq!(response.finish_reason.as_deref(), Some("length"));
+        assert_eq!(
+            response.usage,
+            Some(Usage {
+                prompt_tokens: 0,
+                completion_tokens: 2
+            })
+        );
+
+        let response = backend.send(&request(0.7)).await.unwrap();
+        assert_eq!(response.content, "Sure");
+        assert_eq!(response.finish_reason.as_deref(), Some("stop"));
+        assert_eq!(response.usage, None);
+    }
+
+    #[tokio::test]
+    async fn reads_the_served_model_from_info() {
+        let url = stub_server(|target, _| match target {
+            "GET /info" => (200, r#"{"model_id": "HuggingFaceH4/zephyr-7b-beta"}"#),
+            "GET /health" => (200, ""),
+            _ => (404, r#"{"error": "not found"}"#),
+        })
+        .await;
+        let backend = backend(&url, PromptFormat::ChatMl);
+        assert_eq!(
+            backend.list_models().await.unwrap(),
+            ["HuggingFaceH4/zephyr-7b-beta"]
+        );
+        backend.health().await.unwrap();
+    }
+}
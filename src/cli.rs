@@ -0,0 +1,107 @@
+use crate::config::BackendKind;
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+/// A modern TUI-based AI coding assistant powered by Hugging Face models.
+///
+/// Settings are layered, later sources winning: built-in defaults,
+/// ~/.config/hugging-code/config.toml, the nearest .hugging-code.toml,
+/// --config, environment variables (and .env), then these flags.
+///
+/// With --prompt or --stdin the answer is printed to stdout instead of starting
+/// the TUI. Exit status: 0 on success, 1 if the request failed, 2 for usage or
+/// config errors, 3 if the answer was cut off by max_tokens, 4 if it was empty.
+#[derive(Debug, Default, Parser)]
+#[command(name = "hugging-code", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Extra config file, applied on top of the user and project files
+    #[arg(short, long, value_name = "FILE", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Named profile from the config files to start with
+    #[arg(long, value_name = "NAME", global = true)]
+    pub profile: Option<String>,
+
+    /// API endpoint URL
+    #[arg(long, value_name = "URL", global = true)]
+    pub base_url: Option<String>,
+
+    /// Model to chat with
+    #[arg(short, long, global = true)]
+    pub model: Option<String>,
+
+    /// Wire protocol: openai, ollama or tgi
+    #[arg(long, global = true)]
+    pub backend: Option<BackendKind>,
+
+    /// System prompt for new conversations
+    #[arg(long, value_name = "PROMPT")]
+    pub system_prompt: Option<String>,
+
+    /// Maximum tokens per response
+    #[arg(long, value_name = "N")]
+    pub max_tokens: Option<i64>,
+
+    /// Sampling temperature
+    #[arg(long, value_name = "T")]
+    pub temperature: Option<f64>,
+
+    /// Most recent messages sent as context, on top of the token budget
+    #[arg(long, value_name = "N")]
+    pub max_context_messages: Option<usize>,
+
+    /// Context window of the model in tokens
+    #[arg(long, value_name = "TOKENS")]
+    pub context_length: Option<usize>,
+
+    /// Reopen the most recently saved session
+    #[arg(long = "continue", conflicts_with_all = ["resume", "prompt", "stdin"])]
+    pub continue_session: bool,
+
+    /// Reopen a saved session by ID or ID prefix; without an ID, pick one from the list
+    #[arg(
+        long,
+        value_name = "ID",
+        num_args = 0..=1,
+        default_missing_value = "",
+        conflicts_with_all = ["prompt", "stdin"]
+    )]
+    pub resume: Option<String>,
+
+    /// Ask a single question, print the answer and exit
+    #[arg(short, long, value_name = "QUESTION")]
+    pub prompt: Option<String>,
+
+    /// Read the question from stdin; with --prompt, stdin is appended to it
+    #[arg(long)]
+    pub stdin: bool,
+
+    /// Print the model's text verbatim as it is generated
+    #[arg(long, conflicts_with = "no_thinking")]
+    pub stream: bool,
+
+    /// Print the model's text verbatim, thinking tags included
+    #[arg(long, conflicts_with = "no_thinking")]
+    pub raw: bool,
+
+    /// Leave out the model's thinking instead of printing it to stderr
+    #[arg(long)]
+    pub no_thinking: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Check the configuration and endpoint step by step and suggest fixes.
+    /// Exits with 1 if any check failed.
+    Doctor,
+}
+
+impl Cli {
+    /// Whether to answer from the command line instead of starting the TUI
+    pub fn is_one_shot(&self) -> bool {
+        self.prompt.is_some() || self.stdin
+    }
+}
@@ -1,49 +1,1654 @@
+use crate::{cli::Cli, types::Usage};
+use openai_api_rs::v1::chat_completion::ChatCompletionRequest;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt::{self, Display},
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    str::FromStr,
+    time::Duration,
+};
+
+/// Name of the project-local config file, looked up from the working directory upwards
+pub const PROJECT_CONFIG_FILE: &str = ".hugging-code.toml";
 
 /// Configuration for the chat client
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct ChatConfig {
     pub base_url: String,
-    pub token: String,
+    pub token: Token,
     pub model: String,
+    pub params: SamplingParams,
+    pub system_prompt: Option<String>,
+    /// Upper bound on the messages sent as context; by default only the token budget applies
+    pub max_context_messages: Option<usize>,
+    /// Context window of the model in tokens, shared by the prompt and the answer
+    pub context_length: usize,
+    /// Hugging Face `tokenizer.json` of the model, for exact token counts
+    pub tokenizer: Option<PathBuf>,
+    /// Summarise messages that no longer fit the context instead of just dropping them
+    pub compaction: bool,
+    /// Save every conversation to the session store as it goes
+    pub autosave: bool,
+    /// Where sessions are stored; defaults to `~/.local/share/hugging-code/sessions`
+    pub sessions_dir: Option<PathBuf>,
+    pub retry: RetryConfig,
+    pub http: HttpConfig,
+    pub backend: BackendKind,
+    pub backend_options: BackendOptions,
+    /// Optional price table keyed by model name, used for the cost meter
+    pub prices: HashMap<String, ModelPrice>,
+    /// Read the token from this environment variable instead of `token`
+    pub token_env: Option<String>,
+    /// Read the token from this file, which must not be accessible by other users
+    pub token_file: Option<PathBuf>,
+    /// Shell command whose output is the token, e.g. a password manager lookup
+    pub token_command: Option<String>,
+    /// Profile applied on top of these settings
+    pub profile: Option<String>,
+    /// Named overrides of any of the settings above, e.g. `[profiles.local]`
+    pub profiles: BTreeMap<String, toml::Table>,
+    /// Environment the settings were loaded from, where profiles look up their tokens
+    #[serde(skip)]
+    pub(crate) env: LoadedEnv,
+}
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "http://localhost:11434/v1".to_string(),
+            token: Token::default(),
+            model: "llama3.2".to_string(),
+            params: SamplingParams::default(),
+            system_prompt: None,
+            max_context_messages: None,
+            context_length: 8192,
+            tokenizer: None,
+            compaction: false,
+            autosave: true,
+            sessions_dir: None,
+            retry: RetryConfig::default(),
+            http: HttpConfig::default(),
+            backend: BackendKind::OpenAi,
+            backend_options: BackendOptions::default(),
+            prices: HashMap::new(),
+            token_env: None,
+            token_file: None,
+            token_command: None,
+            profile: None,
+            profiles: BTreeMap::new(),
+            env: LoadedEnv::default(),
+        }
+    }
+}
+
+/// Environment variables kept with a configuration; only their number shows in `Debug`
+/// output, as the values may hold tokens
+#[derive(Clone, Default)]
+pub(crate) struct LoadedEnv(pub(crate) Env);
+
+impl fmt::Debug for LoadedEnv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{} variables>", self.0.len())
+    }
+}
+
+/// An API token that only ever shows up masked in `Debug` and `Display` output
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Token(String);
+
+impl Token {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self(secret.into())
+    }
+
+    /// The secret itself, for the `Authorization` header and nothing else
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Display for Token {
+    /// `(not set)`, or asterisks followed by the last four characters of long tokens
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let chars: Vec<char> = self.0.chars().collect();
+        match chars.len() {
+            0 => f.write_str("(not set)"),
+            len if len < 16 => f.write_str("****"),
+            len => write!(f, "****{}", chars[len - 4..].iter().collect::<String>()),
+        }
+    }
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+/// Price of a model in USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+}
+
+impl ModelPrice {
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        (usage.prompt_tokens as f64 * self.input + usage.completion_tokens as f64 * self.output)
+            / 1_000_000.0
+    }
+}
+
+/// Sampling parameters sent with every request; `None` leaves the server default
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SamplingParams {
     pub max_tokens: Option<i64>,
     pub temperature: Option<f64>,
-    pub system_prompt: Option<String>,
-    pub max_context_messages: usize,
+    pub top_p: Option<f64>,
+    pub stop: Option<Vec<String>>,
+    pub seed: Option<i64>,
+    pub presence_penalty: Option<f64>,
+    pub frequency_penalty: Option<f64>,
+    /// Number of completions to generate per request; all but the first become other
+    /// versions of the answer
+    pub n: Option<i64>,
 }
 
-impl ChatConfig {
-    pub fn from_env() -> Result<Self, Box<dyn Error>> {
-        dotenv::dotenv().ok();
+impl Default for SamplingParams {
+    fn default() -> Self {
+        Self {
+            max_tokens: Some(500),
+            temperature: Some(0.7),
+            top_p: None,
+            stop: None,
+            seed: None,
+            presence_penalty: None,
+            frequency_penalty: None,
+            n: None,
+        }
+    }
+}
 
-        let base_url = std::env::var("HF_BASE_URL").unwrap_or_else(|_| {
-            eprintln!("\n⚠️  No HF_BASE_URL set. Please check WORKING_CONFIGS.md for setup instructions.");
-            eprintln!("   Tip: For free local usage, install Ollama: brew install ollama && ollama pull llama3.2\n");
-            "http://localhost:11434/v1".to_string()
-        });
+impl SamplingParams {
+    pub const KEYS: [&'static str; 8] = [
+        "max_tokens",
+        "temperature",
+        "top_p",
+        "stop",
+        "seed",
+        "presence_penalty",
+        "frequency_penalty",
+        "n",
+    ];
 
-        let token = std::env::var("HUGGINGFACE_TOKEN").unwrap_or_else(|_| {
-            if base_url.contains("localhost") || base_url.contains("127.0.0.1") {
-                "unused".to_string()
-            } else {
-                eprintln!("\n⚠️  HUGGINGFACE_TOKEN not set but using remote API!");
-                eprintln!("   Please set your API token in the .env file.\n");
-                "missing-token".to_string()
+    /// Sets `key` from user input; `none`, `off` or `default` leave it to the server
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        let mut updated = self.clone();
+        match key {
+            "max_tokens" => updated.max_tokens = parse_optional(value)?,
+            "temperature" => updated.temperature = parse_optional(value)?,
+            "top_p" => updated.top_p = parse_optional(value)?,
+            "stop" => {
+                updated.stop = match is_cleared(value) {
+                    true => None,
+                    false => parse_stop(value),
+                }
+            }
+            "seed" => updated.seed = parse_optional(value)?,
+            "presence_penalty" => updated.presence_penalty = parse_optional(value)?,
+            "frequency_penalty" => updated.frequency_penalty = parse_optional(value)?,
+            "n" => updated.n = parse_optional(value)?,
+            other => {
+                return Err(format!(
+                    "unknown parameter {:?}, expected one of {}",
+                    other,
+                    Self::KEYS.join(", ")
+                ))
             }
-        });
+        }
+        updated.validate().map_err(|(_, e)| e)?;
+        *self = updated;
+        Ok(())
+    }
+
+    /// Checks every value against its accepted range, naming the first bad key
+    pub fn validate(&self) -> Result<(), (&'static str, String)> {
+        check_range("max_tokens", self.max_tokens, 1, i64::MAX)?;
+        check_range("temperature", self.temperature, 0.0, 2.0)?;
+        check_range("top_p", self.top_p, 0.0, 1.0)?;
+        check_range("presence_penalty", self.presence_penalty, -2.0, 2.0)?;
+        check_range("frequency_penalty", self.frequency_penalty, -2.0, 2.0)?;
+        check_range("n", self.n, 1, 128)?;
+        Ok(())
+    }
+
+    /// Current value of `key` for display, or `None` when the server default applies
+    pub fn get(&self, key: &str) -> Option<String> {
+        match key {
+            "max_tokens" => self.max_tokens.map(|v| v.to_string()),
+            "temperature" => self.temperature.map(|v| v.to_string()),
+            "top_p" => self.top_p.map(|v| v.to_string()),
+            "stop" => self.stop.as_ref().map(|stop| {
+                stop.iter()
+                    .map(|s| format!("{:?}", s))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }),
+            "seed" => self.seed.map(|v| v.to_string()),
+            "presence_penalty" => self.presence_penalty.map(|v| v.to_string()),
+            "frequency_penalty" => self.frequency_penalty.map(|v| v.to_string()),
+            "n" => self.n.map(|v| v.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Copies every configured parameter onto a request
+    pub fn apply(&self, req: &mut ChatCompletionRequest) {
+        req.max_tokens = self.max_tokens;
+        req.temperature = self.temperature;
+        req.top_p = self.top_p;
+        req.stop = self.stop.clone();
+        req.seed = self.seed;
+        req.presence_penalty = self.presence_penalty;
+        req.frequency_penalty = self.frequency_penalty;
+        req.n = self.n;
+    }
+}
+
+fn is_cleared(value: &str) -> bool {
+    matches!(value.to_lowercase().as_str(), "none" | "off" | "default")
+}
 
-        let model = std::env::var("HF_MODEL").unwrap_or_else(|_| "llama3.2".to_string());
-        let system_prompt = std::env::var("SYSTEM_PROMPT").ok();
+fn parse_optional<T>(value: &str) -> Result<Option<T>, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    if is_cleared(value) {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|e| format!("invalid value {:?} ({})", value, e))
+}
 
+fn check_range<T: PartialOrd + Display>(
+    key: &'static str,
+    value: Option<T>,
+    min: T,
+    max: T,
+) -> Result<(), (&'static str, String)> {
+    match value {
+        Some(value) if value < min || value > max => {
+            Err((key, format!("{} is out of range {}..={}", value, min, max)))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Splits comma-separated stop sequences, turning `\n` and `\t` into real newlines and tabs
+fn parse_stop(value: &str) -> Option<Vec<String>> {
+    let stop: Vec<String> = value
+        .split(',')
+        .map(|s| s.trim().replace("\\n", "\n").replace("\\t", "\t"))
+        .filter(|s| !s.is_empty())
+        .collect();
+    (!stop.is_empty()).then_some(stop)
+}
+
+/// Which wire protocol to speak with the endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// Any OpenAI-compatible `/chat/completions` API (HF router, vLLM, Ollama `/v1`, ...)
+    OpenAi,
+    /// Ollama's native `/api/chat`
+    Ollama,
+    /// Text Generation Inference `/generate` and `/generate_stream`
+    Tgi,
+}
+
+impl FromStr for BackendKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "openai" => Ok(BackendKind::OpenAi),
+            "ollama" => Ok(BackendKind::Ollama),
+            "tgi" => Ok(BackendKind::Tgi),
+            other => Err(format!("expected openai, ollama or tgi, got {:?}", other)),
+        }
+    }
+}
+
+/// Chat template used to flatten messages into a raw prompt for TGI's `/generate`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptFormat {
+    ChatMl,
+    Llama3,
+    Mistral,
+    Plain,
+}
+
+impl FromStr for PromptFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "chatml" => Ok(PromptFormat::ChatMl),
+            "llama3" => Ok(PromptFormat::Llama3),
+            "mistral" => Ok(PromptFormat::Mistral),
+            "plain" => Ok(PromptFormat::Plain),
+            other => Err(format!(
+                "expected chatml, llama3, mistral or plain, got {:?}",
+                other
+            )),
+        }
+    }
+}
+
+/// Settings that only apply to one backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackendOptions {
+    /// Ollama: how long the model stays loaded after a request (e.g. "10m", "-1")
+    pub keep_alive: Option<String>,
+    /// TGI: chat template for the raw prompt
+    pub prompt_format: PromptFormat,
+    /// TGI: guided generation grammar, e.g. `{"type": "json", "value": {...}}`
+    pub grammar: Option<serde_json::Value>,
+}
+
+impl Default for BackendOptions {
+    fn default() -> Self {
+        Self {
+            keep_alive: None,
+            prompt_format: PromptFormat::ChatMl,
+            grammar: None,
+        }
+    }
+}
+
+/// Retry policy for rate limits (429) and transient server errors (5xx)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    pub max_retries: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    /// How long to keep waiting for a cold-starting model before giving up
+    pub model_load_timeout_secs: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay_ms: 1000,
+            max_delay_ms: 30_000,
+            model_load_timeout_secs: 300,
+        }
+    }
+}
+
+/// Timeouts for the shared HTTP client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    pub connect_timeout_secs: u64,
+    /// Longest gap between two reads; a stream that keeps sending tokens never times out
+    pub read_timeout_secs: u64,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 10,
+            read_timeout_secs: 120,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Delay before retry number `attempt` (1-based): the server's `Retry-After` when given,
+    /// otherwise exponential backoff with jitter; either way capped at `max_delay_ms`
+    pub fn delay_for(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(retry_after) = retry_after {
+            return retry_after.min(Duration::from_millis(self.max_delay_ms));
+        }
+        let exponential = self
+            .base_delay_ms
+            .saturating_mul(1u64 << attempt.saturating_sub(1).min(16))
+            .min(self.max_delay_ms);
+        let jittered = rand::thread_rng().gen_range(exponential / 2..=exponential);
+        Duration::from_millis(jittered)
+    }
+}
+
+/// One source of settings, kept separate so errors can name where a value came from
+struct Layer {
+    source: String,
+    table: toml::Table,
+}
+
+impl Layer {
+    /// A layer holding a single value at `path`
+    fn value(source: &str, path: &[&str], value: toml::Value) -> Self {
+        let mut value = value;
+        for key in path.iter().skip(1).rev() {
+            let mut table = toml::Table::new();
+            table.insert(key.to_string(), value);
+            value = toml::Value::Table(table);
+        }
+        let mut table = toml::Table::new();
+        table.insert(path[0].to_string(), value);
+        Self {
+            source: source.to_string(),
+            table,
+        }
+    }
+
+    /// Reads a TOML file, rejecting unknown keys and mistyped values with their location
+    fn file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read config file {}: {}", path.display(), e))?;
+        let config = toml::from_str::<ChatConfig>(&text)
+            .map_err(|e| format!("Invalid config file {}:\n{}", path.display(), e))?;
+        for (name, profile) in &config.profiles {
+            let (settings, _) = split_profile(name, profile)
+                .map_err(|e| format!("Invalid config file {}:\n{}", path.display(), e))?;
+            toml::Value::Table(settings)
+                .try_into::<ChatConfig>()
+                .map_err(|e| {
+                    format!(
+                        "Invalid config file {}:\nprofiles.{}: {}",
+                        path.display(),
+                        name,
+                        e
+                    )
+                })?;
+        }
         Ok(Self {
-            base_url,
-            token,
-            model,
-            max_tokens: Some(500),
-            temperature: Some(0.7),
-            system_prompt,
-            max_context_messages: 20,
+            source: path.display().to_string(),
+            table: toml::from_str(&text)?,
         })
     }
+
+    fn contains(&self, path: &[String]) -> bool {
+        let mut table = &self.table;
+        for (i, key) in path.iter().enumerate() {
+            match table.get(key) {
+                Some(toml::Value::Table(inner)) if i + 1 < path.len() => table = inner,
+                Some(_) => return i + 1 == path.len(),
+                None => return false,
+            }
+        }
+        false
+    }
+}
+
+impl ChatConfig {
+    /// Builds the configuration from every layer, later ones winning: built-in defaults,
+    /// `~/.config/hugging-code/config.toml`, the nearest `.hugging-code.toml`, `--config`,
+    /// environment variables (including `.env`) and finally command-line flags.
+    ///
+    /// The selected profile is not applied yet, see [`ChatConfig::resolve_profile`].
+    /// Settings that work but are probably not what was meant come back as warnings, for
+    /// the caller to show where they don't get in the way.
+    pub fn load(cli: &Cli) -> Result<(Self, Vec<String>), Box<dyn Error>> {
+        Self::build(cli, &Sources::current(cli), None)
+    }
+
+    /// Like [`ChatConfig::load`], but never gives up: every problem is collected next to
+    /// the best configuration that could still be built, so `doctor` can report on them
+    /// and go on to check the endpoint.
+    pub fn load_best_effort(cli: &Cli) -> (Self, Vec<String>) {
+        Self::build_best_effort(cli, &Sources::current(cli))
+    }
+
+    pub(crate) fn build_best_effort(cli: &Cli, sources: &Sources) -> (Self, Vec<String>) {
+        let mut problems = Vec::new();
+        let config = match Self::build(cli, sources, Some(&mut problems)) {
+            Ok((config, _)) => config,
+            Err(e) => {
+                problems.push(e.to_string());
+                Self::default()
+            }
+        };
+        (config, problems)
+    }
+
+    /// Fails on the first problem unless `problems` collects them, in which case a bad
+    /// layer is left out, a bad token left empty and an unknown profile ignored
+    fn build(
+        cli: &Cli,
+        sources: &Sources,
+        mut problems: Option<&mut Vec<String>>,
+    ) -> Result<(Self, Vec<String>), Box<dyn Error>> {
+        let mut report = |e: Box<dyn Error>| match problems.as_deref_mut() {
+            Some(problems) => {
+                problems.push(e.to_string());
+                Ok(())
+            }
+            None => Err(e),
+        };
+        let mut layers = Vec::new();
+        for path in &sources.files {
+            match Layer::file(path) {
+                Ok(layer) => layers.push(layer),
+                Err(e) => report(e)?,
+            }
+        }
+        let (env, errors) = env_layers(&sources.env);
+        layers.extend(env);
+        for e in errors {
+            report(e)?;
+        }
+        match cli_layers(cli) {
+            Ok(flags) => layers.extend(flags),
+            Err(e) => report(e)?,
+        }
+
+        let mut merged = toml::Table::new();
+        for layer in &layers {
+            merge_layer(&mut merged, &layer.table);
+        }
+        let base_url_set = merged.contains_key("base_url");
+        let mut config: ChatConfig = match toml::Value::Table(merged).try_into() {
+            Ok(config) => config,
+            Err(e) => {
+                report(format!("Invalid configuration: {}", e).into())?;
+                ChatConfig::default()
+            }
+        };
+        config.env = LoadedEnv(sources.env.clone());
+
+        // With a profile selected the token is resolved once the profile is applied
+        if config.profile.is_none() {
+            if let Err(e) = config.resolve_token(&sources.env) {
+                report(format!("Invalid configuration: {}", e).into())?;
+            }
+        }
+        if let Err((path, message)) = config.validate() {
+            let source = layers
+                .iter()
+                .rev()
+                .find(|layer| layer.contains(&path))
+                .map_or("the built-in defaults", |layer| layer.source.as_str());
+            report(
+                format!(
+                    "Invalid value for {} in {}: {}",
+                    path.join("."),
+                    source,
+                    message
+                )
+                .into(),
+            )?;
+        }
+
+        for name in config.profiles.keys() {
+            if let Err(e) = config.overlay_profile(name) {
+                report(e.into())?;
+            }
+        }
+        if let Some(name) = config.profile.clone() {
+            if !config.profiles.contains_key(&name) {
+                report(config.unknown_profile(&name).into())?;
+                config.profile = None;
+            }
+        }
+
+        let mut warnings = Vec::new();
+        let profile_base_url = config
+            .profile
+            .as_ref()
+            .and_then(|name| config.profiles.get(name))
+            .is_some_and(|profile| profile.contains_key("base_url"));
+        if !base_url_set && !profile_base_url {
+            warnings.push(
+                "No HF_BASE_URL set. Run `hugging-code doctor` to check your setup.\n   \
+                 Tip: For free local usage, install Ollama: brew install ollama && ollama pull llama3.2"
+                    .to_string(),
+            );
+        }
+
+        let local = config.base_url.contains("localhost") || config.base_url.contains("127.0.0.1");
+        if config.token.is_empty() && !local && config.profile.is_none() {
+            warnings.push(
+                "HUGGINGFACE_TOKEN not set but using remote API!\n   \
+                 Set it in the .env file, or configure token_file or token_command."
+                    .to_string(),
+            );
+        }
+
+        Ok((config, warnings))
+    }
+
+    /// The settings to use: these with the selected profile, if any, applied on top
+    pub fn resolve_profile(&self) -> Result<ChatConfig, String> {
+        match &self.profile {
+            Some(name) => self.with_profile(name),
+            None => Ok(self.clone()),
+        }
+    }
+
+    /// These settings with profile `name` applied on top, taking precedence over every
+    /// other source so switching profiles always switches endpoint
+    pub fn with_profile(&self, name: &str) -> Result<ChatConfig, String> {
+        let mut config = self.overlay_profile(name)?;
+        config
+            .resolve_token(&self.env.0)
+            .map_err(|e| format!("profiles.{}.{}", name, e))?;
+        Ok(config)
+    }
+
+    fn overlay_profile(&self, name: &str) -> Result<ChatConfig, String> {
+        let overlay = self
+            .profiles
+            .get(name)
+            .ok_or_else(|| self.unknown_profile(name))?;
+        let (overlay, inherit_token) = split_profile(name, overlay)?;
+        let mut table = toml::Table::try_from(self).map_err(|e| e.to_string())?;
+        // A token is only meant for the host it was configured for
+        let other_host = overlay
+            .get("base_url")
+            .and_then(toml::Value::as_str)
+            .is_some_and(|url| !authority(url).eq_ignore_ascii_case(authority(&self.base_url)));
+        if other_host && !inherit_token {
+            clear_token_source(&mut table);
+        }
+        merge_layer(&mut table, &overlay);
+        let mut config: ChatConfig = toml::Value::Table(table)
+            .try_into()
+            .map_err(|e| format!("Invalid profile {:?}: {}", name, e))?;
+        config.profile = Some(name.to_string());
+        config.env = self.env.clone();
+        config.validate().map_err(|(path, message)| {
+            format!(
+                "Invalid value for profiles.{}.{}: {}",
+                name,
+                path.join("."),
+                message
+            )
+        })?;
+        Ok(config)
+    }
+
+    fn unknown_profile(&self, name: &str) -> String {
+        if self.profiles.is_empty() {
+            format!("Unknown profile {:?}: no profiles are configured", name)
+        } else {
+            format!(
+                "Unknown profile {:?}, expected one of {}",
+                name,
+                self.profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        }
+    }
+
+    /// Fills in the token from its source, which [`merge_layer`] took from a single layer.
+    /// Should that layer set several, `token_command` goes before `token_file`, `token_env`
+    /// and `token` itself. Hugging Face endpoints without any of these fall back to the
+    /// token saved by `huggingface-cli login`.
+    fn resolve_token(&mut self, env: &Env) -> Result<(), String> {
+        if let Some(command) = &self.token_command {
+            self.token = run_token_command(command).map_err(|e| format!("token_command: {}", e))?;
+        } else if let Some(path) = &self.token_file {
+            self.token =
+                read_token_file(&expand_home(path)).map_err(|e| format!("token_file: {}", e))?;
+        } else if let Some(var) = &self.token_env {
+            self.token = env
+                .get(var)
+                .map(Token::new)
+                .ok_or_else(|| format!("token_env: environment variable {} is not set", var))?;
+        } else if self.token.is_empty() && is_hugging_face(&self.base_url) {
+            // `huggingface-cli login` writes this file with the default umask, usually 0644,
+            // so it is read as it is; an unreadable one leaves the token unset
+            if let Some(token) = hf_token_path(env).and_then(|path| read_token(&path).ok()) {
+                self.token = token;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks values whose type alone doesn't make them valid, returning the offending key
+    fn validate(&self) -> Result<(), (Vec<String>, String)> {
+        let at = |path: &[&str], message: &str| {
+            Err((
+                path.iter().map(|key| key.to_string()).collect(),
+                message.to_string(),
+            ))
+        };
+
+        if !self.base_url.starts_with("http://") && !self.base_url.starts_with("https://") {
+            return at(&["base_url"], "expected an http:// or https:// URL");
+        }
+        if self.model.trim().is_empty() {
+            return at(&["model"], "must not be empty");
+        }
+        if self.max_context_messages == Some(0) {
+            return at(&["max_context_messages"], "must be at least 1");
+        }
+        if self.params.max_tokens.unwrap_or(0) >= self.context_length as i64 {
+            return at(
+                &["context_length"],
+                "must be larger than params.max_tokens to leave room for the prompt",
+            );
+        }
+        if let Err((key, message)) = self.params.validate() {
+            return at(&["params", key], &message);
+        }
+        if self.retry.base_delay_ms > self.retry.max_delay_ms {
+            return at(
+                &["retry", "base_delay_ms"],
+                "must not be larger than retry.max_delay_ms",
+            );
+        }
+        if self.http.connect_timeout_secs == 0 {
+            return at(&["http", "connect_timeout_secs"], "must be at least 1");
+        }
+        if self.http.read_timeout_secs == 0 {
+            return at(&["http", "read_timeout_secs"], "must be at least 1");
+        }
+        for (model, price) in &self.prices {
+            if price.input < 0.0 || price.output < 0.0 {
+                return at(&["prices", model], "prices must not be negative");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs `token_command` through the shell and returns its trimmed output.
+///
+/// Stdin is closed so the command can't swallow input meant for `--stdin`; stderr is
+/// passed through so password managers can still prompt.
+fn run_token_command(command: &str) -> Result<Token, String> {
+    #[cfg(windows)]
+    let mut shell = Command::new("cmd");
+    #[cfg(windows)]
+    shell.arg("/C");
+    #[cfg(not(windows))]
+    let mut shell = Command::new("sh");
+    #[cfg(not(windows))]
+    shell.arg("-c");
+
+    let output = shell
+        .arg(command)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| format!("could not run {:?}: {}", command, e))?;
+    if !output.status.success() {
+        return Err(format!("{:?} failed with {}", command, output.status));
+    }
+    let token = String::from_utf8(output.stdout)
+        .map_err(|_| format!("{:?} printed something that is not UTF-8", command))?;
+    match token.trim() {
+        "" => Err(format!("{:?} printed nothing", command)),
+        token => Ok(Token::new(token)),
+    }
+}
+
+/// Reads a token file, refusing files that other users could read
+fn read_token_file(path: &Path) -> Result<Token, String> {
+    check_private(path)?;
+    read_token(path)
+}
+
+/// Reads a file holding nothing but a token
+fn read_token(path: &Path) -> Result<Token, String> {
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    match text.trim() {
+        "" => Err(format!("{} is empty", path.display())),
+        token => Ok(Token::new(token)),
+    }
+}
+
+#[cfg(unix)]
+fn check_private(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path)
+        .map_err(|e| format!("cannot read {}: {}", path.display(), e))?
+        .permissions()
+        .mode();
+    if mode & 0o077 != 0 {
+        return Err(format!(
+            "{} is accessible by other users (mode {:o}), run `chmod 600 {}`",
+            path.display(),
+            mode & 0o777,
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_private(_path: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+/// Expands a leading `~/` to the home directory
+pub(crate) fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Where `huggingface-cli login` saves the token: `$HF_TOKEN_PATH`, `$HF_HOME/token`,
+/// or `~/.cache/huggingface/token`
+fn hf_token_path(env: &Env) -> Option<PathBuf> {
+    if let Some(path) = env.get("HF_TOKEN_PATH") {
+        return Some(PathBuf::from(path));
+    }
+    let hf_home = env.get("HF_HOME").map(PathBuf::from).or_else(|| {
+        env.get("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                env.get("HOME")
+                    .map(|home| PathBuf::from(home).join(".cache"))
+            })
+            .map(|cache| cache.join("huggingface"))
+    })?;
+    Some(hf_home.join("token"))
+}
+
+/// Whether `base_url` points at Hugging Face, the only place the saved login token is sent
+pub(crate) fn is_hugging_face(base_url: &str) -> bool {
+    let host = authority(base_url).split(':').next().unwrap_or_default();
+    ["huggingface.co", "hf.co", "huggingface.cloud"]
+        .iter()
+        .any(|domain| host == *domain || host.ends_with(&format!(".{}", domain)))
+}
+
+/// `host[:port]` of a URL, e.g. `localhost:11434`
+fn authority(url: &str) -> &str {
+    url.split("://")
+        .nth(1)
+        .and_then(|rest| rest.split('/').next())
+        .unwrap_or_default()
+}
+
+/// Environment variables by name
+pub(crate) type Env = HashMap<String, String>;
+
+/// The process environment; variables that are not valid UTF-8 are left out
+fn process_env() -> Env {
+    std::env::vars_os()
+        .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+        .collect()
+}
+
+/// What [`ChatConfig::build`] reads below the command-line flags
+pub(crate) struct Sources {
+    /// Config files that exist, lowest precedence first
+    pub(crate) files: Vec<PathBuf>,
+    pub(crate) env: Env,
+}
+
+impl Sources {
+    /// The user and project config files, `--config`, and the environment with `.env`
+    /// loaded into it
+    fn current(cli: &Cli) -> Self {
+        dotenv::dotenv().ok();
+        Self {
+            files: [
+                user_config_path(),
+                project_config_path(),
+                cli.config.clone(),
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+            env: process_env(),
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/hugging-code/config.toml`, falling back to `~/.config`, if it exists
+fn user_config_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    let path = config_home.join("hugging-code").join("config.toml");
+    path.is_file().then_some(path)
+}
+
+/// The nearest `.hugging-code.toml` in the working directory or one of its parents
+fn project_config_path() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Separates a profile's settings from `inherit_token`, which only profiles have: whether
+/// the token goes along to another `base_url` host
+fn split_profile(name: &str, profile: &toml::Table) -> Result<(toml::Table, bool), String> {
+    let mut settings = profile.clone();
+    match settings.remove("inherit_token") {
+        None => Ok((settings, false)),
+        Some(toml::Value::Boolean(inherit)) => Ok((settings, inherit)),
+        Some(_) => Err(format!(
+            "Invalid value for profiles.{}.inherit_token: expected true or false",
+            name
+        )),
+    }
+}
+
+/// Keys naming where the token comes from
+const TOKEN_SOURCES: [&str; 4] = ["token_command", "token_file", "token_env", "token"];
+
+/// Like [`merge_tables`], except that a layer setting any token source replaces the
+/// source of the layers below, so e.g. `HUGGINGFACE_TOKEN` beats a config file's
+/// `token_command` instead of losing to it
+fn merge_layer(base: &mut toml::Table, overlay: &toml::Table) {
+    if TOKEN_SOURCES.iter().any(|key| overlay.contains_key(*key)) {
+        clear_token_source(base);
+    }
+    merge_tables(base, overlay);
+}
+
+fn clear_token_source(table: &mut toml::Table) {
+    for key in TOKEN_SOURCES {
+        table.remove(key);
+    }
+}
+
+/// Recursively copies `overlay` into `base`; tables merge, any other value replaces
+fn merge_tables(base: &mut toml::Table, overlay: &toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge_tables(base_table, overlay_table)
+            }
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// One layer per environment variable that is set, so errors can name the variable. A bad
+/// value is returned as an error of its own and leaves out only that variable.
+fn env_layers(env: &Env) -> (Vec<Layer>, Vec<Box<dyn Error>>) {
+    let mut layers = Vec::new();
+    let mut errors = Vec::new();
+    let mut add = |layer: Result<Option<Layer>, Box<dyn Error>>| match layer {
+        Ok(layer) => layers.extend(layer),
+        Err(e) => errors.push(e),
+    };
+    add(env_layer::<String>(env, "HF_BASE_URL", &["base_url"]));
+    add(env_layer::<String>(env, "HUGGINGFACE_TOKEN", &["token"]));
+    add(env_layer::<String>(env, "HF_TOKEN_FILE", &["token_file"]));
+    add(env_layer::<String>(
+        env,
+        "HF_TOKEN_COMMAND",
+        &["token_command"],
+    ));
+    add(env_layer::<String>(env, "HF_MODEL", &["model"]));
+    add(env_layer::<String>(env, "HF_PROFILE", &["profile"]));
+    add(env_layer::<String>(
+        env,
+        "SYSTEM_PROMPT",
+        &["system_prompt"],
+    ));
+    add(env_layer::<u64>(
+        env,
+        "HF_MAX_CONTEXT_MESSAGES",
+        &["max_context_messages"],
+    ));
+    add(env_layer::<u64>(
+        env,
+        "HF_CONTEXT_LENGTH",
+        &["context_length"],
+    ));
+    add(env_layer::<String>(env, "HF_TOKENIZER", &["tokenizer"]));
+    add(env_layer::<bool>(env, "HF_COMPACTION", &["compaction"]));
+    add(env_layer::<bool>(env, "HF_AUTOSAVE", &["autosave"]));
+    add(env_layer::<String>(
+        env,
+        "HF_SESSIONS_DIR",
+        &["sessions_dir"],
+    ));
+    add(env_layer::<u32>(
+        env,
+        "HF_MAX_RETRIES",
+        &["retry", "max_retries"],
+    ));
+    add(env_layer::<u64>(
+        env,
+        "HF_RETRY_BASE_MS",
+        &["retry", "base_delay_ms"],
+    ));
+    add(env_layer::<u64>(
+        env,
+        "HF_RETRY_MAX_MS",
+        &["retry", "max_delay_ms"],
+    ));
+    add(env_layer::<u64>(
+        env,
+        "HF_MODEL_LOAD_TIMEOUT",
+        &["retry", "model_load_timeout_secs"],
+    ));
+    add(env_layer::<u64>(
+        env,
+        "HF_CONNECT_TIMEOUT",
+        &["http", "connect_timeout_secs"],
+    ));
+    add(env_layer::<u64>(
+        env,
+        "HF_READ_TIMEOUT",
+        &["http", "read_timeout_secs"],
+    ));
+    add(env_layer::<BackendKind>(env, "HF_BACKEND", &["backend"]));
+    add(env_layer::<String>(
+        env,
+        "OLLAMA_KEEP_ALIVE",
+        &["backend_options", "keep_alive"],
+    ));
+    add(env_layer::<PromptFormat>(
+        env,
+        "TGI_PROMPT_FORMAT",
+        &["backend_options", "prompt_format"],
+    ));
+    add(env_layer::<serde_json::Value>(
+        env,
+        "TGI_GRAMMAR",
+        &["backend_options", "grammar"],
+    ));
+    add(env_layer::<serde_json::Value>(
+        env,
+        "HF_PRICES",
+        &["prices"],
+    ));
+
+    for key in SamplingParams::KEYS {
+        add(param_layer(env, key));
+    }
+    (layers, errors)
+}
+
+fn param_layer(env: &Env, key: &str) -> Result<Option<Layer>, Box<dyn Error>> {
+    let var = format!("HF_{}", key.to_uppercase());
+    let Some(value) = env.get(&var) else {
+        return Ok(None);
+    };
+    let mut params = SamplingParams::default();
+    params
+        .set(key, value)
+        .map_err(|e| format!("Invalid value for {}: {}", var, e))?;
+    let serialized = toml::Table::try_from(&params)?;
+    Ok(serialized
+        .get(key)
+        .map(|value| Layer::value(&var, &["params", key], value.clone())))
+}
+
+fn env_layer<T>(env: &Env, var: &str, path: &[&str]) -> Result<Option<Layer>, Box<dyn Error>>
+where
+    T: FromStr + Serialize,
+    T::Err: Display,
+{
+    let Some(value) = env_parse::<T>(env, var)? else {
+        return Ok(None);
+    };
+    let value =
+        toml::Value::try_from(value).map_err(|e| format!("Invalid value for {}: {}", var, e))?;
+    Ok(Some(Layer::value(var, path, value)))
+}
+
+fn cli_layers(cli: &Cli) -> Result<Vec<Layer>, Box<dyn Error>> {
+    let mut layers = Vec::new();
+    let mut push = |flag: &str, path: &[&str], value: Option<toml::Value>| {
+        if let Some(value) = value {
+            layers.push(Layer::value(flag, path, value));
+        }
+    };
+    push(
+        "--base-url",
+        &["base_url"],
+        cli.base_url.clone().map(toml::Value::String),
+    );
+    push(
+        "--model",
+        &["model"],
+        cli.model.clone().map(toml::Value::String),
+    );
+    push(
+        "--profile",
+        &["profile"],
+        cli.profile.clone().map(toml::Value::String),
+    );
+    push(
+        "--backend",
+        &["backend"],
+        cli.backend.map(toml::Value::try_from).transpose()?,
+    );
+    push(
+        "--system-prompt",
+        &["system_prompt"],
+        cli.system_prompt.clone().map(toml::Value::String),
+    );
+    push(
+        "--max-tokens",
+        &["params", "max_tokens"],
+        cli.max_tokens.map(toml::Value::Integer),
+    );
+    push(
+        "--temperature",
+        &["params", "temperature"],
+        cli.temperature.map(toml::Value::Float),
+    );
+    push(
+        "--max-context-messages",
+        &["max_context_messages"],
+        cli.max_context_messages
+            .map(|n| toml::Value::Integer(n as i64)),
+    );
+    push(
+        "--context-length",
+        &["context_length"],
+        cli.context_length.map(|n| toml::Value::Integer(n as i64)),
+    );
+    Ok(layers)
+}
+
+/// Reads and parses an optional environment variable, naming it in the error on bad input
+fn env_parse<T: FromStr>(env: &Env, key: &str) -> Result<Option<T>, Box<dyn Error>>
+where
+    T::Err: std::fmt::Display,
+{
+    match env.get(key) {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|e| format!("Invalid value for {}: {:?} ({})", key, value, e).into()),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use clap::Parser;
+
+    fn table(toml: &str) -> toml::Table {
+        toml.parse().unwrap()
+    }
+
+    #[test]
+    fn a_later_layer_replaces_the_token_source() {
+        let mut merged = toml::Table::new();
+        merge_layer(
+            &mut merged,
+            &table("token_command = \"pass show hf\"\nmodel = \"a\""),
+        );
+        merge_layer(&mut merged, &table("token = \"hf_env\""));
+        assert_eq!(merged, table("token = \"hf_env\"\nmodel = \"a\""));
+
+        // Layers without a token source leave it alone
+        merge_layer(&mut merged, &table("model = \"b\""));
+        assert_eq!(merged, table("token = \"hf_env\"\nmodel = \"b\""));
+
+        merge_layer(&mut merged, &table("token_file = \"~/.hf\""));
+        assert_eq!(merged, table("token_file = \"~/.hf\"\nmodel = \"b\""));
+    }
+
+    #[test]
+    fn a_file_setting_several_token_sources_prefers_the_command() {
+        let mut config: ChatConfig = toml::Value::Table(table(
+            "token = \"hf_plain\"\ntoken_command = \"echo hf_from_command\"",
+        ))
+        .try_into()
+        .unwrap();
+        config.resolve_token(&Env::new()).unwrap();
+        assert_eq!(config.token.expose(), "hf_from_command");
+    }
+
+    fn config(toml: &str) -> ChatConfig {
+        toml::Value::Table(table(toml)).try_into().unwrap()
+    }
+
+    #[cfg(unix)]
+    fn token_file(dir: &TempDir, name: &str, mode: u32) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = dir.file(name, "hf_saved_token\n");
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        path
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_token_file_must_be_private() {
+        let dir = TempDir::new("config-token-file");
+        let shared = token_file(&dir, "shared", 0o644);
+        let mut config = ChatConfig {
+            token_file: Some(shared.clone()),
+            ..ChatConfig::default()
+        };
+        let e = config.resolve_token(&Env::new()).unwrap_err();
+        assert!(e.contains("chmod 600"), "{}", e);
+        assert!(config.token.is_empty());
+
+        config.token_file = Some(token_file(&dir, "private", 0o600));
+        config.resolve_token(&Env::new()).unwrap();
+        assert_eq!(config.token.expose(), "hf_saved_token");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn the_hugging_face_login_token_is_read_as_written() {
+        let dir = TempDir::new("config-hf-login");
+        let saved = token_file(&dir, "token", 0o644);
+        let env: Env = [(
+            "HF_TOKEN_PATH".to_string(),
+            saved.to_str().unwrap().to_string(),
+        )]
+        .into();
+
+        let mut config = ChatConfig {
+            base_url: "https://router.huggingface.co/v1".to_string(),
+            ..ChatConfig::default()
+        };
+        config.resolve_token(&env).unwrap();
+        assert_eq!(config.token.expose(), "hf_saved_token");
+
+        // It is never sent anywhere else
+        let mut config = ChatConfig {
+            base_url: "https://llm.example.com/v1".to_string(),
+            ..ChatConfig::default()
+        };
+        config.resolve_token(&env).unwrap();
+        assert!(config.token.is_empty());
+    }
+
+    const PROFILES: &str = r#"
+        base_url = "https://router.huggingface.co/v1"
+        token = "hf_base"
+        token_command = "pass show hf"
+
+        [profiles.same_host]
+        model = "Qwen/Qwen2.5-Coder-32B-Instruct"
+
+        [profiles.own_token]
+        token_env = "OTHER_TOKEN"
+
+        [profiles.local]
+        base_url = "http://localhost:11434"
+
+        [profiles.proxy]
+        base_url = "https://llm-proxy.example.com/v1"
+        inherit_token = true
+    "#;
+
+    #[test]
+    fn a_profile_keeps_the_token_for_the_same_host() {
+        let profile = config(PROFILES).overlay_profile("same_host").unwrap();
+        assert_eq!(profile.token.expose(), "hf_base");
+        assert_eq!(profile.token_command.as_deref(), Some("pass show hf"));
+    }
+
+    #[test]
+    fn a_profile_token_source_replaces_the_base_one() {
+        let profile = config(PROFILES).overlay_profile("own_token").unwrap();
+        assert_eq!(profile.token_env.as_deref(), Some("OTHER_TOKEN"));
+        assert_eq!(profile.token_command, None);
+        assert!(profile.token.is_empty());
+    }
+
+    #[test]
+    fn a_profile_on_another_host_does_not_inherit_the_token() {
+        let base = config(PROFILES);
+        let local = base.overlay_profile("local").unwrap();
+        assert!(local.token.is_empty());
+        assert_eq!(local.token_command, None);
+
+        let proxy = base.overlay_profile("proxy").unwrap();
+        assert_eq!(proxy.token.expose(), "hf_base");
+        assert_eq!(proxy.token_command.as_deref(), Some("pass show hf"));
+    }
+
+    #[test]
+    fn inherit_token_must_be_a_bool() {
+        let base = config(
+            "[profiles.proxy]\nbase_url = \"http://localhost:8080\"\ninherit_token = \"yes\"",
+        );
+        let e = base.overlay_profile("proxy").unwrap_err();
+        assert!(e.contains("profiles.proxy.inherit_token"), "{}", e);
+    }
+
+    #[test]
+    fn answers_per_request_must_be_in_range() {
+        let mut params = SamplingParams::default();
+        params.set("n", "3").unwrap();
+        assert_eq!(params.n, Some(3));
+        let e = params.set("n", "0").unwrap_err();
+        assert!(e.contains("out of range"), "{}", e);
+        assert_eq!(params.n, Some(3));
+        params.set("n", "none").unwrap();
+        assert_eq!(params.n, None);
+    }
+
+    #[test]
+    fn retries_back_off_exponentially_up_to_the_cap() {
+        let retry = RetryConfig {
+            base_delay_ms: 1000,
+            max_delay_ms: 5000,
+            ..RetryConfig::default()
+        };
+        for (attempt, full) in [(1, 1000), (2, 2000), (3, 4000), (4, 5000), (40, 5000)] {
+            let delay = retry.delay_for(attempt, None);
+            assert!(
+                delay >= Duration::from_millis(full / 2) && delay <= Duration::from_millis(full),
+                "attempt {}: {:?}",
+                attempt,
+                delay
+            );
+        }
+        assert_eq!(
+            retry.delay_for(1, Some(Duration::from_secs(3))),
+            Duration::from_secs(3)
+        );
+        // A server cannot hold the client for longer than the cap
+        assert_eq!(
+            retry.delay_for(1, Some(Duration::from_secs(9))),
+            Duration::from_secs(5)
+        );
+    }
+
+    #[test]
+    fn config_files_accept_inherit_token_in_profiles_only() {
+        let dir = TempDir::new("config-inherit");
+        let file = dir.file(
+            "inherit.toml",
+            "[profiles.proxy]\nbase_url = \"http://localhost:8080\"\ninherit_token = true\n",
+        );
+        assert!(Layer::file(&file).is_ok());
+
+        let file = dir.file("inherit-top.toml", "inherit_token = true\n");
+        let e = Layer::file(&file).err().unwrap().to_string();
+        assert!(e.contains("unknown field `inherit_token`"), "{}", e);
+    }
+
+    fn cli(args: &[&str]) -> Cli {
+        Cli::parse_from(std::iter::once("hugging-code").chain(args.iter().copied()))
+    }
+
+    /// Config files and environment variables standing in for the real ones
+    fn sources(files: &[&Path], env: &[(&str, &str)]) -> Sources {
+        Sources {
+            files: files.iter().map(|file| file.to_path_buf()).collect(),
+            env: env
+                .iter()
+                .map(|(var, value)| (var.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    fn load(cli_args: &[&str], sources: &Sources) -> Result<ChatConfig, Box<dyn Error>> {
+        ChatConfig::build(&cli(cli_args), sources, None).map(|(config, _)| config)
+    }
+
+    fn warnings(cli_args: &[&str], sources: &Sources) -> Vec<String> {
+        ChatConfig::build(&cli(cli_args), sources, None).unwrap().1
+    }
+
+    #[test]
+    fn a_profile_switched_to_later_reads_the_environment_the_config_came_from() {
+        let dir = TempDir::new("config-profile-env");
+        let file = dir.file(
+            "profiles.toml",
+            "[profiles.remote]\nbase_url = \"https://llm.example.com/v1\"\n\
+             token_env = \"REMOTE_LLM_TOKEN\"\n",
+        );
+        let config = load(
+            &[],
+            &sources(&[&file], &[("REMOTE_LLM_TOKEN", "remote-secret")]),
+        )
+        .unwrap();
+        assert!(config.token.is_empty());
+        let remote = config.with_profile("remote").unwrap();
+        assert_eq!(remote.token.expose(), "remote-secret");
+        // And again from the profile's own settings, as `/profile` does
+        let again = remote.with_profile("remote").unwrap();
+        assert_eq!(again.token.expose(), "remote-secret");
+    }
+
+    #[test]
+    fn warns_about_a_missing_base_url_unless_a_profile_sets_one() {
+        let dir = TempDir::new("config-warnings");
+        let file = dir.file(
+            "profiles.toml",
+            "[profiles.local]\nbase_url = \"http://localhost:8080/v1\"\n\
+             [profiles.small]\nmodel = \"llama3.2:1b\"\n",
+        );
+        let sources = sources(&[&file], &[]);
+        let no_base_url = |warnings: &[String]| {
+            warnings
+                .iter()
+                .any(|warning| warning.starts_with("No HF_BASE_URL set"))
+        };
+
+        assert!(no_base_url(&warnings(&[], &sources)));
+        assert!(no_base_url(&warnings(&["--profile", "small"], &sources)));
+        assert!(!no_base_url(&warnings(&["--profile", "local"], &sources)));
+        assert_eq!(
+            warnings(&["--base-url", "http://localhost:8080/v1"], &sources),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn warns_about_a_remote_endpoint_without_a_token() {
+        let remote = sources(&[], &[("HF_BASE_URL", "https://llm.example.com/v1")]);
+        let warnings = warnings(&[], &remote);
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(warnings[0].starts_with("HUGGINGFACE_TOKEN not set"));
+
+        let remote = sources(
+            &[],
+            &[
+                ("HF_BASE_URL", "https://llm.example.com/v1"),
+                ("HUGGINGFACE_TOKEN", "hf_secret"),
+            ],
+        );
+        assert_eq!(self::warnings(&[], &remote), Vec::<String>::new());
+    }
+
+    #[test]
+    fn later_layers_win_and_tables_merge() {
+        let dir = TempDir::new("config-layers");
+        let user = dir.file(
+            "layers-user.toml",
+            "model = \"user-model\"\nsystem_prompt = \"user\"\n[retry]\nmax_retries = 7\n",
+        );
+        let project = dir.file(
+            "layers-project.toml",
+            "model = \"project-model\"\n[params]\ntemperature = 0.2\n",
+        );
+        let sources = sources(
+            &[&user, &project],
+            &[("HF_MODEL", "env-model"), ("HF_TEMPERATURE", "0.5")],
+        );
+        let config = load(&["--temperature", "0.9"], &sources).unwrap();
+        assert_eq!(config.model, "env-model");
+        assert_eq!(config.system_prompt.as_deref(), Some("user"));
+        assert_eq!(config.params.temperature, Some(0.9));
+
+        let config = load(&["--model", "cli-model"], &sources).unwrap();
+        assert_eq!(config.model, "cli-model");
+        assert_eq!(config.params.temperature, Some(0.5));
+        // Keys the file and flags leave alone keep their defaults
+        assert_eq!(
+            config.params.max_tokens,
+            SamplingParams::default().max_tokens
+        );
+        assert_eq!(config.retry.max_retries, 7);
+        assert_eq!(
+            config.retry.base_delay_ms,
+            RetryConfig::default().base_delay_ms
+        );
+    }
+
+    #[test]
+    fn the_environment_token_beats_a_file_token_command() {
+        let dir = TempDir::new("config-token");
+        let file = dir.file("token-command.toml", "token_command = \"exit 1\"\n");
+        let config = load(&[], &sources(&[&file], &[("HUGGINGFACE_TOKEN", "hf_env")])).unwrap();
+        assert_eq!(config.token.expose(), "hf_env");
+        assert_eq!(config.token_command, None);
+
+        let e = load(&[], &sources(&[&file], &[]))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(e.contains("token_command"), "{}", e);
+    }
+
+    #[test]
+    fn invalid_values_name_the_layer_they_came_from() {
+        let dir = TempDir::new("config-invalid");
+        let file = dir.file("small-context.toml", "context_length = 256\n");
+        let path = file.to_str().unwrap();
+        let from_file = sources(&[&file], &[]);
+        let e = load(&[], &from_file).err().unwrap().to_string();
+        assert_eq!(
+            e,
+            format!(
+                "Invalid value for context_length in {}: must be larger than params.max_tokens \
+                 to leave room for the prompt",
+                path
+            )
+        );
+
+        // A later layer can make it valid again
+        let config = load(&["--max-tokens", "100"], &from_file).unwrap();
+        assert_eq!(config.context_length, 256);
+
+        let e = load(&[], &sources(&[], &[("HF_CONTEXT_LENGTH", "lots")]))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(
+            e.starts_with("Invalid value for HF_CONTEXT_LENGTH"),
+            "{}",
+            e
+        );
+    }
+
+    #[test]
+    fn config_files_reject_unknown_keys_and_mistyped_values() {
+        let dir = TempDir::new("config-typos");
+        let file = dir.file("typo.toml", "modle = \"llama3.2\"\n");
+        let e = Layer::file(&file).err().unwrap().to_string();
+        assert!(e.contains(file.to_str().unwrap()), "{}", e);
+        assert!(e.contains("unknown field `modle`"), "{}", e);
+
+        let file = dir.file("mistyped.toml", "context_length = \"big\"\n");
+        let e = Layer::file(&file).err().unwrap().to_string();
+        assert!(e.contains("context_length"), "{}", e);
+
+        let file = dir.file(
+            "bad-profile.toml",
+            "[profiles.x]\nbackend = \"llamafile\"\n",
+        );
+        let e = Layer::file(&file).err().unwrap().to_string();
+        assert!(e.contains("profiles.x"), "{}", e);
+    }
+
+    #[test]
+    fn single_value_layers_nest_their_path() {
+        let layer = Layer::value(
+            "HF_MAX_RETRIES",
+            &["retry", "max_retries"],
+            toml::Value::Integer(5),
+        );
+        assert_eq!(layer.table, table("[retry]\nmax_retries = 5"));
+        assert!(layer.contains(&["retry".to_string(), "max_retries".to_string()]));
+        assert!(!layer.contains(&["retry".to_string(), "base_delay_ms".to_string()]));
+        assert!(!layer.contains(&["model".to_string()]));
+    }
+
+    #[test]
+    fn validate_names_the_offending_key() {
+        assert_eq!(ChatConfig::default().validate(), Ok(()));
+        for (settings, path) in [
+            ("base_url = \"localhost:8080\"", vec!["base_url"]),
+            ("model = \" \"", vec!["model"]),
+            ("max_context_messages = 0", vec!["max_context_messages"]),
+            ("context_length = 500", vec!["context_length"]),
+            (
+                "params = { temperature = 2.5 }",
+                vec!["params", "temperature"],
+            ),
+            ("params = { max_tokens = 0 }", vec!["params", "max_tokens"]),
+            (
+                "retry = { base_delay_ms = 10, max_delay_ms = 5 }",
+                vec!["retry", "base_delay_ms"],
+            ),
+            (
+                "http = { connect_timeout_secs = 0 }",
+                vec!["http", "connect_timeout_secs"],
+            ),
+            (
+                "http = { read_timeout_secs = 0 }",
+                vec!["http", "read_timeout_secs"],
+            ),
+            (
+                "prices = { m = { input = -1.0, output = 0.0 } }",
+                vec!["prices", "m"],
+            ),
+        ] {
+            let (at, _) = config(settings).validate().unwrap_err();
+            assert_eq!(at, path, "{}", settings);
+        }
+    }
+
+    #[test]
+    fn best_effort_load_reports_a_broken_file_and_keeps_the_rest() {
+        let dir = TempDir::new("config-best-effort");
+        let file = dir.file("broken.toml", "model = \n");
+        let cli = cli(&["--model", "llama3.2", "doctor"]);
+        let sources = sources(&[&file], &[("HF_TEMPERATURE", "hot")]);
+
+        assert!(ChatConfig::build(&cli, &sources, None).is_err());
+        let (config, problems) = ChatConfig::build_best_effort(&cli, &sources);
+        assert!(
+            problems
+                .iter()
+                .any(|problem| problem.contains(file.to_str().unwrap())),
+            "{:?}",
+            problems
+        );
+        assert!(
+            problems
+                .iter()
+                .any(|problem| problem.contains("HF_TEMPERATURE")),
+            "{:?}",
+            problems
+        );
+        assert_eq!(config.model, "llama3.2");
+    }
 }
@@ -0,0 +1,254 @@
+use crate::{
+    config::ChatConfig,
+    tokenizer::TokenCounter,
+    types::{Message, Role},
+};
+use std::borrow::Borrow;
+
+/// Tokens kept free for the answer when `max_tokens` is left to the server
+pub const DEFAULT_ANSWER_RESERVE: usize = 1024;
+
+/// Which messages of the conversation go into the next request, and what they cost
+#[derive(Debug, Clone, Default)]
+pub struct ContextPlan {
+    /// Indices of the messages to send, oldest first
+    pub included: Vec<usize>,
+    /// Indices of the messages left out to stay within budget, oldest first
+    pub dropped: Vec<usize>,
+    /// Indices of the messages replaced by the latest summary note, oldest first
+    pub summarized: Vec<usize>,
+    /// Indices of the messages excluded by the user, oldest first
+    pub excluded: Vec<usize>,
+    /// Tokens of every message in the conversation, template overhead included
+    pub tokens: Vec<usize>,
+    /// Tokens of the included messages
+    pub total: usize,
+    /// Tokens available for the prompt: the context length minus the answer reserve
+    pub budget: usize,
+}
+
+impl ContextPlan {
+    /// The newest message alone is larger than the budget and will likely be rejected
+    pub fn over_budget(&self) -> bool {
+        self.total > self.budget
+    }
+}
+
+/// Prompt tokens available per request: `context_length` minus `max_tokens`
+pub fn budget(config: &ChatConfig) -> usize {
+    let reserve = config
+        .params
+        .max_tokens
+        .map_or(DEFAULT_ANSWER_RESERVE, |max_tokens| {
+            max_tokens.max(0) as usize
+        });
+    config.context_length.saturating_sub(reserve)
+}
+
+/// Keeps a leading system prompt, the latest summary note, which stands in for every
+/// message before it, and every pinned message, then as many of the most recent messages
+/// as fit into the token budget and `max_context_messages`. The window is contiguous
+/// (apart from excluded messages, which are never sent) so the model never sees a
+/// conversation with holes in it, and the newest message is always sent.
+pub fn plan<M: Borrow<Message>>(
+    messages: &[M],
+    counter: &TokenCounter,
+    config: &ChatConfig,
+) -> ContextPlan {
+    let tokens = messages
+        .iter()
+        .map(|m| counter.count_message(m.borrow()))
+        .collect();
+    plan_with_tokens(messages, tokens, config)
+}
+
+/// [`plan`] for messages whose token counts, `tokens`, are already known
+pub fn plan_with_tokens<M: Borrow<Message>>(
+    messages: &[M],
+    tokens: Vec<usize>,
+    config: &ChatConfig,
+) -> ContextPlan {
+    let messages: Vec<&Message> = messages.iter().map(Borrow::borrow).collect();
+    let budget = budget(config);
+    let max_messages = config.max_context_messages.unwrap_or(usize::MAX);
+    let excluded: Vec<usize> = (0..messages.len())
+        .filter(|&i| messages[i].excluded)
+        .collect();
+    // Messages that compete for the remaining room
+    let trimmable = |i: &usize| !messages[*i].excluded && !messages[*i].pinned;
+
+    let mut included = Vec::new();
+    let mut total = 0;
+    let has_system = messages
+        .first()
+        .is_some_and(|m| m.role == Role::System && m.summarizes.is_none() && !m.excluded);
+    if has_system {
+        included.push(0);
+    }
+    let mut summarized = Vec::new();
+    let mut start = usize::from(has_system);
+    if let Some(summary) = messages
+        .iter()
+        .rposition(|m| m.summarizes.is_some() && !m.excluded)
+    {
+        summarized.extend((start..summary).filter(trimmable));
+        included.push(summary);
+        start = summary + 1;
+    }
+    included.extend((0..messages.len()).filter(|&i| messages[i].pinned && !messages[i].excluded));
+    included.sort_unstable();
+    included.dedup();
+    total += included.iter().map(|&i| tokens[i]).sum::<usize>();
+
+    // Sent whatever it costs; an older message only goes if there is room
+    let newest = (0..messages.len()).rev().find(|&i| !messages[i].excluded);
+    let mut recent = Vec::new();
+    let mut first_kept = messages.len();
+    for i in (start..messages.len()).rev().filter(trimmable) {
+        let full = included.len() + recent.len() >= max_messages;
+        if Some(i) != newest && (full || total + tokens[i] > budget) {
+            break;
+        }
+        recent.push(i);
+        total += tokens[i];
+        first_kept = i;
+    }
+
+    included.extend(recent);
+    included.sort_unstable();
+
+    ContextPlan {
+        included,
+        dropped: (start..first_kept).filter(trimmable).collect(),
+        summarized,
+        excluded,
+        tokens,
+        total,
+        budget,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tokenizer::MESSAGE_OVERHEAD, types::MessageContent};
+
+    /// Each message costs one estimated token plus the template overhead
+    const COST: usize = 1 + MESSAGE_OVERHEAD;
+
+    fn message(role: Role) -> Message {
+        Message::new(role, MessageContent::Text("word".to_string()))
+    }
+
+    fn conversation(turns: usize) -> Vec<Message> {
+        let mut messages = vec![message(Role::System)];
+        for _ in 0..turns {
+            messages.push(message(Role::User));
+            messages.push(message(Role::Assistant));
+        }
+        messages
+    }
+
+    /// Room for `messages` messages
+    fn config(messages: usize) -> ChatConfig {
+        let mut config = ChatConfig {
+            context_length: 100 + messages * COST,
+            ..ChatConfig::default()
+        };
+        config.params.max_tokens = Some(100);
+        config
+    }
+
+    fn plan_for(messages: &[Message], config: &ChatConfig) -> ContextPlan {
+        plan(messages, &TokenCounter::Estimate, config)
+    }
+
+    #[test]
+    fn budget_leaves_room_for_the_answer() {
+        let mut config = ChatConfig {
+            context_length: 8192,
+            ..ChatConfig::default()
+        };
+        config.params.max_tokens = Some(1000);
+        assert_eq!(budget(&config), 7192);
+        config.params.max_tokens = None;
+        assert_eq!(budget(&config), 8192 - DEFAULT_ANSWER_RESERVE);
+        config.context_length = 100;
+        assert_eq!(budget(&config), 0);
+    }
+
+    #[test]
+    fn keeps_the_system_prompt_and_the_newest_messages() {
+        let messages = conversation(3);
+        let plan = plan_for(&messages, &config(3));
+        assert_eq!(plan.included, vec![0, 5, 6]);
+        assert_eq!(plan.dropped, vec![1, 2, 3, 4]);
+        assert_eq!(plan.total, 3 * COST);
+        assert!(!plan.over_budget());
+    }
+
+    #[test]
+    fn sends_everything_that_fits() {
+        let messages = conversation(2);
+        let plan = plan_for(&messages, &config(10));
+        assert_eq!(plan.included, vec![0, 1, 2, 3, 4]);
+        assert!(plan.dropped.is_empty());
+        assert_eq!(plan.tokens, vec![COST; 5]);
+    }
+
+    #[test]
+    fn always_sends_the_newest_message() {
+        let messages = vec![Message::new(
+            Role::User,
+            MessageContent::Text("a rather long question".to_string()),
+        )];
+        let plan = plan_for(&messages, &config(0));
+        assert_eq!(plan.included, vec![0]);
+        assert!(plan.over_budget());
+    }
+
+    #[test]
+    fn caps_the_number_of_messages() {
+        let messages = conversation(3);
+        let mut config = config(10);
+        config.max_context_messages = Some(3);
+        let plan = plan_for(&messages, &config);
+        assert_eq!(plan.included, vec![0, 5, 6]);
+        assert_eq!(plan.dropped, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn a_summary_stands_in_for_the_messages_before_it() {
+        let mut messages = conversation(2);
+        let mut summary = message(Role::System);
+        summary.summarizes = Some(4);
+        messages.push(summary);
+        messages.push(message(Role::User));
+        let plan = plan_for(&messages, &config(10));
+        assert_eq!(plan.included, vec![0, 5, 6]);
+        assert_eq!(plan.summarized, vec![1, 2, 3, 4]);
+        assert!(plan.dropped.is_empty());
+    }
+
+    #[test]
+    fn pinned_messages_stay_and_excluded_ones_are_never_sent() {
+        let mut messages = conversation(3);
+        messages[1].pinned = true;
+        messages[6].excluded = true;
+        let plan = plan_for(&messages, &config(4));
+        assert_eq!(plan.included, vec![0, 1, 4, 5]);
+        assert_eq!(plan.dropped, vec![2, 3]);
+        assert_eq!(plan.excluded, vec![6]);
+    }
+
+    #[test]
+    fn a_pinned_newest_message_leaves_no_room_for_older_ones() {
+        let mut messages = conversation(2);
+        messages[4].pinned = true;
+        let plan = plan_for(&messages, &config(2));
+        assert_eq!(plan.included, vec![0, 4]);
+        assert_eq!(plan.dropped, vec![1, 2, 3]);
+        assert_eq!(plan.total, 2 * COST);
+        assert!(!plan.over_budget());
+    }
+}
@@ -0,0 +1,607 @@
+use crate::{
+    backend::{self, ChatBackend},
+    config::{self, BackendKind, ChatConfig},
+    error::{ApiError, DOCTOR_TIP},
+};
+use openai_api_rs::v1::chat_completion::{
+    ChatCompletionMessage, ChatCompletionRequest, MessageRole,
+};
+use std::{fmt, sync::Arc, time::Instant};
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    /// Works, but something looks off
+    Warn,
+    Fail,
+    /// Not run because an earlier check failed
+    Skip,
+}
+
+/// One line of the doctor report
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to change when the check did not pass
+    pub fix: Option<String>,
+}
+
+/// Everything `hugging-code doctor` found out about the effective configuration
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// Whether any check failed outright; warnings and skipped checks don't count
+    pub fn failed(&self) -> bool {
+        self.checks
+            .iter()
+            .any(|check| check.status == CheckStatus::Fail)
+    }
+
+    fn count(&self, status: CheckStatus) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.status == status)
+            .count()
+    }
+
+    fn push(&mut self, name: &'static str, status: CheckStatus, detail: impl Into<String>) {
+        self.checks.push(Check {
+            name,
+            status,
+            detail: detail.into(),
+            fix: None,
+        });
+    }
+
+    fn push_with_fix(
+        &mut self,
+        name: &'static str,
+        status: CheckStatus,
+        detail: impl Into<String>,
+        fix: Option<String>,
+    ) {
+        self.push(name, status, detail);
+        if let Some(check) = self.checks.last_mut() {
+            check.fix = fix;
+        }
+    }
+
+    fn skip_remaining(&mut self, names: &[&'static str], reason: &str) {
+        for name in names {
+            self.push(name, CheckStatus::Skip, reason);
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "🩺 hugging-code doctor\n")?;
+        for check in &self.checks {
+            let glyph = match check.status {
+                CheckStatus::Pass => "✓",
+                CheckStatus::Warn => "⚠",
+                CheckStatus::Fail => "✗",
+                CheckStatus::Skip => "⊘",
+            };
+            writeln!(f, "{} {:<14} {}", glyph, check.name, check.detail)?;
+            if let Some(fix) = &check.fix {
+                writeln!(f, "  → {}", fix)?;
+            }
+        }
+        write!(
+            f,
+            "\n{} passed, {} warnings, {} failed, {} skipped",
+            self.count(CheckStatus::Pass),
+            self.count(CheckStatus::Warn),
+            self.count(CheckStatus::Fail),
+            self.count(CheckStatus::Skip)
+        )
+    }
+}
+
+/// Runs every check against the endpoint `config` points at, stopping early once the
+/// remaining checks can't tell anything new. `problems` are what went wrong while loading
+/// the configuration, from [`ChatConfig::load_best_effort`]; the endpoint is still checked
+/// with whatever could be loaded.
+///
+/// Nothing is printed, so the report can be asserted on against a stub server.
+pub async fn diagnose(config: &ChatConfig, problems: &[String]) -> Report {
+    let mut report = Report::default();
+    let remaining = ["Endpoint", "Health", "Models", "Completion"];
+
+    let config = match config.resolve_profile() {
+        Ok(config) => config,
+        Err(e) => {
+            report.push("Configuration", CheckStatus::Fail, e);
+            report.skip_remaining(&remaining, "configuration is invalid");
+            return report;
+        }
+    };
+    let backend = match backend::from_config(&config) {
+        Ok(backend) => backend,
+        Err(e) => {
+            report.push("Configuration", CheckStatus::Fail, describe(&e));
+            report.skip_remaining(&remaining, "configuration is invalid");
+            return report;
+        }
+    };
+    let profile = match &config.profile {
+        Some(profile) => format!(", profile {}", profile),
+        None => String::new(),
+    };
+    let summary = format!(
+        "{} backend, model {}, token {}{}",
+        backend.name(),
+        config.model,
+        config.token,
+        profile
+    );
+    if problems.is_empty() {
+        report.push("Configuration", CheckStatus::Pass, summary);
+    } else {
+        report.push_with_fix(
+            "Configuration",
+            CheckStatus::Fail,
+            problems.join("; "),
+            Some(format!(
+                "Fix the setting above; the checks below carry on with {}",
+                summary
+            )),
+        );
+    }
+
+    // Any HTTP answer at all, even a 404, proves the host and port are right
+    let probe = match backend::http_client(&config) {
+        Ok(http) => http
+            .get(&config.base_url)
+            .send()
+            .await
+            .map_err(ApiError::from),
+        Err(e) => Err(e),
+    };
+    match probe {
+        Ok(response) => report.push(
+            "Endpoint",
+            CheckStatus::Pass,
+            format!("{} answered (HTTP {})", config.base_url, response.status()),
+        ),
+        Err(e) => {
+            report.push_with_fix(
+                "Endpoint",
+                CheckStatus::Fail,
+                describe(&e),
+                fix_for(&e, &config),
+            );
+            report.skip_remaining(&remaining[1..], "endpoint is unreachable");
+            return report;
+        }
+    }
+
+    let listed = match backend.health().await {
+        Ok(listed) => {
+            report.push(
+                "Health",
+                CheckStatus::Pass,
+                format!("{} API is up and accepts the request", backend.name()),
+            );
+            listed
+        }
+        Err(e) if e.status_code() == Some(404) => {
+            let fix = match find_working_endpoint(&config).await {
+                Some(fix) => Some(fix),
+                None => Some(format!(
+                    "Nothing at {} speaks the {} API; check base_url and backend",
+                    config.base_url,
+                    backend.name()
+                )),
+            };
+            report.push_with_fix("Health", CheckStatus::Fail, describe(&e), fix);
+            report.skip_remaining(&remaining[2..], "API not found at base_url");
+            return report;
+        }
+        Err(e) => {
+            let fix = fix_for(&e, &config);
+            report.push_with_fix("Health", CheckStatus::Fail, describe(&e), fix);
+            if matches!(e.status_code(), Some(401 | 403)) {
+                report.skip_remaining(&remaining[2..], "token was rejected");
+                return report;
+            }
+            None
+        }
+    };
+
+    check_models(&mut report, backend.as_ref(), &config, listed).await;
+    check_completion(&mut report, backend, &config).await;
+    report
+}
+
+/// Looks the configured model up in the endpoint's model list, fetching it unless the health
+/// check already did
+async fn check_models(
+    report: &mut Report,
+    backend: &dyn ChatBackend,
+    config: &ChatConfig,
+    listed: Option<Vec<String>>,
+) {
+    let models = match listed {
+        Some(models) => Ok(models),
+        None => backend.list_models().await,
+    };
+    match models {
+        Ok(models) if models.is_empty() => {
+            report.push("Models", CheckStatus::Warn, "the endpoint lists no models")
+        }
+        Ok(models) if models.iter().any(|m| same_model(m, &config.model)) => report.push(
+            "Models",
+            CheckStatus::Pass,
+            format!("{} available, including {}", models.len(), config.model),
+        ),
+        Ok(models) => {
+            let wanted = config.model.to_lowercase();
+            let short = wanted.rsplit('/').next().unwrap_or(&wanted);
+            let mut suggestions: Vec<&str> = models
+                .iter()
+                .filter(|m| m.to_lowercase().contains(short))
+                .map(String::as_str)
+                .collect();
+            if suggestions.is_empty() {
+                suggestions = models.iter().map(String::as_str).collect();
+            }
+            let more = if suggestions.len() > 5 { ", ..." } else { "" };
+            suggestions.truncate(5);
+            report.push_with_fix(
+                "Models",
+                CheckStatus::Warn,
+                format!(
+                    "{} is not among the {} listed models",
+                    config.model,
+                    models.len()
+                ),
+                Some(format!(
+                    "Set model to one of {}{} (or pick one with /model)",
+                    suggestions.join(", "),
+                    more
+                )),
+            );
+        }
+        Err(e) if e.status_code() == Some(404) => report.push(
+            "Models",
+            CheckStatus::Warn,
+            "the endpoint does not list its models",
+        ),
+        Err(e) => {
+            let fix = fix_for(&e, config);
+            report.push_with_fix("Models", CheckStatus::Fail, describe(&e), fix);
+        }
+    }
+}
+
+/// Sends a tiny prompt through the same request path as the chat
+async fn check_completion(report: &mut Report, backend: Arc<dyn ChatBackend>, config: &ChatConfig) {
+    let mut req = ChatCompletionRequest::new(
+        config.model.clone(),
+        vec![ChatCompletionMessage {
+            role: MessageRole::user,
+            content: "Reply with the single word OK.".to_string(),
+            name: None,
+            function_call: None,
+        }],
+    );
+    config.params.apply(&mut req);
+    req.max_tokens = Some(16);
+    req.n = None;
+
+    let started = Instant::now();
+    match backend.send(&req).await {
+        Ok(response) if response.content.trim().is_empty() => report.push_with_fix(
+            "Completion",
+            CheckStatus::Warn,
+            "the model answered with no content",
+            Some("Check the model's chat template, or try backend = \"openai\"".to_string()),
+        ),
+        Ok(response) => {
+            let mut answer: String = response.content.trim().chars().take(40).collect();
+            if answer.len() < response.content.trim().len() {
+                answer.push('…');
+            }
+            report.push(
+                "Completion",
+                CheckStatus::Pass,
+                format!(
+                    "answered {:?} in {}ms",
+                    answer,
+                    started.elapsed().as_millis()
+                ),
+            );
+        }
+        Err(e @ ApiError::ModelLoading { .. }) => report.push_with_fix(
+            "Completion",
+            CheckStatus::Warn,
+            describe(&e),
+            Some("The model is cold-starting; run doctor again in a minute".to_string()),
+        ),
+        Err(e) if e.status_code() == Some(404) => report.push_with_fix(
+            "Completion",
+            CheckStatus::Fail,
+            describe(&e),
+            Some(format!(
+                "{} is not served here; pick a listed model with /model or --model",
+                config.model
+            )),
+        ),
+        Err(e) => {
+            let fix = fix_for(&e, config);
+            report.push_with_fix("Completion", CheckStatus::Fail, describe(&e), fix);
+        }
+    }
+}
+
+/// Tries the usual suspects when the configured API is not found: a missing or extra
+/// `/v1`, or the wrong backend for the server
+async fn find_working_endpoint(config: &ChatConfig) -> Option<String> {
+    let trimmed = config.base_url.trim_end_matches('/');
+    let root = trimmed.strip_suffix("/v1").unwrap_or(trimmed).to_string();
+    let with_v1 = format!("{}/v1", root);
+    let candidates = [
+        (BackendKind::OpenAi, with_v1),
+        (BackendKind::OpenAi, root.clone()),
+        (BackendKind::Ollama, root.clone()),
+        (BackendKind::Tgi, root),
+    ];
+
+    for (kind, base_url) in candidates {
+        if kind == config.backend && base_url == trimmed {
+            continue;
+        }
+        let candidate = ChatConfig {
+            backend: kind,
+            base_url: base_url.clone(),
+            ..config.clone()
+        };
+        let Ok(backend) = backend::from_config(&candidate) else {
+            continue;
+        };
+        if backend.health().await.is_ok() {
+            return Some(if kind == config.backend {
+                format!("Set base_url = \"{}\"", base_url)
+            } else {
+                format!(
+                    "Set backend = \"{}\" and base_url = \"{}\"",
+                    backend.name(),
+                    base_url
+                )
+            });
+        }
+    }
+    None
+}
+
+/// A specific suggestion for errors that have one
+fn fix_for(error: &ApiError, config: &ChatConfig) -> Option<String> {
+    let hugging_face = config::is_hugging_face(&config.base_url);
+    match (error, error.status_code()) {
+        (_, Some(401)) if config.token.is_empty() => Some(
+            "The endpoint needs a token: set HUGGINGFACE_TOKEN, token_file or token_command"
+                .to_string(),
+        ),
+        (_, Some(401)) if hugging_face => Some(format!(
+            "The token {} was rejected; check it at https://huggingface.co/settings/tokens",
+            config.token
+        )),
+        (_, Some(401)) => Some(format!(
+            "The token {} was rejected; check it is valid for this endpoint",
+            config.token
+        )),
+        (_, Some(403)) if hugging_face => Some(
+            "Fine-grained tokens need the \"Make calls to Inference Providers\" permission, \
+             and gated models need their license accepted on the model page"
+                .to_string(),
+        ),
+        (_, Some(403)) => Some("The token lacks permission for this model".to_string()),
+        (_, Some(400 | 422)) => Some(
+            "The endpoint rejected the sampling parameters; check them with /params".to_string(),
+        ),
+        (_, Some(429)) => Some("Rate limited; wait a moment or use another token".to_string()),
+        (ApiError::Transport(_), _) => {
+            let local =
+                config.base_url.contains("localhost") || config.base_url.contains("127.0.0.1");
+            Some(if local {
+                "Start the local server (e.g. `ollama serve`) or fix the port in base_url"
+                    .to_string()
+            } else {
+                "Check the host in base_url, your network and any proxy settings".to_string()
+            })
+        }
+        (ApiError::Timeout, _) => Some(
+            "Raise http.connect_timeout_secs or http.read_timeout_secs, or try again once the model is warm"
+                .to_string(),
+        ),
+        (ApiError::Decode { .. }, _) => Some(format!(
+            "The answer was not in the {} format; check the backend setting",
+            format!("{:?}", config.backend).to_lowercase()
+        )),
+        _ => None,
+    }
+}
+
+/// The error's hint, minus the advice to run the doctor we are already in
+fn describe(error: &ApiError) -> String {
+    error.hint().replace(&format!(" {}", DOCTOR_TIP), "")
+}
+
+/// Ollama lists `llama3.2:latest` for a model configured as `llama3.2`
+fn same_model(listed: &str, wanted: &str) -> bool {
+    listed == wanted || listed.strip_suffix(":latest") == Some(wanted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cli::Cli, config::Sources, test_util::stub_server};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn config(base_url: &str, model: &str) -> ChatConfig {
+        ChatConfig {
+            base_url: base_url.to_string(),
+            model: model.to_string(),
+            ..ChatConfig::default()
+        }
+    }
+
+    fn check<'a>(report: &'a Report, name: &str) -> &'a Check {
+        report
+            .checks
+            .iter()
+            .find(|check| check.name == name)
+            .unwrap_or_else(|| panic!("no {} check in\n{}", name, report))
+    }
+
+    const MODELS: &str = r#"{"data": [{"id": "llama3.2"}, {"id": "qwen2.5-coder"}]}"#;
+    const ANSWER: &str =
+        r#"{"choices": [{"index": 0, "message": {"content": "OK"}, "finish_reason": "stop"}]}"#;
+
+    #[tokio::test]
+    async fn suggests_adding_a_missing_v1() {
+        let url = stub_server(|target, _| match target {
+            "GET /v1/models" => (200, MODELS),
+            "POST /v1/chat/completions" => (200, ANSWER),
+            _ => (404, r#"{"error": "not found"}"#),
+        })
+        .await;
+
+        let report = diagnose(&config(&url, "llama3.2"), &[]).await;
+        assert_eq!(check(&report, "Endpoint").status, CheckStatus::Pass);
+        let health = check(&report, "Health");
+        assert_eq!(health.status, CheckStatus::Fail);
+        assert_eq!(
+            health.fix.as_deref(),
+            Some(format!("Set base_url = \"{}/v1\"", url).as_str())
+        );
+        assert_eq!(check(&report, "Models").status, CheckStatus::Skip);
+        assert!(report.failed());
+
+        let fixed = diagnose(&config(&format!("{}/v1", url), "llama3.2"), &[]).await;
+        assert!(!fixed.failed(), "{}", fixed);
+        assert_eq!(check(&fixed, "Completion").status, CheckStatus::Pass);
+    }
+
+    #[tokio::test]
+    async fn stops_at_a_rejected_token() {
+        let url = stub_server(|_, _| {
+            (
+                401,
+                r#"{"error": "Invalid credentials in Authorization header"}"#,
+            )
+        })
+        .await;
+
+        let report = diagnose(&config(&format!("{}/v1", url), "llama3.2"), &[]).await;
+        assert_eq!(check(&report, "Endpoint").status, CheckStatus::Pass);
+        let health = check(&report, "Health");
+        assert_eq!(health.status, CheckStatus::Fail);
+        assert!(health.fix.as_deref().unwrap().contains("needs a token"));
+        assert_eq!(check(&report, "Models").status, CheckStatus::Skip);
+        assert_eq!(check(&report, "Completion").status, CheckStatus::Skip);
+    }
+
+    #[tokio::test]
+    async fn points_out_a_model_the_server_does_not_serve() {
+        let url = stub_server(|target, _| match target {
+            "GET /v1/models" => (200, MODELS),
+            "POST /v1/chat/completions" => (404, r#"{"error": "model 'llama3.3' not found"}"#),
+            _ => (404, r#"{"error": "not found"}"#),
+        })
+        .await;
+
+        let report = diagnose(&config(&format!("{}/v1", url), "llama3.3"), &[]).await;
+        assert_eq!(check(&report, "Health").status, CheckStatus::Pass);
+        let models = check(&report, "Models");
+        assert_eq!(models.status, CheckStatus::Warn);
+        assert!(models.fix.as_deref().unwrap().contains("llama3.2"));
+        let completion = check(&report, "Completion");
+        assert_eq!(completion.status, CheckStatus::Fail);
+        assert!(completion
+            .fix
+            .as_deref()
+            .unwrap()
+            .contains("not served here"));
+    }
+
+    #[tokio::test]
+    async fn lists_the_models_once_for_health_and_models() {
+        let listings = Arc::new(AtomicUsize::new(0));
+        let counter = listings.clone();
+        let url = stub_server(move |target, _| match target {
+            "GET /v1/models" => {
+                counter.fetch_add(1, Ordering::SeqCst);
+                (200, MODELS)
+            }
+            "POST /v1/chat/completions" => (200, ANSWER),
+            _ => (404, r#"{"error": "not found"}"#),
+        })
+        .await;
+
+        let report = diagnose(&config(&format!("{}/v1", url), "llama3.2"), &[]).await;
+        assert_eq!(check(&report, "Health").status, CheckStatus::Pass);
+        assert_eq!(check(&report, "Models").status, CheckStatus::Pass);
+        assert_eq!(listings.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn a_bad_variable_leaves_the_others_in_place() {
+        let url = stub_server(|target, _| match target {
+            "GET /v1/models" => (200, MODELS),
+            "POST /v1/chat/completions" => (200, ANSWER),
+            _ => (404, r#"{"error": "not found"}"#),
+        })
+        .await;
+        let base_url = format!("{}/v1", url);
+        let sources = Sources {
+            files: Vec::new(),
+            env: [
+                ("HF_TEMPERATURE", "hot"),
+                ("HF_BASE_URL", base_url.as_str()),
+                ("HF_MODEL", "llama3.2"),
+            ]
+            .into_iter()
+            .map(|(var, value)| (var.to_string(), value.to_string()))
+            .collect(),
+        };
+
+        let (config, problems) = ChatConfig::build_best_effort(&Cli::default(), &sources);
+        let report = diagnose(&config, &problems).await;
+        let configuration = check(&report, "Configuration");
+        assert_eq!(configuration.status, CheckStatus::Fail);
+        assert!(
+            configuration.detail.contains("HF_TEMPERATURE"),
+            "{}",
+            report
+        );
+        assert!(check(&report, "Endpoint").detail.contains(&base_url));
+        assert_eq!(check(&report, "Models").status, CheckStatus::Pass);
+        assert_eq!(check(&report, "Completion").status, CheckStatus::Pass);
+    }
+
+    #[tokio::test]
+    async fn checks_the_endpoint_despite_configuration_problems() {
+        let url = stub_server(|target, _| match target {
+            "GET /v1/models" => (200, MODELS),
+            "POST /v1/chat/completions" => (200, ANSWER),
+            _ => (404, r#"{"error": "not found"}"#),
+        })
+        .await;
+
+        let problems = vec!["Invalid configuration: token_command: \"pass hf\" failed".to_string()];
+        let report = diagnose(&config(&format!("{}/v1", url), "llama3.2"), &problems).await;
+        let configuration = check(&report, "Configuration");
+        assert_eq!(configuration.status, CheckStatus::Fail);
+        assert!(configuration.detail.contains("token_command"));
+        assert_eq!(check(&report, "Completion").status, CheckStatus::Pass);
+    }
+}
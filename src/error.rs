@@ -0,0 +1,427 @@
+use serde::Deserialize;
+use std::{error::Error, fmt, time::Duration};
+
+/// Errors produced while talking to a chat completion endpoint
+#[derive(Debug, Clone)]
+pub enum ApiError {
+    /// Non-success HTTP status whose body was not a recognised error object
+    Status {
+        code: u16,
+        body: String,
+        retry_after: Option<Duration>,
+    },
+    /// Structured error reported by the provider, either as an HTTP error body or mid-stream
+    Provider {
+        code: Option<u16>,
+        message: String,
+        error_type: Option<String>,
+        body: String,
+        retry_after: Option<Duration>,
+    },
+    /// Hugging Face returned 503 while a scaled-to-zero model is still warming up
+    ModelLoading {
+        estimated_time: Duration,
+        body: String,
+    },
+    /// The request never got a response (DNS, refused connection, TLS, reset socket...)
+    Transport(String),
+    /// The server stopped answering within the configured timeout
+    Timeout,
+    /// A response arrived but could not be parsed
+    Decode { message: String, body: String },
+}
+
+/// Error bodies seen in the wild: OpenAI `{"error": {"message", "type"}}`,
+/// HF/TGI/Ollama `{"error": "...", "error_type": "..."}` and FastAPI `{"detail": "..."}`
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: Option<ErrorField>,
+    error_type: Option<String>,
+    detail: Option<serde_json::Value>,
+    estimated_time: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ErrorField {
+    Message(String),
+    Object {
+        message: Option<String>,
+        #[serde(rename = "type")]
+        error_type: Option<String>,
+    },
+}
+
+impl ApiError {
+    /// Builds an error from a non-success HTTP response, keeping the raw body
+    pub fn from_response(code: u16, body: String, retry_after: Option<Duration>) -> Self {
+        if code == 503 {
+            if let Some(estimated_time) = parse_estimated_time(&body) {
+                return ApiError::ModelLoading {
+                    estimated_time,
+                    body,
+                };
+            }
+        }
+        match parse_error_body(&body) {
+            Some((message, error_type)) => ApiError::Provider {
+                code: Some(code),
+                message,
+                error_type,
+                body,
+                retry_after,
+            },
+            None => ApiError::Status {
+                code,
+                body,
+                retry_after,
+            },
+        }
+    }
+
+    /// Builds an error from an `error` object embedded in a stream chunk
+    pub fn from_stream_error(error: serde_json::Value) -> Self {
+        let body = error.to_string();
+        let wrapped = serde_json::json!({ "error": error }).to_string();
+        let (message, error_type) = parse_error_body(&wrapped).unwrap_or((body.clone(), None));
+        ApiError::Provider {
+            code: None,
+            message,
+            error_type,
+            body,
+            retry_after: None,
+        }
+    }
+
+    pub fn decode(message: impl Into<String>, body: impl Into<String>) -> Self {
+        ApiError::Decode {
+            message: message.into(),
+            body: body.into(),
+        }
+    }
+
+    /// HTTP status code, when the server sent one
+    pub fn status_code(&self) -> Option<u16> {
+        match self {
+            ApiError::Status { code, .. } => Some(*code),
+            ApiError::Provider { code, .. } => *code,
+            ApiError::ModelLoading { .. } => Some(503),
+            _ => None,
+        }
+    }
+
+    /// Whether the request is worth sending again: rate limits and server-side failures
+    pub fn is_retryable(&self) -> bool {
+        matches!(self.status_code(), Some(429 | 500 | 502 | 503 | 504))
+    }
+
+    /// Delay requested by the server through the `Retry-After` header
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ApiError::Status { retry_after, .. } | ApiError::Provider { retry_after, .. } => {
+                *retry_after
+            }
+            _ => None,
+        }
+    }
+
+    /// Raw response body, if any was received
+    pub fn raw_body(&self) -> Option<&str> {
+        match self {
+            ApiError::Status { body, .. }
+            | ApiError::Provider { body, .. }
+            | ApiError::ModelLoading { body, .. }
+            | ApiError::Decode { body, .. } => Some(body.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Short, actionable message for the status bar
+    pub fn hint(&self) -> String {
+        match self {
+            ApiError::Status { code, .. } => status_hint(*code),
+            ApiError::Provider {
+                code: Some(code),
+                message,
+                ..
+            } => format!("{} ({})", status_hint(*code), message),
+            ApiError::Provider {
+                code: None,
+                message,
+                ..
+            } => format!("Provider Error: {}", message),
+            ApiError::ModelLoading { estimated_time, .. } => format!(
+                "Error 503: Model is still loading on the endpoint (about {}s left). Try again shortly.",
+                estimated_time.as_secs()
+            ),
+            ApiError::Transport(detail) => format!(
+                "Connection Error: Cannot reach API ({}). Check if service is running and HF_BASE_URL is correct.",
+                detail
+            ),
+            ApiError::Timeout => {
+                "Timeout: The API did not respond in time. The model may be overloaded or still loading.".to_string()
+            }
+            ApiError::Decode { message, .. } => {
+                format!("Unexpected response from API: {}", message)
+            }
+        }
+    }
+}
+
+/// Pointer to the diagnostics command, appended to errors with no more specific advice
+pub const DOCTOR_TIP: &str = "Run `hugging-code doctor` to diagnose.";
+
+fn status_hint(code: u16) -> String {
+    match code {
+        400 => "Error 400: Bad request. The endpoint rejected the request parameters.".to_string(),
+        401 => "Error 401: Invalid API key. Please check your token.".to_string(),
+        403 => "Error 403: Access denied. Your token lacks permission for this model.".to_string(),
+        404 => format!("Error 404: API endpoint or model not found. {}", DOCTOR_TIP),
+        422 => {
+            "Error 422: Invalid request. The model may not support these parameters.".to_string()
+        }
+        429 => "Error 429: Rate limit exceeded. Please wait and try again.".to_string(),
+        500..=599 => format!(
+            "Error {}: Server error. The endpoint is unavailable or overloaded.",
+            code
+        ),
+        _ => format!("Error {}: API request failed. {}", code, DOCTOR_TIP),
+    }
+}
+
+/// Parses a `Retry-After` header given either as delay-seconds or as an HTTP date
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<f64>() {
+        // Negative, infinite or out-of-range delays are not worth honouring
+        return Duration::try_from_secs_f64(seconds).ok();
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Extracts `estimated_time` from a Hugging Face "model is loading" body
+fn parse_estimated_time(body: &str) -> Option<Duration> {
+    let parsed: ErrorBody = serde_json::from_str(body).ok()?;
+    Duration::try_from_secs_f64(parsed.estimated_time?).ok()
+}
+
+fn parse_error_body(body: &str) -> Option<(String, Option<String>)> {
+    let parsed: ErrorBody = serde_json::from_str(body).ok()?;
+    match (parsed.error, parsed.detail) {
+        (Some(ErrorField::Message(message)), _) => Some((message, parsed.error_type)),
+        (
+            Some(ErrorField::Object {
+                message,
+                error_type,
+            }),
+            _,
+        ) => Some((
+            message.unwrap_or_else(|| body.to_string()),
+            error_type.or(parsed.error_type),
+        )),
+        (None, Some(serde_json::Value::String(detail))) => Some((detail, None)),
+        (None, Some(detail)) => Some((detail.to_string(), None)),
+        (None, None) => None,
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            ApiError::Timeout
+        } else if err.is_decode() {
+            ApiError::decode(err.to_string(), String::new())
+        } else {
+            // The top-level message is generic; the cause chain says what actually failed
+            let mut detail = err.to_string();
+            let mut source = err.source();
+            while let Some(cause) = source {
+                detail = format!("{}: {}", detail, cause);
+                source = cause.source();
+            }
+            ApiError::Transport(detail)
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Status { code, body, .. } => write!(f, "HTTP {}: {}", code, body),
+            ApiError::Provider {
+                code: Some(code),
+                message,
+                ..
+            } => write!(f, "HTTP {}: {}", code, message),
+            ApiError::Provider { message, .. } => write!(f, "{}", message),
+            ApiError::ModelLoading { estimated_time, .. } => write!(
+                f,
+                "model is loading (estimated {:.0}s)",
+                estimated_time.as_secs_f64()
+            ),
+            ApiError::Transport(detail) => write!(f, "transport error: {}", detail),
+            ApiError::Timeout => write!(f, "request timed out"),
+            ApiError::Decode { message, .. } => write!(f, "decode error: {}", message),
+        }
+    }
+}
+
+impl Error for ApiError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(error: &ApiError) -> (Option<u16>, &str, Option<&str>) {
+        match error {
+            ApiError::Provider {
+                code,
+                message,
+                error_type,
+                ..
+            } => (*code, message.as_str(), error_type.as_deref()),
+            other => panic!("expected a provider error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reads_openai_error_objects() {
+        let body = r#"{"error": {"message": "Invalid model", "type": "invalid_request_error"}}"#;
+        let error = ApiError::from_response(400, body.to_string(), None);
+        assert_eq!(
+            provider(&error),
+            (Some(400), "Invalid model", Some("invalid_request_error"))
+        );
+        assert_eq!(error.raw_body(), Some(body));
+        assert!(error.hint().contains("Invalid model"));
+    }
+
+    #[test]
+    fn reads_hugging_face_and_fastapi_errors() {
+        let error = ApiError::from_response(
+            422,
+            r#"{"error": "Input validation error", "error_type": "validation"}"#.to_string(),
+            None,
+        );
+        assert_eq!(
+            provider(&error),
+            (Some(422), "Input validation error", Some("validation"))
+        );
+
+        let error = ApiError::from_response(404, r#"{"detail": "Not Found"}"#.to_string(), None);
+        assert_eq!(provider(&error), (Some(404), "Not Found", None));
+
+        let error = ApiError::from_response(
+            422,
+            r#"{"detail": [{"loc": ["body", "model"]}]}"#.to_string(),
+            None,
+        );
+        assert_eq!(
+            provider(&error),
+            (Some(422), r#"[{"loc":["body","model"]}]"#, None)
+        );
+    }
+
+    #[test]
+    fn keeps_unrecognised_bodies_as_a_status() {
+        let retry_after = Some(Duration::from_secs(7));
+        let error =
+            ApiError::from_response(502, "<html>Bad Gateway</html>".to_string(), retry_after);
+        assert!(matches!(error, ApiError::Status { code: 502, .. }));
+        assert_eq!(error.raw_body(), Some("<html>Bad Gateway</html>"));
+        assert_eq!(error.retry_after(), retry_after);
+        assert!(error.is_retryable());
+    }
+
+    #[test]
+    fn recognises_a_loading_model() {
+        let body = r#"{"error": "Model is currently loading", "estimated_time": 42.5}"#;
+        let error = ApiError::from_response(503, body.to_string(), None);
+        assert!(matches!(
+            error,
+            ApiError::ModelLoading { estimated_time, .. } if estimated_time == Duration::from_secs_f64(42.5)
+        ));
+        assert_eq!(error.status_code(), Some(503));
+
+        // An estimate too large for a duration is an ordinary, retryable 503
+        let body = r#"{"error": "Model is currently loading", "estimated_time": 1e300}"#;
+        let error = ApiError::from_response(503, body.to_string(), None);
+        assert!(matches!(
+            error,
+            ApiError::Provider {
+                code: Some(503),
+                ..
+            }
+        ));
+        assert!(error.is_retryable());
+
+        // Only a 503 means the model is loading
+        let error = ApiError::from_response(500, body.to_string(), None);
+        assert!(matches!(error, ApiError::Provider { .. }));
+    }
+
+    #[test]
+    fn only_rate_limits_and_server_errors_are_retried() {
+        for code in [429, 500, 502, 503, 504] {
+            assert!(ApiError::from_response(code, String::new(), None).is_retryable());
+        }
+        for code in [400, 401, 403, 404, 422, 501] {
+            assert!(!ApiError::from_response(code, String::new(), None).is_retryable());
+        }
+        assert!(!ApiError::Timeout.is_retryable());
+    }
+
+    #[test]
+    fn reads_errors_sent_mid_stream() {
+        let error = ApiError::from_stream_error(serde_json::json!({
+            "message": "context length exceeded",
+            "type": "invalid_request_error"
+        }));
+        assert_eq!(
+            provider(&error),
+            (
+                None,
+                "context length exceeded",
+                Some("invalid_request_error")
+            )
+        );
+
+        let error = ApiError::from_stream_error(serde_json::json!("overloaded"));
+        assert_eq!(provider(&error), (None, "overloaded", None));
+        assert_eq!(error.hint(), "Provider Error: overloaded");
+    }
+
+    #[test]
+    fn parses_retry_after_seconds() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after(" 1.5 "),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(parse_retry_after("0"), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("-3"), None);
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn ignores_retry_after_values_out_of_range() {
+        assert_eq!(parse_retry_after("inf"), None);
+        assert_eq!(parse_retry_after("NaN"), None);
+        assert_eq!(parse_retry_after("1e30"), None);
+    }
+
+    #[test]
+    fn parses_retry_after_dates() {
+        let in_a_minute = (chrono::Utc::now() + chrono::Duration::seconds(60)).to_rfc2822();
+        let delay = parse_retry_after(&in_a_minute).unwrap();
+        assert!(delay > Duration::from_secs(55) && delay <= Duration::from_secs(60));
+
+        // A date in the past means now
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+    }
+}
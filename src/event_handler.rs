@@ -5,11 +5,73 @@ use crate::app::{App, InputMode, ScrollState, StatusType};
 pub async fn handle_event(app: &mut App, event: Event) -> Result<bool, Box<dyn std::error::Error>> {
     if let Event::Key(key) = event {
         if key.kind == KeyEventKind::Press {
+            if let Some(browser) = &mut app.session_browser {
+                if let Some(title) = &mut browser.renaming {
+                    match key.code {
+                        KeyCode::Esc => browser.renaming = None,
+                        KeyCode::Enter => app.rename_selected_session(),
+                        KeyCode::Backspace => {
+                            title.pop();
+                        }
+                        KeyCode::Char(c) => title.push(c),
+                        _ => {}
+                    }
+                    return Ok(false);
+                }
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => app.session_browser = None,
+                    KeyCode::Enter => app.confirm_session_browser(),
+                    KeyCode::Up => browser.select_previous(),
+                    KeyCode::Down => browser.select_next(),
+                    KeyCode::Char('r') => {
+                        browser.confirm_delete = false;
+                        browser.renaming = browser.selected().map(|info| info.title.clone());
+                    }
+                    KeyCode::Char('d') if browser.confirm_delete => app.delete_selected_session(),
+                    KeyCode::Char('d') => browser.confirm_delete = browser.selected().is_some(),
+                    _ => browser.confirm_delete = false,
+                }
+                return Ok(false);
+            }
+
+            if let Some(picker) = &mut app.model_picker {
+                match key.code {
+                    KeyCode::Esc => app.model_picker = None,
+                    KeyCode::Enter => app.confirm_model_picker(),
+                    KeyCode::Up => picker.select_previous(),
+                    KeyCode::Down => picker.select_next(),
+                    KeyCode::Backspace => picker.pop_filter(),
+                    KeyCode::Char(c) => picker.push_filter(c),
+                    _ => {}
+                }
+                return Ok(false);
+            }
+
             match app.input_mode {
                 InputMode::Normal => match key.code {
+                    KeyCode::Char('c')
+                        if app.is_loading && key.modifiers.contains(KeyModifiers::CONTROL) =>
+                    {
+                        app.cancel_request()
+                    }
+                    // Esc closes an open popup first, so it cannot throw away the answer
+                    KeyCode::Esc if app.show_error_details => app.show_error_details = false,
+                    KeyCode::Esc if app.show_params => app.show_params = false,
+                    KeyCode::Esc if app.show_context => app.show_context = false,
+                    KeyCode::Esc if app.show_help => app.show_help = false,
+                    KeyCode::Esc if app.is_loading => app.cancel_request(),
+                    KeyCode::Up if app.show_help => app.scroll_help(-1),
+                    KeyCode::Down if app.show_help => app.scroll_help(1),
+                    KeyCode::PageUp if app.show_help => app.scroll_help(-10),
+                    KeyCode::PageDown if app.show_help => app.scroll_help(10),
+                    KeyCode::Home if app.show_help => app.help_scroll = 0,
+                    KeyCode::End if app.show_help => app.help_scroll = app.help_max_scroll.get(),
+                    KeyCode::Char('e') if app.last_error.is_some() => {
+                        app.show_error_details = !app.show_error_details
+                    }
                     KeyCode::Char('q') => return Ok(true),
                     KeyCode::Char('i') => app.input_mode = InputMode::Editing,
-                    KeyCode::Char('h') => app.show_help = !app.show_help,
+                    KeyCode::Char('h') => app.toggle_help(),
                     KeyCode::Char('t') => {
                         app.show_thinking = !app.show_thinking;
                         app.status_message = Some((
@@ -20,6 +82,15 @@ pub async fn handle_event(app: &mut App, event: Event) -> Result<bool, Box<dyn s
                             StatusType::Info,
                         ));
                     }
+                    KeyCode::Char('c') if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app.show_context = !app.show_context
+                    }
+                    KeyCode::Char('p') => app.toggle_pinned(),
+                    KeyCode::Char('x') => app.toggle_excluded(),
+                    KeyCode::Char('E') => app.start_edit(),
+                    KeyCode::Char('r') => app.retry(1, app.config.params.clone()),
+                    KeyCode::Left => app.switch_sibling(false),
+                    KeyCode::Right => app.switch_sibling(true),
                     KeyCode::Up => app.scroll_up(),
                     KeyCode::Down => app.scroll_down(),
                     KeyCode::PageUp => {
@@ -54,19 +125,18 @@ pub async fn handle_event(app: &mut App, event: Event) -> Result<bool, Box<dyn s
                     }
                     KeyCode::Char(c) => {
                         if key.modifiers.contains(KeyModifiers::CONTROL) {
-                            match c {
-                                's' => {
-                                    if let Err(e) = app.save_conversation("conversation.json") {
-                                        app.status_message = Some((
-                                            format!("Save failed: {}", e),
-                                            StatusType::Error,
-                                        ));
-                                    } else {
-                                        app.status_message =
-                                            Some(("Saved!".to_string(), StatusType::Success));
+                            if c == 's' {
+                                app.status_message = match app.save_session() {
+                                    Ok(Some(id)) => {
+                                        Some((format!("Saved session {}", id), StatusType::Success))
                                     }
-                                }
-                                _ => {}
+                                    Ok(None) => {
+                                        Some(("Nothing to save yet".to_string(), StatusType::Info))
+                                    }
+                                    Err(e) => {
+                                        Some((format!("Save failed: {}", e), StatusType::Error))
+                                    }
+                                };
                             }
                         } else {
                             app.input.push(c);
@@ -75,6 +145,7 @@ pub async fn handle_event(app: &mut App, event: Event) -> Result<bool, Box<dyn s
                     KeyCode::Backspace => {
                         app.input.pop();
                     }
+                    KeyCode::Esc if app.editing.is_some() => app.cancel_edit(),
                     KeyCode::Esc => app.input_mode = InputMode::Normal,
                     _ => {}
                 },
@@ -83,3 +154,39 @@ pub async fn handle_event(app: &mut App, event: Event) -> Result<bool, Box<dyn s
     }
     Ok(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::ChatConfig, types::Role};
+    use crossterm::event::KeyEvent;
+
+    fn press(code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[tokio::test]
+    async fn escape_closes_a_popup_before_cancelling() {
+        let mut app = App::headless(ChatConfig {
+            autosave: false,
+            ..ChatConfig::default()
+        })
+        .unwrap();
+        app.add_message(Role::User, "question".to_string());
+        app.is_loading = true;
+        app.show_help = true;
+        app.show_context = true;
+
+        handle_event(&mut app, press(KeyCode::Esc)).await.unwrap();
+        assert!(!app.show_context);
+        assert!(app.show_help);
+        handle_event(&mut app, press(KeyCode::Esc)).await.unwrap();
+        assert!(!app.show_help);
+        assert!(app.is_loading);
+        assert_eq!(app.messages.len(), 1);
+
+        handle_event(&mut app, press(KeyCode::Esc)).await.unwrap();
+        assert!(!app.is_loading);
+        assert_eq!(app.input, "question");
+    }
+}
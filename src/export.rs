@@ -0,0 +1,241 @@
+use crate::{
+    markdown::{escape_html, MarkdownRenderer},
+    sessions::title_of,
+    types::{Message, MessageContent, Role, Session},
+};
+use chrono::Local;
+
+/// Formats `/export` can write; both are meant for reading, not for loading back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    /// A single file with inline CSS and highlighting, to attach to a ticket
+    Html,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "md" | "markdown" => Some(Self::Markdown),
+            "html" | "htm" => Some(Self::Html),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+        }
+    }
+}
+
+pub fn export(session: &Session, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Markdown => to_markdown(session),
+        ExportFormat::Html => to_html(session),
+    }
+}
+
+/// The transcript as Markdown; message text is copied as written, so code fences survive
+pub fn to_markdown(session: &Session) -> String {
+    let mut out = format!("# {}\n\n{}\n", title_of(session), summary_line(session));
+    for msg in &session.messages {
+        out.push_str(&format!("\n---\n\n## {}\n\n", heading(msg)));
+        if let MessageContent::WithThinking { thinking, .. } = &msg.content {
+            out.push_str(&format!(
+                "<details>\n<summary>Thinking</summary>\n\n{}\n\n</details>\n\n",
+                close_fences(thinking.trim_end())
+            ));
+        }
+        out.push_str(&close_fences(msg.content.output().trim_end()));
+        out.push('\n');
+    }
+    out
+}
+
+/// The transcript as one standalone HTML page
+pub fn to_html(session: &Session) -> String {
+    let renderer = MarkdownRenderer::new();
+    let title = escape_html(&title_of(session));
+    let mut body = String::new();
+    for msg in &session.messages {
+        let class = match msg.role {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::System => "system",
+        };
+        body.push_str(&format!(
+            "<section class=\"message {}\">\n<h2>{}</h2>\n",
+            class,
+            escape_html(&heading(msg))
+        ));
+        if let MessageContent::WithThinking { thinking, .. } = &msg.content {
+            body.push_str(&format!(
+                "<details class=\"thinking\">\n<summary>Thinking</summary>\n{}</details>\n",
+                renderer.render_html(thinking)
+            ));
+        }
+        body.push_str(&renderer.render_html(msg.content.output()));
+        body.push_str("</section>\n");
+    }
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<main>\n\
+         <header>\n<h1>{title}</h1>\n<p class=\"meta\">{meta}</p>\n</header>\n\
+         {body}</main>\n</body>\n</html>\n",
+        meta = escape_html(&summary_line(session)),
+    )
+}
+
+/// e.g. `6 messages · meta-llama/Llama-3.3-70B-Instruct · 2025-01-14 09:30 · exported 2025-01-15 10:02`
+fn summary_line(session: &Session) -> String {
+    let mut parts = vec![format!("{} messages", session.messages.len())];
+    parts.extend(session.model.clone());
+    parts.extend(
+        session
+            .created
+            .map(|created| created.format("%Y-%m-%d %H:%M").to_string()),
+    );
+    parts.push(format!(
+        "exported {}",
+        Local::now().format("%Y-%m-%d %H:%M")
+    ));
+    parts.join(" · ")
+}
+
+/// Role and time, plus what the TUI shows next to an answer
+fn heading(msg: &Message) -> String {
+    let role = match msg.role {
+        Role::User => "You",
+        Role::Assistant => "AI",
+        Role::System => "System",
+    };
+    let mut parts = vec![
+        role.to_string(),
+        msg.datetime.format("%Y-%m-%d %H:%M:%S").to_string(),
+    ];
+    parts.extend(msg.model.clone());
+    parts.extend(
+        msg.latency_ms
+            .map(|ms| format!("{:.1}s", ms as f64 / 1000.0)),
+    );
+    if msg.finish_reason.as_deref() == Some("length") {
+        parts.push("truncated".to_string());
+    }
+    parts.extend(
+        msg.summarizes
+            .map(|n| format!("summary of {} earlier messages", n)),
+    );
+    parts.join(" · ")
+}
+
+/// Closes a code fence left open by a cut-off answer, so it cannot swallow the rest of
+/// the document
+fn close_fences(text: &str) -> String {
+    let mut open: Option<(char, usize)> = None;
+    for line in text.lines() {
+        let line = line.trim_start();
+        let Some(c) = line.chars().next().filter(|&c| c == '`' || c == '~') else {
+            continue;
+        };
+        let len = line.chars().take_while(|&ch| ch == c).count();
+        if len < 3 {
+            continue;
+        }
+        match open {
+            None => open = Some((c, len)),
+            Some((mark, open_len)) if c == mark && len >= open_len => {
+                if line[len..].trim().is_empty() {
+                    open = None;
+                }
+            }
+            Some(_) => {}
+        }
+    }
+    match open {
+        Some((mark, len)) => format!("{}\n{}", text, mark.to_string().repeat(len)),
+        None => text.to_string(),
+    }
+}
+
+const STYLE: &str = "
+body { margin: 0; background: #1e2128; color: #d8dee9;
+  font: 15px/1.6 -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; }
+main { max-width: 860px; margin: 0 auto; padding: 24px 16px 64px; }
+header { border-bottom: 1px solid #3b4252; margin-bottom: 24px; }
+h1 { font-size: 22px; margin: 0 0 4px; }
+.meta, h2 { color: #7b8394; font-size: 13px; font-weight: normal; }
+h2 { margin: 0 0 8px; }
+.message { border-left: 3px solid #4c566a; padding: 4px 0 4px 16px; margin: 0 0 24px; }
+.message.user { border-color: #a3be8c; }
+.message.assistant { border-color: #81a1c1; }
+.message.system { border-color: #7b8394; color: #aab1be; }
+details.thinking { color: #8f97a6; font-style: italic; margin-bottom: 12px; }
+details.thinking summary { cursor: pointer; }
+pre { padding: 12px; border-radius: 6px; overflow-x: auto; font-size: 13px; line-height: 1.45; }
+pre, code { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; }
+:not(pre) > code { background: #2b303b; padding: 1px 4px; border-radius: 4px; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #3b4252; padding: 4px 8px; }
+a { color: #88c0d0; }
+blockquote { margin: 0; padding-left: 12px; border-left: 3px solid #3b4252; color: #aab1be; }
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_closed_fences_alone() {
+        let text = "Here:\n```rust\nfn main() {}\n```\nDone.";
+        assert_eq!(close_fences(text), text);
+        assert_eq!(close_fences("no code at all"), "no code at all");
+    }
+
+    #[test]
+    fn closes_a_cut_off_fence() {
+        assert_eq!(
+            close_fences("Here:\n```rust\nfn main() {"),
+            "Here:\n```rust\nfn main() {\n```"
+        );
+    }
+
+    #[test]
+    fn closes_with_the_same_mark_and_length() {
+        assert_eq!(close_fences("~~~~\ncode"), "~~~~\ncode\n~~~~");
+        // A shorter run or the other mark does not close the fence
+        assert_eq!(
+            close_fences("````md\n```\nnested\n```\n~~~~"),
+            "````md\n```\nnested\n```\n~~~~\n````"
+        );
+        // Nor does a fence line with an info string
+        assert_eq!(close_fences("```\na\n```rust"), "```\na\n```rust\n```");
+    }
+
+    #[test]
+    fn counts_indented_fences() {
+        assert_eq!(
+            close_fences("1. step\n   ```sh\n   ls"),
+            "1. step\n   ```sh\n   ls\n```"
+        );
+        assert_eq!(close_fences("`inline` and ``two``"), "`inline` and ``two``");
+    }
+
+    #[test]
+    fn markdown_export_closes_a_cut_off_answer() {
+        let mut answer = Message::new(
+            Role::Assistant,
+            MessageContent::Text("```py\nprint(1)".to_string()),
+        );
+        answer.finish_reason = Some("length".to_string());
+        let session = Session {
+            messages: vec![answer],
+            ..Session::default()
+        };
+        let markdown = to_markdown(&session);
+        assert!(markdown.ends_with("```py\nprint(1)\n```\n"), "{}", markdown);
+        assert!(markdown.contains("truncated"), "{}", markdown);
+    }
+}
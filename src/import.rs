@@ -0,0 +1,696 @@
+use crate::{
+    app::parse_thinking_tokens,
+    types::{Message, MessageContent, Role, Session},
+};
+use chrono::{DateTime, Local};
+use serde_json::Value;
+use std::{cmp::Reverse, collections::BTreeMap, error::Error, fmt, fs, path::Path};
+
+/// Conversation files `/import` recognises
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// A Hugging Face chat-ui conversation: messages with `from`, possibly a tree
+    ChatUi,
+    /// ChatGPT's `conversations.json`: a `mapping` of nodes per conversation
+    ChatGpt,
+    /// `{"messages": [...]}` or a bare list, as sent to an OpenAI-compatible endpoint
+    OpenAi,
+}
+
+impl fmt::Display for ImportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ChatUi => "HF chat-ui",
+            Self::ChatGpt => "ChatGPT export",
+            Self::OpenAi => "OpenAI messages",
+        })
+    }
+}
+
+pub struct Imported {
+    pub format: ImportFormat,
+    pub session: Session,
+    /// How many conversations the file holds; exports often hold all of them
+    pub conversations: usize,
+    /// What could not be carried over, by kind, e.g. `"tool message" -> 3`
+    pub skipped: BTreeMap<&'static str, usize>,
+}
+
+impl Imported {
+    /// e.g. `3 tool messages, 1 attachment`
+    pub fn skipped_summary(&self) -> Option<String> {
+        if self.skipped.is_empty() {
+            return None;
+        }
+        let parts: Vec<String> = self
+            .skipped
+            .iter()
+            .map(|(what, &n)| format!("{} {}{}", n, what, if n == 1 { "" } else { "s" }))
+            .collect();
+        Some(parts.join(", "))
+    }
+}
+
+/// Reads conversation `index` (1 = most recently updated) from a file written by another
+/// chat UI
+pub fn import_file(path: &Path, index: usize) -> Result<Imported, Box<dyn Error>> {
+    let json: Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+    if serde_json::from_value::<Session>(json.clone()).is_ok()
+        || serde_json::from_value::<Vec<Message>>(json.clone()).is_ok()
+    {
+        return Err(format!(
+            "{} is a hugging-code conversation; open it with /load",
+            path.display()
+        )
+        .into());
+    }
+
+    let mut conversations = match json {
+        // A bare list of chat messages rather than a list of conversations
+        Value::Array(items) if items.first().is_some_and(|item| item.get("role").is_some()) => {
+            vec![serde_json::json!({ "messages": items })]
+        }
+        Value::Array(items) => items,
+        conversation => vec![conversation],
+    };
+    conversations.sort_by_key(|conversation| {
+        Reverse(
+            timestamp(conversation.get("update_time"))
+                .or_else(|| timestamp(conversation.get("updatedAt"))),
+        )
+    });
+    let count = conversations.len();
+    let conversation = index
+        .checked_sub(1)
+        .and_then(|i| conversations.get(i))
+        .ok_or_else(|| format!("{} holds {} conversations", path.display(), count))?;
+
+    let mut importer = Importer::default();
+    let format = if conversation.get("mapping").is_some() {
+        importer.chatgpt(conversation)?;
+        ImportFormat::ChatGpt
+    } else {
+        let messages = conversation
+            .get("messages")
+            .and_then(Value::as_array)
+            .ok_or("not a HF chat-ui, ChatGPT or OpenAI {\"messages\": [...]} conversation")?;
+        if messages.iter().any(|m| m.get("from").is_some()) {
+            importer.chat_ui(conversation, messages);
+            ImportFormat::ChatUi
+        } else {
+            importer.openai(conversation, messages);
+            ImportFormat::OpenAi
+        }
+    };
+    if importer.thinking.is_some() {
+        importer.skip("reasoning block without an answer");
+    }
+    if importer.messages.is_empty() {
+        return Err(format!("no messages found in this {} file", format).into());
+    }
+
+    let model = importer
+        .model
+        .clone()
+        .or_else(|| importer.messages.iter().rev().find_map(|m| m.model.clone()));
+    let mut session = Session {
+        title: conversation
+            .get("title")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|title| !title.is_empty())
+            .map(str::to_string),
+        model,
+        created: timestamp(conversation.get("create_time"))
+            .or_else(|| timestamp(conversation.get("createdAt"))),
+        messages: importer.messages,
+        ..Session::default()
+    };
+    session.migrate();
+    Ok(Imported {
+        format,
+        session,
+        conversations: count,
+        skipped: importer.skipped,
+    })
+}
+
+#[derive(Default)]
+struct Importer {
+    messages: Vec<Message>,
+    skipped: BTreeMap<&'static str, usize>,
+    /// Conversation-wide model, for formats that do not record it per message
+    model: Option<String>,
+    /// Reasoning stored as its own node, waiting for the answer it belongs to
+    thinking: Option<String>,
+}
+
+impl Importer {
+    fn skip(&mut self, what: &'static str) {
+        *self.skipped.entry(what).or_default() += 1;
+    }
+
+    fn push(
+        &mut self,
+        role: Role,
+        text: String,
+        thinking: Option<String>,
+        datetime: Option<DateTime<Local>>,
+        model: Option<String>,
+    ) {
+        if role != Role::Assistant && self.thinking.take().is_some() {
+            self.skip("reasoning block without an answer");
+        }
+        let thinking = thinking
+            .or_else(|| self.thinking.take())
+            .map(|thinking| thinking.trim().to_string())
+            .filter(|thinking| !thinking.is_empty());
+        let text = text.trim().to_string();
+        if text.is_empty() {
+            self.skip("empty message");
+            return;
+        }
+        let content = match thinking {
+            Some(thinking) => MessageContent::WithThinking {
+                thinking,
+                output: text,
+            },
+            None if role == Role::Assistant => parse_thinking_tokens(&text),
+            None => MessageContent::Text(text),
+        };
+        let mut msg = Message::new(role, content);
+        // Keep the order sensible when a message has no time of its own
+        if let Some(datetime) = datetime.or(self.messages.last().map(|m| m.datetime)) {
+            msg.datetime = datetime;
+        }
+        if msg.role == Role::Assistant {
+            msg.model = model.or_else(|| self.model.clone());
+        }
+        self.messages.push(msg);
+    }
+
+    /// Follows `current_node`, the branch ChatGPT was showing, or else the newest leaf, back
+    /// to the root
+    fn chatgpt(&mut self, conversation: &Value) -> Result<(), Box<dyn Error>> {
+        let mapping = conversation
+            .get("mapping")
+            .and_then(Value::as_object)
+            .ok_or("ChatGPT export without a mapping")?;
+        let mut node_id = conversation
+            .get("current_node")
+            .and_then(Value::as_str)
+            .or_else(|| {
+                mapping
+                    .iter()
+                    .filter(|(_, node)| {
+                        node.get("children")
+                            .and_then(Value::as_array)
+                            .is_none_or(|children| children.is_empty())
+                    })
+                    .max_by_key(|(_, node)| timestamp(node.pointer("/message/create_time")))
+                    .map(|(id, _)| id.as_str())
+            });
+        let mut path = Vec::new();
+        while let Some(node) = node_id.and_then(|id| mapping.get(id)) {
+            if path.len() > mapping.len() {
+                return Err("ChatGPT export with a cycle in its mapping".into());
+            }
+            path.push(node);
+            node_id = node.get("parent").and_then(Value::as_str);
+        }
+        path.reverse();
+
+        let has_message = |node: &Value| node.get("message").is_some_and(Value::is_object);
+        let off_branch = mapping.values().filter(|node| has_message(node)).count()
+            - path.iter().filter(|node| has_message(node)).count();
+        for _ in 0..off_branch {
+            self.skip("message on another branch");
+        }
+
+        for msg in path
+            .iter()
+            .filter_map(|node| node.get("message"))
+            .filter(|m| m.is_object())
+        {
+            let metadata = msg.get("metadata");
+            if metadata
+                .and_then(|m| m.get("is_visually_hidden_from_conversation"))
+                .and_then(Value::as_bool)
+                == Some(true)
+            {
+                continue;
+            }
+            let role = match msg.pointer("/author/role").and_then(Value::as_str) {
+                Some("user") => Role::User,
+                Some("assistant") => Role::Assistant,
+                Some("system") => Role::System,
+                Some("tool") => {
+                    self.skip("tool message");
+                    continue;
+                }
+                _ => {
+                    self.skip("message with an unknown role");
+                    continue;
+                }
+            };
+            let content = msg.get("content");
+            let text = match content
+                .and_then(|c| c.get("content_type"))
+                .and_then(Value::as_str)
+            {
+                Some("text") | Some("multimodal_text") => {
+                    let mut text = Vec::new();
+                    for part in content
+                        .and_then(|c| c.get("parts"))
+                        .and_then(Value::as_array)
+                        .into_iter()
+                        .flatten()
+                    {
+                        match part.as_str() {
+                            Some(part) => text.push(part),
+                            None => self.skip("attachment"),
+                        }
+                    }
+                    text.join("\n")
+                }
+                Some("thoughts") => {
+                    let thoughts: Vec<&str> = content
+                        .and_then(|c| c.get("thoughts"))
+                        .and_then(Value::as_array)
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|thought| {
+                            thought
+                                .get("content")
+                                .or_else(|| thought.get("summary"))
+                                .and_then(Value::as_str)
+                        })
+                        .collect();
+                    if !thoughts.is_empty() {
+                        self.thinking = Some(thoughts.join("\n\n"));
+                    }
+                    continue;
+                }
+                // "Thought for 12 seconds"
+                Some("reasoning_recap") => continue,
+                Some("code") => {
+                    self.skip("tool call");
+                    continue;
+                }
+                _ => {
+                    self.skip("unsupported content block");
+                    continue;
+                }
+            };
+            if role == Role::System && text.trim().is_empty() {
+                continue;
+            }
+            let model = metadata
+                .and_then(|m| m.get("model_slug"))
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            self.push(role, text, None, timestamp(msg.get("create_time")), model);
+        }
+        Ok(())
+    }
+
+    /// chat-ui keeps every version of every message in one list; the last one and its
+    /// ancestors are the branch that was showing
+    fn chat_ui(&mut self, conversation: &Value, messages: &[Value]) {
+        self.model = conversation
+            .get("model")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let by_id = |id: &Value| messages.iter().find(|m| m.get("id") == Some(id));
+        let path: Vec<&Value> = match messages
+            .last()
+            .and_then(|last| Some((last, last.get("ancestors")?.as_array()?)))
+        {
+            Some((last, ancestors)) => ancestors
+                .iter()
+                .filter_map(by_id)
+                .chain(std::iter::once(last))
+                .collect(),
+            None => messages.iter().collect(),
+        };
+        for _ in path.len()..messages.len() {
+            self.skip("message on another branch");
+        }
+
+        let preprompt = conversation
+            .get("preprompt")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        // The system message is often left empty, with the prompt kept on the conversation
+        let has_system = path.iter().any(|m| {
+            m.get("from").and_then(Value::as_str) == Some("system")
+                && m.get("content")
+                    .and_then(Value::as_str)
+                    .is_some_and(|text| !text.trim().is_empty())
+        });
+        if !has_system && !preprompt.trim().is_empty() {
+            let created = timestamp(conversation.get("createdAt"));
+            self.push(Role::System, preprompt.to_string(), None, created, None);
+        }
+        for msg in path {
+            let role = match msg.get("from").and_then(Value::as_str) {
+                Some("user") => Role::User,
+                Some("assistant") => Role::Assistant,
+                Some("system") => Role::System,
+                _ => {
+                    self.skip("message with an unknown role");
+                    continue;
+                }
+            };
+            for _ in msg
+                .get("files")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                self.skip("attachment");
+            }
+            let text = msg
+                .get("content")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            if role == Role::System && text.trim().is_empty() {
+                continue;
+            }
+            let reasoning = msg
+                .get("reasoning")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            self.push(role, text, reasoning, timestamp(msg.get("createdAt")), None);
+        }
+    }
+
+    fn openai(&mut self, conversation: &Value, messages: &[Value]) {
+        self.model = conversation
+            .get("model")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        for msg in messages {
+            let role = match msg.get("role").and_then(Value::as_str) {
+                Some("user") => Role::User,
+                Some("assistant") => Role::Assistant,
+                Some("system") | Some("developer") => Role::System,
+                Some("tool") | Some("function") => {
+                    self.skip("tool message");
+                    continue;
+                }
+                _ => {
+                    self.skip("message with an unknown role");
+                    continue;
+                }
+            };
+            let tool_calls = msg
+                .get("tool_calls")
+                .and_then(Value::as_array)
+                .map_or(0, Vec::len);
+            for _ in 0..tool_calls {
+                self.skip("tool call");
+            }
+            let text = match msg.get("content") {
+                Some(Value::String(text)) => text.clone(),
+                Some(Value::Array(parts)) => {
+                    let mut text = Vec::new();
+                    for part in parts {
+                        match part.get("text").and_then(Value::as_str) {
+                            Some(part) => text.push(part),
+                            None => self.skip("attachment"),
+                        }
+                    }
+                    text.join("\n")
+                }
+                _ => String::new(),
+            };
+            if text.trim().is_empty() && (tool_calls > 0 || role == Role::System) {
+                continue;
+            }
+            let reasoning = ["reasoning_content", "reasoning", "thinking"]
+                .iter()
+                .find_map(|key| msg.get(*key).and_then(Value::as_str))
+                .map(str::to_string);
+            self.push(role, text, reasoning, None, None);
+        }
+    }
+}
+
+/// ChatGPT's Unix seconds, chat-ui's ISO dates, or Mongo's `{"$date": ...}` around either
+fn timestamp(value: Option<&Value>) -> Option<DateTime<Local>> {
+    match value? {
+        Value::Number(secs) => {
+            let secs = secs.as_f64()?;
+            DateTime::from_timestamp(secs.trunc() as i64, (secs.fract() * 1e9) as u32)
+                .map(|utc| utc.with_timezone(&Local))
+        }
+        Value::String(date) => DateTime::parse_from_rfc3339(date)
+            .ok()
+            .map(|date| date.with_timezone(&Local)),
+        Value::Object(wrapper) => timestamp(wrapper.get("$date")),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    /// Each message as `(role, thinking, output)`
+    fn transcript(session: &Session) -> Vec<(&'static str, Option<&str>, &str)> {
+        session
+            .messages
+            .iter()
+            .map(|msg| {
+                let role = match msg.role {
+                    Role::User => "user",
+                    Role::Assistant => "assistant",
+                    Role::System => "system",
+                };
+                let thinking = match &msg.content {
+                    MessageContent::WithThinking { thinking, .. } => Some(thinking.as_str()),
+                    MessageContent::Text(_) => None,
+                };
+                (role, thinking, msg.content.output())
+            })
+            .collect()
+    }
+
+    fn skipped(imported: &Imported) -> Vec<(&'static str, usize)> {
+        imported
+            .skipped
+            .iter()
+            .map(|(&what, &n)| (what, n))
+            .collect()
+    }
+
+    const CHATGPT: &str = r#"[
+        {"title": "Old", "create_time": 1700000000.5, "update_time": 1700000100, "current_node": "a",
+         "mapping": {
+            "r": {"id": "r", "message": null, "parent": null, "children": ["a"]},
+            "a": {"id": "a", "parent": "r", "children": [],
+                  "message": {"author": {"role": "user"}, "create_time": 1700000001,
+                              "content": {"content_type": "text", "parts": ["old q"]}}}}},
+        {"title": "Rust help", "create_time": 1710000000.5, "update_time": 1710000500, "current_node": "n5",
+         "mapping": {
+            "root": {"id": "root", "message": null, "parent": null, "children": ["n0"]},
+            "n0": {"id": "n0", "parent": "root", "children": ["n1"],
+                   "message": {"author": {"role": "system"},
+                               "content": {"content_type": "text", "parts": [""]},
+                               "metadata": {"is_visually_hidden_from_conversation": true}}},
+            "n1": {"id": "n1", "parent": "n0", "children": ["n2", "alt"],
+                   "message": {"author": {"role": "user"}, "create_time": 1710000001,
+                               "content": {"content_type": "multimodal_text",
+                                           "parts": [{"asset_pointer": "file-x"}, "What is this?"]}}},
+            "alt": {"id": "alt", "parent": "n1", "children": [],
+                    "message": {"author": {"role": "assistant"},
+                                "content": {"content_type": "text", "parts": ["old answer"]}}},
+            "n2": {"id": "n2", "parent": "n1", "children": ["n3"],
+                   "message": {"author": {"role": "assistant"}, "create_time": 1710000002,
+                               "content": {"content_type": "thoughts",
+                                           "thoughts": [{"summary": "s", "content": "Let me think"}]}}},
+            "n3": {"id": "n3", "parent": "n2", "children": ["n4"],
+                   "message": {"author": {"role": "assistant"}, "create_time": 1710000003,
+                               "content": {"content_type": "reasoning_recap", "content": "Thought for 3s"}}},
+            "n4": {"id": "n4", "parent": "n3", "children": ["n5"],
+                   "message": {"author": {"role": "tool"},
+                               "content": {"content_type": "text", "parts": ["x"]}}},
+            "n5": {"id": "n5", "parent": "n4", "children": [],
+                   "message": {"author": {"role": "assistant"}, "create_time": 1710000004,
+                               "metadata": {"model_slug": "gpt-4o"},
+                               "content": {"content_type": "text", "parts": ["It is a `crab`."]}}}}}
+    ]"#;
+
+    #[test]
+    fn imports_the_branch_chatgpt_was_showing() {
+        let dir = TempDir::new("import-chatgpt");
+        let file = dir.file("chatgpt.json", CHATGPT);
+        let imported = import_file(&file, 1).unwrap();
+        assert_eq!(imported.format, ImportFormat::ChatGpt);
+        assert_eq!(imported.conversations, 2);
+        let session = &imported.session;
+        assert_eq!(session.title.as_deref(), Some("Rust help"));
+        assert_eq!(session.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(
+            transcript(session),
+            [
+                ("user", None, "What is this?"),
+                ("assistant", Some("Let me think"), "It is a `crab`."),
+            ]
+        );
+        assert_eq!(
+            session.created,
+            timestamp(Some(&serde_json::json!(1710000000.5)))
+        );
+        assert_eq!(
+            skipped(&imported),
+            [
+                ("attachment", 1),
+                ("message on another branch", 1),
+                ("tool message", 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn picks_older_conversations_by_index() {
+        let dir = TempDir::new("import-chatgpt-index");
+        let file = dir.file("chatgpt-index.json", CHATGPT);
+        let imported = import_file(&file, 2).unwrap();
+        assert_eq!(imported.session.title.as_deref(), Some("Old"));
+        assert_eq!(transcript(&imported.session), [("user", None, "old q")]);
+
+        let e = import_file(&file, 3).err().unwrap().to_string();
+        assert!(e.ends_with("holds 2 conversations"), "{}", e);
+    }
+
+    #[test]
+    fn imports_the_last_chat_ui_branch_with_its_preprompt() {
+        let dir = TempDir::new("import-chat-ui");
+        let file = dir.file(
+            "chat-ui.json",
+            r#"{"title": "HF chat", "model": "meta-llama/Llama-3.3-70B-Instruct", "preprompt": "Be brief",
+                "createdAt": {"$date": "2025-01-14T09:30:00.000Z"},
+                "messages": [
+                    {"id": "s", "from": "system", "content": "", "createdAt": "2025-01-14T09:30:00.000Z"},
+                    {"id": "u1", "from": "user", "content": "hi", "ancestors": ["s"],
+                     "createdAt": "2025-01-14T09:30:01.000Z", "files": [{"name": "a.png"}]},
+                    {"id": "a1", "from": "assistant", "content": "hello", "ancestors": ["s", "u1"],
+                     "createdAt": "2025-01-14T09:30:02.000Z"},
+                    {"id": "a2", "from": "assistant", "content": "hey there", "reasoning": "greet them",
+                     "ancestors": ["s", "u1"], "createdAt": "2025-01-14T09:31:02.000Z"}
+                ]}"#,
+        );
+        let imported = import_file(&file, 1).unwrap();
+        assert_eq!(imported.format, ImportFormat::ChatUi);
+        let session = &imported.session;
+        assert_eq!(
+            transcript(session),
+            [
+                ("system", None, "Be brief"),
+                ("user", None, "hi"),
+                ("assistant", Some("greet them"), "hey there"),
+            ]
+        );
+        assert_eq!(
+            session.messages[2].model.as_deref(),
+            Some("meta-llama/Llama-3.3-70B-Instruct")
+        );
+        assert_eq!(
+            session.created,
+            timestamp(Some(&serde_json::json!("2025-01-14T09:30:00.000Z")))
+        );
+        assert_eq!(
+            skipped(&imported),
+            [("attachment", 1), ("message on another branch", 1)]
+        );
+    }
+
+    #[test]
+    fn imports_openai_messages() {
+        let dir = TempDir::new("import-openai");
+        let file = dir.file(
+            "openai.json",
+            r#"{"model": "gpt-x", "messages": [
+                {"role": "developer", "content": "sys"},
+                {"role": "user", "content": [{"type": "text", "text": "Look"},
+                                             {"type": "image_url", "image_url": {"url": "x"}}]},
+                {"role": "assistant", "content": null, "tool_calls": [{"id": "1"}]},
+                {"role": "tool", "content": "r"},
+                {"role": "assistant", "content": "<thinking>hmm</thinking>Done"},
+                {"role": "assistant", "reasoning_content": "why", "content": "Because"}
+            ]}"#,
+        );
+        let imported = import_file(&file, 1).unwrap();
+        assert_eq!(imported.format, ImportFormat::OpenAi);
+        assert_eq!(imported.session.model.as_deref(), Some("gpt-x"));
+        assert_eq!(
+            transcript(&imported.session),
+            [
+                ("system", None, "sys"),
+                ("user", None, "Look"),
+                ("assistant", Some("hmm"), "Done"),
+                ("assistant", Some("why"), "Because"),
+            ]
+        );
+        assert_eq!(
+            skipped(&imported),
+            [("attachment", 1), ("tool call", 1), ("tool message", 1)]
+        );
+        assert_eq!(
+            imported.skipped_summary().as_deref(),
+            Some("1 attachment, 1 tool call, 1 tool message")
+        );
+    }
+
+    #[test]
+    fn imports_a_bare_message_list() {
+        let dir = TempDir::new("import-bare-list");
+        let file = dir.file(
+            "bare-list.json",
+            r#"[{"role": "user", "content": "bare"}, {"role": "assistant", "content": "list"}]"#,
+        );
+        let imported = import_file(&file, 1).unwrap();
+        assert_eq!(imported.format, ImportFormat::OpenAi);
+        assert_eq!(imported.conversations, 1);
+        assert_eq!(
+            transcript(&imported.session),
+            [("user", None, "bare"), ("assistant", None, "list")]
+        );
+        assert!(imported.skipped_summary().is_none());
+    }
+
+    #[test]
+    fn refuses_files_it_cannot_import() {
+        let dir = TempDir::new("import-refused");
+        let file = dir.file(
+            "own.json",
+            r#"{"version": 2, "messages": [{"role": "User", "content": {"Text": "hi"},
+                                            "datetime": "2025-01-01T10:00:00+01:00"}]}"#,
+        );
+        let e = import_file(&file, 1).err().unwrap().to_string();
+        assert!(
+            e.ends_with("is a hugging-code conversation; open it with /load"),
+            "{}",
+            e
+        );
+
+        let file = dir.file("unknown.json", r#"{"foo": 1}"#);
+        let e = import_file(&file, 1).err().unwrap().to_string();
+        assert!(
+            e.starts_with("not a HF chat-ui, ChatGPT or OpenAI"),
+            "{}",
+            e
+        );
+
+        let file = dir.file(
+            "empty.json",
+            r#"{"messages": [{"role": "user", "content": " "}]}"#,
+        );
+        let e = import_file(&file, 1).err().unwrap().to_string();
+        assert_eq!(e, "no messages found in this OpenAI messages file");
+    }
+}
@@ -1,7 +1,20 @@
 pub mod app;
+pub mod backend;
+pub mod cli;
 pub mod config;
+pub mod context;
+pub mod doctor;
+pub mod error;
 pub mod event_handler;
+pub mod export;
+pub mod import;
 pub mod markdown;
+pub mod oneshot;
+pub mod sessions;
+pub mod streaming;
+#[cfg(test)]
+mod test_util;
+pub mod tokenizer;
 pub mod types;
 pub mod ui;
 pub mod widgets;
@@ -9,28 +9,81 @@ use ratatui::{
 };
 use std::{io, time::{Duration, Instant}};
 
-mod app;
-mod config;
-mod event_handler;
-mod markdown;
-mod types;
-mod ui;
-mod widgets;
-
-use app::App;
-use config::ChatConfig;
+use clap::Parser;
+use hugging_code::{
+    app::{App, StatusType},
+    cli::{Cli, Command},
+    config::ChatConfig,
+    doctor, event_handler, oneshot,
+    sessions::{self, Resume},
+    ui,
+};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let config = ChatConfig::from_env()?;
+    let cli = Cli::parse();
+
+    // The doctor reports a broken configuration instead of refusing to start over it
+    if let Some(Command::Doctor) = cli.command {
+        let (config, problems) = ChatConfig::load_best_effort(&cli);
+        let report = doctor::diagnose(&config, &problems).await;
+        println!("{}", report);
+        std::process::exit(if report.failed() { 1 } else { 0 });
+    }
+
+    let (config, warnings) = match ChatConfig::load(&cli) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("✗ {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    if cli.is_one_shot() {
+        let status = oneshot::run(config, &cli).await;
+        std::process::exit(status.code());
+    }
+
+    // Setup hints are for people; one-shot output stays clean for scripts
+    for warning in &warnings {
+        eprintln!("\n⚠️  {}\n", warning);
+    }
+
+    let resume = match sessions::resume_target(&config, &cli) {
+        Ok(resume) => resume,
+        Err(e) => {
+            eprintln!("✗ {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    // Anything that can fail goes before the terminal is switched to raw mode, so the
+    // error is readable and the shell is left as it was
+    let mut app = match App::new(config) {
+        Ok(app) => app,
+        Err(e) => {
+            eprintln!("✗ {}", e);
+            std::process::exit(2);
+        }
+    };
+    match resume {
+        Some(Resume::Session(id)) => {
+            if let Err(e) = app.open_session(&id) {
+                app.status_message = Some((
+                    format!("✗ Cannot open session {}: {}", id, e),
+                    StatusType::Error,
+                ));
+            }
+        }
+        Some(Resume::Browse) => app.open_session_browser(),
+        None => {}
+    }
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
-
-    let app = App::new(config)?;
     let res = run_app(&mut terminal, app).await;
 
     disable_raw_mode()?;
@@ -60,15 +113,16 @@ async fn run_app(
 
         app.update_loader_animation();
         app.process_api_response();
+        app.poll_model_list();
 
         let timeout = tick_rate
             .checked_sub(last_tick.elapsed())
             .unwrap_or_else(|| Duration::from_secs(0));
 
-        if crossterm::event::poll(timeout)? {
-            if event_handler::handle_event(&mut app, crossterm_event::read()?).await? {
-                return Ok(());
-            }
+        if crossterm::event::poll(timeout)?
+            && event_handler::handle_event(&mut app, crossterm_event::read()?).await?
+        {
+            return Ok(());
         }
 
         if last_tick.elapsed() >= tick_rate {
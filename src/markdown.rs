@@ -1,4 +1,4 @@
-use pulldown_cmark::{Event as MdEvent, HeadingLevel, Options, Parser, Tag, TagEnd};
+use pulldown_cmark::{CodeBlockKind, Event as MdEvent, HeadingLevel, Options, Parser, Tag, TagEnd};
 use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
@@ -6,6 +6,7 @@ use ratatui::{
 use syntect::{
     easy::HighlightLines,
     highlighting::{Style as SyntectStyle, Theme, ThemeSet},
+    html::highlighted_html_for_string,
     parsing::SyntaxSet,
 };
 
@@ -66,16 +67,12 @@ impl MarkdownRenderer {
                     Tag::TableHead => {
                         in_table = true;
                     }
-                    Tag::TableRow => {
-                        if in_table {
-                            current_row.clear();
-                        }
+                    Tag::TableRow if in_table => {
+                        current_row.clear();
                     }
-                    Tag::TableCell => {
-                        if in_table {
-                            in_table_cell = true;
-                            current_cell.clear();
-                        }
+                    Tag::TableCell if in_table => {
+                        in_table_cell = true;
+                        current_cell.clear();
                     }
                     Tag::Link { dest_url, .. } => {
                         in_link = true;
@@ -122,52 +119,40 @@ impl MarkdownRenderer {
                         let indent = "  ".repeat(list_depth.saturating_sub(1));
                         current_line_spans.push(Span::raw(format!("{}• ", indent)));
                     }
-                    Tag::Paragraph => {
-                        if !current_line_spans.is_empty() {
-                            lines.push(Line::from(current_line_spans.clone()));
-                            current_line_spans.clear();
-                        }
+                    Tag::Paragraph if !current_line_spans.is_empty() => {
+                        lines.push(Line::from(current_line_spans.clone()));
+                        current_line_spans.clear();
                     }
                     _ => {}
                 },
                 MdEvent::End(tag) => match tag {
-                    TagEnd::TableCell => {
-                        if in_table {
-                            in_table_cell = false;
-                            current_row.push(current_cell.trim().to_string());
-                        }
+                    TagEnd::TableCell if in_table => {
+                        in_table_cell = false;
+                        current_row.push(current_cell.trim().to_string());
                     }
-                    TagEnd::TableRow => {
-                        if in_table {
-                            if !current_row.is_empty() {
-                                table_rows.push(current_row.clone());
-                                current_row.clear();
-                            }
-                        }
+                    TagEnd::TableRow if in_table && !current_row.is_empty() => {
+                        table_rows.push(current_row.clone());
+                        current_row.clear();
                     }
-                    TagEnd::TableHead => {
-                        if in_table {
-                            table_header_rows = table_rows.len();
-                        }
+                    TagEnd::TableHead if in_table => {
+                        table_header_rows = table_rows.len();
                     }
-                    TagEnd::Table => {
-                        if in_table {
-                            if !current_line_spans.is_empty() {
-                                lines.push(Line::from(current_line_spans.clone()));
-                                current_line_spans.clear();
-                            }
-                            let table_lines =
-                                render_table(&table_rows, table_header_rows, base_style);
-                            lines.extend(table_lines);
-                            lines.push(Line::default());
-
-                            in_table = false;
-                            in_table_cell = false;
-                            table_rows.clear();
-                            current_row.clear();
-                            current_cell.clear();
-                            table_header_rows = 0;
+                    TagEnd::Table if in_table => {
+                        if !current_line_spans.is_empty() {
+                            lines.push(Line::from(current_line_spans.clone()));
+                            current_line_spans.clear();
                         }
+                        let table_lines =
+                            render_table(&table_rows, table_header_rows, base_style);
+                        lines.extend(table_lines);
+                        lines.push(Line::default());
+
+                        in_table = false;
+                        in_table_cell = false;
+                        table_rows.clear();
+                        current_row.clear();
+                        current_cell.clear();
+                        table_header_rows = 0;
                     }
                     TagEnd::Heading(_) => {
                         lines.push(Line::from(current_line_spans.clone()));
@@ -288,10 +273,10 @@ impl MarkdownRenderer {
             lines.push(Line::from(current_line_spans));
         }
 
-        while lines.first().map_or(false, |l| l.spans.is_empty()) {
+        while lines.first().is_some_and(|l| l.spans.is_empty()) {
             lines.remove(0);
         }
-        while lines.last().map_or(false, |l| l.spans.is_empty()) {
+        while lines.last().is_some_and(|l| l.spans.is_empty()) {
             lines.pop();
         }
 
@@ -362,6 +347,69 @@ impl MarkdownRenderer {
         output
     }
 
+    /// Renders markdown to an HTML fragment. Code blocks are highlighted with inline colours
+    /// from the same theme as the terminal, so the result needs no stylesheet; raw HTML in
+    /// the markdown is shown as text rather than passed through, and so are links and images
+    /// that are not http, https or mailto, such as `javascript:` URLs.
+    pub fn render_html(&self, markdown: &str) -> String {
+        let mut options = Options::empty();
+        options.insert(Options::ENABLE_TABLES);
+        options.insert(Options::ENABLE_STRIKETHROUGH);
+        let mut events = Vec::new();
+        let mut code: Option<(Option<String>, String)> = None;
+        // Whether each link or image still open is kept; dropped ones leave their text
+        let mut links = Vec::new();
+        for event in Parser::new_ext(markdown, options) {
+            match event {
+                MdEvent::Start(Tag::Link { ref dest_url, .. })
+                | MdEvent::Start(Tag::Image { ref dest_url, .. }) => {
+                    let safe = is_safe_url(dest_url);
+                    links.push(safe);
+                    if safe {
+                        events.push(event);
+                    }
+                }
+                MdEvent::End(TagEnd::Link) | MdEvent::End(TagEnd::Image) => {
+                    if links.pop().unwrap_or(true) {
+                        events.push(event);
+                    }
+                }
+                MdEvent::Start(Tag::CodeBlock(kind)) => {
+                    let lang = match kind {
+                        CodeBlockKind::Fenced(info) => {
+                            info.split_whitespace().next().map(str::to_string)
+                        }
+                        CodeBlockKind::Indented => None,
+                    };
+                    code = Some((lang, String::new()));
+                }
+                MdEvent::Text(text) if code.is_some() => {
+                    if let Some((_, content)) = &mut code {
+                        content.push_str(&text);
+                    }
+                }
+                MdEvent::End(TagEnd::CodeBlock) => {
+                    if let Some((lang, content)) = code.take() {
+                        events.push(MdEvent::Html(
+                            self.code_block_html(&content, lang.as_deref()).into(),
+                        ));
+                    }
+                }
+                MdEvent::Html(html) | MdEvent::InlineHtml(html) => events.push(MdEvent::Text(html)),
+                event => events.push(event),
+            }
+        }
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(&mut html, events.into_iter());
+        html
+    }
+
+    fn code_block_html(&self, content: &str, lang: Option<&str>) -> String {
+        let syntax = self.syntax_for_lang(lang);
+        highlighted_html_for_string(content, &self.syntax_set, syntax, self.theme())
+            .unwrap_or_else(|_| format!("<pre><code>{}</code></pre>\n", escape_html(content)))
+    }
+
     fn syntax_for_lang(&self, lang: Option<&str>) -> &syntect::parsing::SyntaxReference {
         if let Some(token) = lang {
             if let Some(syntax) = self.syntax_set.find_syntax_by_token(token) {
@@ -383,6 +431,30 @@ impl MarkdownRenderer {
     }
 }
 
+/// Only schemes that cannot run script or load local files may become a link
+fn is_safe_url(url: &str) -> bool {
+    let scheme = url.split_once(':').map_or("", |(scheme, _)| scheme);
+    ["http", "https", "mailto"]
+        .iter()
+        .any(|safe| scheme.eq_ignore_ascii_case(safe))
+}
+
+/// Escapes text for HTML element content and attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 fn render_table(rows: &[Vec<String>], header_rows: usize, base_style: Style) -> Vec<Line<'static>> {
     if rows.is_empty() {
         return Vec::new();
@@ -407,9 +479,9 @@ fn render_table(rows: &[Vec<String>], header_rows: usize, base_style: Style) ->
 
     for (idx, row) in rows.iter().enumerate() {
         let mut line = String::new();
-        for col in 0..col_count {
+        for (col, width) in widths.iter().enumerate() {
             let cell = row.get(col).map(String::as_str).unwrap_or("");
-            let pad = widths[col].saturating_sub(cell.len());
+            let pad = width.saturating_sub(cell.len());
             line.push_str(cell);
             line.push_str(&" ".repeat(pad));
             if col + 1 < col_count {
@@ -425,8 +497,8 @@ fn render_table(rows: &[Vec<String>], header_rows: usize, base_style: Style) ->
 
         if idx + 1 == header_rows {
             let mut sep = String::new();
-            for col in 0..col_count {
-                sep.push_str(&"-".repeat(widths[col].max(1)));
+            for (col, width) in widths.iter().enumerate() {
+                sep.push_str(&"-".repeat((*width).max(1)));
                 if col + 1 < col_count {
                     sep.push_str("-+-");
                 }
@@ -473,3 +545,59 @@ fn syntect_to_style(style: SyntectStyle) -> Style {
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_keeps_web_and_mail_links() {
+        let html = MarkdownRenderer::new().render_html(
+            "[docs](https://hf.co/docs) or [mail](mailto:a@b.c) ![logo](HTTP://x/y.png)",
+        );
+        assert!(
+            html.contains(r#"<a href="https://hf.co/docs">docs</a>"#),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains(r#"<a href="mailto:a@b.c">mail</a>"#),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains(r#"<img src="HTTP://x/y.png" alt="logo""#),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn html_turns_other_links_into_text() {
+        let html = MarkdownRenderer::new().render_html(
+            "[click](javascript:alert(1)) <JavaScript:alert(2)> ![x](data:image/svg+xml,<svg>) [rel](page.html)",
+        );
+        assert!(!html.contains("href"), "{}", html);
+        assert!(!html.contains("<img"), "{}", html);
+        assert!(html.contains("click"), "{}", html);
+        assert!(html.contains("JavaScript:alert(2)"), "{}", html);
+        assert!(html.contains("rel"), "{}", html);
+    }
+
+    #[test]
+    fn html_shows_raw_html_as_text() {
+        let html =
+            MarkdownRenderer::new().render_html("<script>alert(1)</script>\n\nhi <b>there</b>");
+        assert!(!html.contains("<script>"), "{}", html);
+        assert!(html.contains("&lt;script&gt;"), "{}", html);
+        assert!(html.contains("&lt;b&gt;"), "{}", html);
+    }
+
+    #[test]
+    fn escapes_every_special_character() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &#39;Jerry&#39;&lt;/a&gt;"
+        );
+    }
+}
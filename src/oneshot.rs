@@ -0,0 +1,348 @@
+use crate::{
+    app::{parse_thinking_tokens, ApiMessage, App},
+    cli::Cli,
+    config::ChatConfig,
+    types::{MessageContent, Role},
+};
+use std::{
+    future::Future,
+    io::{self, Read, Write},
+};
+
+/// Exit status of a non-interactive run, so scripts and git hooks can branch on the outcome
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// The full answer was printed
+    Success = 0,
+    /// The request failed after any retries, or the connection was lost
+    RequestFailed = 1,
+    /// Bad arguments, config or input; nothing was sent
+    Usage = 2,
+    /// The answer was printed but cut off by `max_tokens`
+    Truncated = 3,
+    /// The endpoint answered with no content
+    Empty = 4,
+    /// Interrupted with Ctrl-C
+    Interrupted = 130,
+}
+
+impl ExitStatus {
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+/// Asks the question given by `--prompt` and/or `--stdin` and prints the answer to stdout.
+///
+/// Progress, retries, thinking and errors go to stderr so stdout only ever holds the answer.
+pub async fn run(config: ChatConfig, cli: &Cli) -> ExitStatus {
+    let interrupted = async {
+        // Without a signal handler there is no Ctrl-C to wait for
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    };
+    ask(config, cli, io::stdin(), &mut io::stdout(), interrupted).await
+}
+
+/// [`run`] with the input, the output and the interruption supplied by the caller
+async fn ask(
+    config: ChatConfig,
+    cli: &Cli,
+    input: impl Read,
+    out: &mut impl Write,
+    interrupted: impl Future<Output = ()>,
+) -> ExitStatus {
+    let question = match read_question(cli, input) {
+        Ok(question) if question.trim().is_empty() => {
+            eprintln!("✗ Nothing to ask: the question is empty");
+            return ExitStatus::Usage;
+        }
+        Ok(question) => question,
+        Err(e) => {
+            eprintln!("✗ Could not read stdin: {}", e);
+            return ExitStatus::Usage;
+        }
+    };
+
+    let mut app = match App::headless(config) {
+        Ok(app) => app,
+        Err(e) => {
+            eprintln!("✗ {}", e);
+            return ExitStatus::Usage;
+        }
+    };
+    app.add_message(Role::User, question);
+    app.start_request();
+    let Some(mut receiver) = app.api_receiver.take() else {
+        return ExitStatus::RequestFailed;
+    };
+
+    let mut text = String::new();
+    tokio::pin!(interrupted);
+    let finish_reason = loop {
+        let message = tokio::select! {
+            message = receiver.recv() => message,
+            _ = &mut interrupted => {
+                if let Some(handle) = app.request_handle.take() {
+                    handle.abort();
+                }
+                end_line(out, cli.stream, &text);
+                eprintln!("⊘ Request cancelled");
+                return ExitStatus::Interrupted;
+            }
+        };
+
+        match message {
+            Some(ApiMessage::Delta(delta)) => {
+                if cli.stream {
+                    emit(out, &delta);
+                }
+                text.push_str(&delta);
+            }
+            // Only the first answer is printed when `n` asks for several
+            Some(
+                ApiMessage::Usage(_) | ApiMessage::Summary(_) | ApiMessage::OtherChoice { .. },
+            ) => {}
+            Some(ApiMessage::Done(finish_reason)) => break finish_reason,
+            Some(ApiMessage::Retrying {
+                attempt,
+                max_retries,
+                delay,
+                error,
+            }) => {
+                eprintln!(
+                    "⟳ {} - retry {}/{} in {}s",
+                    error.hint(),
+                    attempt,
+                    max_retries,
+                    delay.as_secs()
+                );
+            }
+            Some(ApiMessage::ModelLoading { estimated_time }) => {
+                eprintln!(
+                    "⏳ Model is loading on the endpoint - about {}s left",
+                    estimated_time.as_secs()
+                );
+            }
+            Some(ApiMessage::Error(error)) => {
+                end_line(out, cli.stream, &text);
+                eprintln!("✗ {}", error.hint());
+                return ExitStatus::RequestFailed;
+            }
+            None => {
+                end_line(out, cli.stream, &text);
+                eprintln!("✗ API connection lost");
+                return ExitStatus::RequestFailed;
+            }
+        }
+    };
+
+    if text.trim().is_empty() {
+        eprintln!("✗ No content received");
+        return ExitStatus::Empty;
+    }
+
+    if cli.stream {
+        end_line(out, true, &text);
+    } else if cli.raw {
+        emit(out, &text);
+        end_line(out, true, &text);
+    } else {
+        match parse_thinking_tokens(&text) {
+            MessageContent::WithThinking { thinking, output } => {
+                if !cli.no_thinking {
+                    eprintln!("🤔 Thinking:\n{}\n", thinking);
+                }
+                emit(out, &output);
+                end_line(out, true, &output);
+            }
+            MessageContent::Text(text) => {
+                emit(out, &text);
+                end_line(out, true, &text);
+            }
+        }
+    }
+
+    if finish_reason.as_deref() == Some("length") {
+        eprintln!("⚠ Response truncated: max_tokens reached");
+        return ExitStatus::Truncated;
+    }
+    ExitStatus::Success
+}
+
+/// The `--prompt` text, followed by everything on stdin when `--stdin` is set
+fn read_question(cli: &Cli, mut input: impl Read) -> io::Result<String> {
+    let mut question = cli.prompt.clone().unwrap_or_default();
+    if cli.stdin {
+        let mut piped = String::new();
+        input.read_to_string(&mut piped)?;
+        if !question.is_empty() && !piped.is_empty() {
+            question.push_str("\n\n");
+        }
+        question.push_str(&piped);
+    }
+    Ok(question)
+}
+
+/// Writes to the output, ignoring a closed pipe (e.g. `| head`) instead of panicking
+fn emit(out: &mut impl Write, text: &str) {
+    let _ = out.write_all(text.as_bytes());
+    let _ = out.flush();
+}
+
+/// Terminates printed output with a newline so the shell prompt starts on its own line
+fn end_line(out: &mut impl Write, printed: bool, text: &str) {
+    if printed && !text.is_empty() && !text.ends_with('\n') {
+        emit(out, "\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{stub_server, Reply};
+    use clap::Parser;
+    use std::time::Duration;
+
+    fn event(content: &str, finish_reason: Option<&str>) -> String {
+        let chunk = serde_json::json!({
+            "choices": [{"index": 0, "delta": {"content": content}, "finish_reason": finish_reason}]
+        });
+        format!("data: {}\n\n", chunk)
+    }
+
+    /// Asks a server that streams `chunks` back for every question, with `args` on the
+    /// command line and `input` on stdin
+    async fn ask_with(chunks: Vec<String>, args: &[&str], input: &str) -> (ExitStatus, String) {
+        ask_until(chunks, args, input, std::future::pending()).await
+    }
+
+    async fn ask_until(
+        chunks: Vec<String>,
+        args: &[&str],
+        input: &str,
+        interrupted: impl Future<Output = ()>,
+    ) -> (ExitStatus, String) {
+        let url = stub_server(move |_, _| {
+            let chunks: Vec<&str> = chunks.iter().map(String::as_str).collect();
+            Reply::stream(&chunks)
+        })
+        .await;
+        let config = ChatConfig {
+            base_url: format!("{}/v1", url),
+            autosave: false,
+            ..ChatConfig::default()
+        };
+        let cli = Cli::parse_from(std::iter::once("hugging-code").chain(args.iter().copied()));
+        let mut out = Vec::new();
+        let status = ask(config, &cli, input.as_bytes(), &mut out, interrupted).await;
+        (status, String::from_utf8(out).unwrap())
+    }
+
+    #[tokio::test]
+    async fn prints_the_answer_without_its_thinking() {
+        let chunks = vec![
+            event("<thinking>The user wants", None),
+            event(" a greeting</thinking>\n\nHello!", Some("stop")),
+        ];
+        let (status, out) = ask_with(chunks.clone(), &["-p", "Hi"], "").await;
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(out, "Hello!\n");
+
+        let (status, out) = ask_with(chunks, &["-p", "Hi", "--raw"], "").await;
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(
+            out,
+            "<thinking>The user wants a greeting</thinking>\n\nHello!\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn appends_stdin_to_the_prompt() {
+        let question = "Review this\n\nfn main() {}";
+        let url = stub_server(move |_, body: &str| {
+            let expected = serde_json::to_string(question).unwrap();
+            match body.contains(&expected) {
+                true => Reply::stream(&[
+                    "data: {\"choices\": [{\"index\": 0, \"delta\": {\"content\": \"LGTM\"}}]}\n\n",
+                ]),
+                false => Reply::json(400, r#"{"error": "unexpected question"}"#),
+            }
+        })
+        .await;
+        let config = ChatConfig {
+            base_url: format!("{}/v1", url),
+            autosave: false,
+            ..ChatConfig::default()
+        };
+        let cli = Cli::parse_from(["hugging-code", "-p", "Review this", "--stdin"]);
+        let mut out = Vec::new();
+        let status = ask(
+            config,
+            &cli,
+            "fn main() {}".as_bytes(),
+            &mut out,
+            std::future::pending(),
+        )
+        .await;
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(String::from_utf8(out).unwrap(), "LGTM\n");
+    }
+
+    #[tokio::test]
+    async fn refuses_an_empty_question_or_a_broken_config() {
+        let (status, out) = ask_with(Vec::new(), &["--stdin"], "  \n").await;
+        assert_eq!(status, ExitStatus::Usage);
+        assert_eq!(out, "");
+
+        let config = ChatConfig {
+            profile: Some("missing".to_string()),
+            ..ChatConfig::default()
+        };
+        let cli = Cli::parse_from(["hugging-code", "-p", "Hi"]);
+        let mut out = Vec::new();
+        let status = ask(config, &cli, io::empty(), &mut out, std::future::pending()).await;
+        assert_eq!(status, ExitStatus::Usage);
+        assert!(out.is_empty());
+    }
+
+    #[tokio::test]
+    async fn fails_when_the_request_does() {
+        let (status, out) = ask_with(
+            vec!["data: {\"error\": {\"message\": \"model not found\"}}\n\n".to_string()],
+            &["-p", "Hi"],
+            "",
+        )
+        .await;
+        assert_eq!(status, ExitStatus::RequestFailed);
+        assert_eq!(out, "");
+    }
+
+    #[tokio::test]
+    async fn reports_truncated_and_empty_answers() {
+        let (status, out) = ask_with(
+            vec![event("Once upon a", Some("length"))],
+            &["-p", "Tell a story"],
+            "",
+        )
+        .await;
+        assert_eq!(status, ExitStatus::Truncated);
+        assert_eq!(out, "Once upon a\n");
+
+        let (status, out) = ask_with(vec![event("", Some("stop"))], &["-p", "Hi"], "").await;
+        assert_eq!(status, ExitStatus::Empty);
+        assert_eq!(out, "");
+    }
+
+    #[tokio::test]
+    async fn an_interrupt_keeps_what_was_streamed() {
+        let mut chunks = vec![event("Partial", None)];
+        chunks.extend((0..50).map(|_| ": still generating\n\n".to_string()));
+        let interrupted = tokio::time::sleep(Duration::from_millis(200));
+
+        let (status, out) = ask_until(chunks, &["-p", "Hi", "--stream"], "", interrupted).await;
+        assert_eq!(status, ExitStatus::Interrupted);
+        assert_eq!(out, "Partial\n");
+    }
+}
@@ -0,0 +1,402 @@
+use crate::{
+    cli::Cli,
+    config::{expand_home, ChatConfig},
+    types::{Message, Role, Session, SESSION_FORMAT_VERSION},
+};
+use chrono::{DateTime, Local};
+use rand::Rng;
+use serde::Deserialize;
+use std::{
+    cmp::Reverse,
+    error::Error,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Messages shown in the preview pane of the session browser
+const PREVIEW_MESSAGES: usize = 6;
+
+/// Conversation files are either a [`Session`] or, from older versions, a bare message list
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedConversation {
+    Session(Box<Session>),
+    Messages(Vec<Message>),
+}
+
+/// Reads a conversation file in any of the formats `/save` ever wrote, migrated to the
+/// current one
+pub fn read_session(path: &Path) -> Result<Session, Box<dyn Error>> {
+    let json = fs::read_to_string(path)?;
+    let mut session = match serde_json::from_str(&json)? {
+        SavedConversation::Session(session) => *session,
+        SavedConversation::Messages(messages) => Session {
+            messages,
+            ..Session::default()
+        },
+    };
+    if session.version > SESSION_FORMAT_VERSION {
+        return Err(format!(
+            "{} uses session format {}, newer than the {} this version reads; update hugging-code",
+            path.display(),
+            session.version,
+            SESSION_FORMAT_VERSION
+        )
+        .into());
+    }
+    session.migrate();
+    Ok(session)
+}
+
+/// What `--resume` or `--continue` asked to open
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resume {
+    Session(String),
+    /// `--resume` without an ID: start with the session browser open
+    Browse,
+}
+
+/// Resolves `--resume`/`--continue` to a stored session before the TUI starts, so a
+/// typo is reported on the terminal instead of silently starting a new conversation
+pub fn resume_target(config: &ChatConfig, cli: &Cli) -> Result<Option<Resume>, String> {
+    if !cli.continue_session && cli.resume.is_none() {
+        return Ok(None);
+    }
+    let store = SessionStore::from_config(config)
+        .ok_or("No sessions directory: set sessions_dir or HF_SESSIONS_DIR")?;
+    match cli.resume.as_deref() {
+        Some("") => Ok(Some(Resume::Browse)),
+        Some(id) => store.find(id).map(|id| Some(Resume::Session(id))),
+        None => store
+            .latest()
+            .map(|id| Some(Resume::Session(id)))
+            .ok_or_else(|| format!("No saved sessions in {}", store.dir().display())),
+    }
+}
+
+/// Summary of a stored session for the browser, without holding on to the whole transcript
+#[derive(Clone)]
+pub struct SessionInfo {
+    pub id: String,
+    pub title: String,
+    pub updated: DateTime<Local>,
+    pub model: Option<String>,
+    pub message_count: usize,
+    /// Role and first line of the last few messages
+    pub preview: Vec<(Role, String)>,
+}
+
+/// Directory of autosaved conversations, one `<id>.json` file each
+#[derive(Debug, Clone)]
+pub struct SessionStore {
+    dir: PathBuf,
+}
+
+impl SessionStore {
+    /// `sessions_dir` from the config, else `$XDG_DATA_HOME/hugging-code/sessions`,
+    /// falling back to `~/.local/share`
+    pub fn from_config(config: &ChatConfig) -> Option<Self> {
+        let dir = match &config.sessions_dir {
+            Some(dir) => expand_home(dir),
+            None => std::env::var_os("XDG_DATA_HOME")
+                .map(PathBuf::from)
+                .or_else(|| {
+                    std::env::var_os("HOME")
+                        .map(|home| PathBuf::from(home).join(".local").join("share"))
+                })?
+                .join("hugging-code")
+                .join("sessions"),
+        };
+        Some(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// A fresh ID that sorts by creation time, e.g. `20250114-093012-4f1c`
+    pub fn new_id() -> String {
+        format!(
+            "{}-{:04x}",
+            Local::now().format("%Y%m%d-%H%M%S"),
+            rand::thread_rng().gen::<u16>()
+        )
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    /// Writes the session through a temporary file, so a crash never leaves half a file
+    pub fn save(&self, id: &str, session: &Session) -> Result<(), Box<dyn Error>> {
+        create_private_dir(&self.dir)?;
+        let json = serde_json::to_string_pretty(session)?;
+        let tmp = self.dir.join(format!(".{}.json.tmp", id));
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, self.path(id))?;
+        Ok(())
+    }
+
+    pub fn load(&self, id: &str) -> Result<Session, Box<dyn Error>> {
+        read_session(&self.path(id))
+    }
+
+    /// Every readable session, most recently updated first
+    pub fn list(&self) -> Vec<SessionInfo> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut sessions: Vec<SessionInfo> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let path = entry.path();
+                let id = path
+                    .file_name()?
+                    .to_str()?
+                    .strip_suffix(".json")?
+                    .to_string();
+                if id.starts_with('.') {
+                    return None;
+                }
+                let modified: DateTime<Local> = entry.metadata().ok()?.modified().ok()?.into();
+                let session = read_session(&path).ok()?;
+                Some(SessionInfo {
+                    title: title_of(&session),
+                    updated: session.updated.unwrap_or(modified),
+                    model: session.model.clone(),
+                    message_count: session.messages.len(),
+                    preview: session
+                        .messages
+                        .iter()
+                        .rev()
+                        .take(PREVIEW_MESSAGES)
+                        .rev()
+                        .map(|m| (m.role.clone(), first_line(m.content.output())))
+                        .collect(),
+                    id,
+                })
+            })
+            .collect();
+        sessions.sort_by_key(|info| Reverse(info.updated));
+        sessions
+    }
+
+    /// The session with this ID, or the only one whose ID starts with it
+    pub fn find(&self, id: &str) -> Result<String, String> {
+        if self.path(id).is_file() {
+            return Ok(id.to_string());
+        }
+        let matches: Vec<String> = self
+            .list()
+            .into_iter()
+            .map(|info| info.id)
+            .filter(|candidate| candidate.starts_with(id))
+            .collect();
+        match matches.as_slice() {
+            [only] => Ok(only.clone()),
+            [] => Err(format!("No session {} in {}", id, self.dir.display())),
+            _ => Err(format!(
+                "Session ID {} is ambiguous: {}",
+                id,
+                matches.join(", ")
+            )),
+        }
+    }
+
+    /// The most recently updated session, for `--continue`
+    pub fn latest(&self) -> Option<String> {
+        self.list().into_iter().next().map(|info| info.id)
+    }
+
+    pub fn rename(&self, id: &str, title: &str) -> Result<(), Box<dyn Error>> {
+        let mut session = self.load(id)?;
+        let title = title.trim();
+        session.title = (!title.is_empty()).then(|| title.to_string());
+        self.save(id, &session)
+    }
+
+    pub fn delete(&self, id: &str) -> io::Result<()> {
+        fs::remove_file(self.path(id))
+    }
+}
+
+/// The session's own title, or the start of its first prompt
+pub fn title_of(session: &Session) -> String {
+    if let Some(title) = &session.title {
+        return title.clone();
+    }
+    session
+        .messages
+        .iter()
+        .find(|m| m.role == Role::User)
+        .map(|m| {
+            let line = first_line(m.content.output());
+            match line.char_indices().nth(60) {
+                Some((end, _)) => format!("{}…", &line[..end]),
+                None => line,
+            }
+        })
+        .unwrap_or_else(|| "(empty)".to_string())
+}
+
+fn first_line(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Conversations can hold anything that was pasted into them, so the store is kept
+/// readable by its owner only
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::SamplingParams,
+        test_util::TempDir,
+        types::{MessageContent, Usage},
+    };
+
+    fn datetime(rfc3339: &str) -> DateTime<Local> {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .with_timezone(&Local)
+    }
+
+    #[test]
+    fn reads_bare_message_lists() {
+        let dir = TempDir::new("sessions-bare");
+        let path = dir.file(
+            "bare.json",
+            r#"[
+                {"role": "User", "content": {"Text": "hello"}, "datetime": "2025-01-01T10:00:00+01:00"},
+                {"role": "Assistant", "content": {"Text": "hi"}, "datetime": "2025-01-01T10:00:05+01:00",
+                 "model": "m1", "usage": {"prompt_tokens": 12, "completion_tokens": 3}}
+            ]"#,
+        );
+        let session = read_session(&path).unwrap();
+        assert_eq!(session.version, SESSION_FORMAT_VERSION);
+        assert_eq!(session.messages.len(), 2);
+        assert_eq!(session.created, Some(datetime("2025-01-01T10:00:00+01:00")));
+        assert_eq!(session.updated, Some(datetime("2025-01-01T10:00:05+01:00")));
+        assert_eq!(session.model.as_deref(), Some("m1"));
+        assert!(session.params.is_none());
+        assert_eq!(
+            session.usage["m1"],
+            Usage {
+                prompt_tokens: 12,
+                completion_tokens: 3
+            }
+        );
+    }
+
+    #[test]
+    fn reads_unversioned_sessions_with_params() {
+        let dir = TempDir::new("sessions-v0");
+        let path = dir.file(
+            "v0.json",
+            r#"{"params": {"temperature": 0.3},
+                "messages": [{"role": "User", "content": {"Text": "hello"}, "datetime": "2025-01-02T10:00:00+01:00"}]}"#,
+        );
+        let session = read_session(&path).unwrap();
+        assert_eq!(session.version, SESSION_FORMAT_VERSION);
+        let params = session.params.unwrap();
+        assert_eq!(params.temperature, Some(0.3));
+        assert_eq!(params.max_tokens, SamplingParams::default().max_tokens);
+        assert_eq!(session.created, Some(datetime("2025-01-02T10:00:00+01:00")));
+        assert!(session.model.is_none());
+    }
+
+    #[test]
+    fn keeps_what_version_1_recorded() {
+        let dir = TempDir::new("sessions-v1");
+        let path = dir.file(
+            "v1.json",
+            r#"{"version": 1, "title": "Plans", "model": "m2",
+                "created": "2025-01-02T09:00:00Z", "updated": "2025-01-03T09:00:00Z",
+                "messages": [
+                    {"role": "User", "content": {"Text": "hello"}, "datetime": "2025-01-02T09:00:00Z"},
+                    {"role": "Assistant", "content": {"Text": "hi"}, "datetime": "2025-01-02T09:00:04Z",
+                     "model": "m1", "usage": {"prompt_tokens": 10, "completion_tokens": 2},
+                     "latency_ms": 3900, "finish_reason": "length", "branch": 1,
+                     "siblings": [[{"role": "Assistant", "content": {"Text": "hey"},
+                                    "datetime": "2025-01-02T09:00:02Z", "model": "m1",
+                                    "usage": {"prompt_tokens": 10, "completion_tokens": 1}}]]}
+                ]}"#,
+        );
+        let session = read_session(&path).unwrap();
+        assert_eq!(session.title.as_deref(), Some("Plans"));
+        assert_eq!(session.model.as_deref(), Some("m2"));
+        assert_eq!(session.updated, Some(datetime("2025-01-03T09:00:00Z")));
+        let answer = &session.messages[1];
+        assert_eq!(answer.latency_ms, Some(3900));
+        assert_eq!(answer.finish_reason.as_deref(), Some("length"));
+        assert_eq!(answer.versions(), 2);
+        // Both versions of the answer were billed
+        assert_eq!(
+            session.usage["m1"],
+            Usage {
+                prompt_tokens: 20,
+                completion_tokens: 3
+            }
+        );
+    }
+
+    #[test]
+    fn refuses_sessions_from_a_newer_version() {
+        let dir = TempDir::new("sessions-future");
+        let path = dir.file("future.json", r#"{"version": 99, "messages": []}"#);
+        let e = read_session(&path).err().unwrap().to_string();
+        assert!(e.contains("session format 99"), "{}", e);
+        assert!(e.contains("update hugging-code"), "{}", e);
+    }
+
+    #[test]
+    fn the_store_reads_back_what_it_saved() {
+        let dir = TempDir::new("sessions-store");
+        let store = SessionStore {
+            dir: dir.path().join("sessions"),
+        };
+        let mut answer = Message::new(Role::Assistant, MessageContent::Text("hi".to_string()));
+        answer.latency_ms = Some(1200);
+        let mut usage = std::collections::BTreeMap::new();
+        usage.insert(
+            "m1".to_string(),
+            Usage {
+                prompt_tokens: 5,
+                completion_tokens: 1,
+            },
+        );
+        let session = Session {
+            version: SESSION_FORMAT_VERSION,
+            title: Some("Greeting".to_string()),
+            usage: usage.clone(),
+            messages: vec![answer],
+            ..Session::default()
+        };
+
+        let id = SessionStore::new_id();
+        store.save(&id, &session).unwrap();
+        let loaded = store.load(&id).unwrap();
+        assert_eq!(loaded.title.as_deref(), Some("Greeting"));
+        assert_eq!(loaded.usage, usage);
+        assert_eq!(loaded.messages[0].latency_ms, Some(1200));
+        assert_eq!(store.find(&id[..8]), Ok(id.clone()));
+        assert_eq!(store.latest(), Some(id));
+    }
+}
@@ -0,0 +1,205 @@
+use crate::{app::ApiMessage, backend::ChatBackend, config::RetryConfig, error::ApiError};
+use openai_api_rs::v1::chat_completion::ChatCompletionRequest;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Runs [`ChatBackend::stream`], retrying rate limits and server errors with backoff.
+///
+/// Each retry is announced to the UI with [`ApiMessage::Retrying`], and Hugging Face cold
+/// starts with [`ApiMessage::ModelLoading`] until the model is ready. Retryable errors are
+/// only ever returned before the stream starts, so no partial output is duplicated.
+pub async fn stream_with_retry(
+    backend: &dyn ChatBackend,
+    req: &ChatCompletionRequest,
+    tx: &mpsc::UnboundedSender<ApiMessage>,
+    retry: &RetryConfig,
+) -> Result<(), ApiError> {
+    let mut attempt = 0;
+    let started = Instant::now();
+    let model_load_timeout = Duration::from_secs(retry.model_load_timeout_secs);
+    loop {
+        match backend.stream(req, tx).await {
+            // Cold starts don't count against the retry budget; poll until the model is up
+            Err(ApiError::ModelLoading { estimated_time, .. })
+                if started.elapsed() < model_load_timeout =>
+            {
+                let estimated_time = estimated_time.min(model_load_timeout);
+                let _ = tx.send(ApiMessage::ModelLoading { estimated_time });
+                // Check back at least every 10s, but no sooner than a first retry would
+                let poll = estimated_time
+                    .min(Duration::from_secs(10))
+                    .max(Duration::from_millis(retry.base_delay_ms));
+                tokio::time::sleep(poll).await;
+            }
+            Err(error) if error.is_retryable() && attempt < retry.max_retries => {
+                attempt += 1;
+                let delay = retry.delay_for(attempt, error.retry_after());
+                let _ = tx.send(ApiMessage::Retrying {
+                    attempt,
+                    max_retries: retry.max_retries,
+                    delay,
+                    error,
+                });
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::openai::OpenAiBackend,
+        config::ChatConfig,
+        test_util::{deltas, stub_server, Reply},
+    };
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    const ANSWER: &str = "data: {\"choices\": [{\"index\": 0, \"delta\": {\"content\": \"Ready\"}, \"finish_reason\": \"stop\"}]}\n\n";
+    const LOADING: &str = r#"{"error": "Model is currently loading", "estimated_time": 0.01}"#;
+
+    fn retry(max_retries: u32) -> RetryConfig {
+        RetryConfig {
+            max_retries,
+            base_delay_ms: 5,
+            max_delay_ms: 20,
+            model_load_timeout_secs: 300,
+        }
+    }
+
+    /// A server whose answer to the n-th request (from 0) is `reply(n)`, and how many
+    /// requests it has had
+    async fn server(
+        reply: impl Fn(usize) -> Reply + Send + Sync + 'static,
+    ) -> (OpenAiBackend, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let url = stub_server(move |_, _| reply(counter.fetch_add(1, Ordering::SeqCst))).await;
+        let config = ChatConfig {
+            base_url: format!("{}/v1", url),
+            ..ChatConfig::default()
+        };
+        (
+            OpenAiBackend::new(&config, reqwest::Client::new()),
+            requests,
+        )
+    }
+
+    async fn run(
+        backend: &OpenAiBackend,
+        retry: &RetryConfig,
+    ) -> (Result<(), ApiError>, Vec<ApiMessage>) {
+        let req = ChatCompletionRequest::new("llama3.2".to_string(), Vec::new());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let result = stream_with_retry(backend, &req, &tx, retry).await;
+        drop(tx);
+        let mut messages = Vec::new();
+        while let Some(message) = rx.recv().await {
+            messages.push(message);
+        }
+        (result, messages)
+    }
+
+    fn retries(messages: &[ApiMessage]) -> Vec<(u32, Option<u16>)> {
+        messages
+            .iter()
+            .filter_map(|message| match message {
+                ApiMessage::Retrying { attempt, error, .. } => {
+                    Some((*attempt, error.status_code()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn waits_for_a_cold_start_without_using_up_retries() {
+        let (backend, requests) = server(|n| match n {
+            0 | 1 => Reply::json(503, LOADING),
+            _ => Reply::stream(&[ANSWER]),
+        })
+        .await;
+
+        let (result, messages) = run(&backend, &retry(0)).await;
+        result.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        let loading: Vec<_> = messages
+            .iter()
+            .filter_map(|message| match message {
+                ApiMessage::ModelLoading { estimated_time } => Some(*estimated_time),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(loading, [Duration::from_millis(10); 2]);
+        assert_eq!(deltas(&messages), "Ready");
+        assert!(matches!(messages.last(), Some(ApiMessage::Done(_))));
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_a_cold_start_at_the_model_load_timeout() {
+        let (backend, requests) = server(|_| {
+            Reply::json(
+                503,
+                r#"{"error": "Model is currently loading", "estimated_time": 600.0}"#,
+            )
+        })
+        .await;
+        let retry = RetryConfig {
+            model_load_timeout_secs: 1,
+            ..retry(0)
+        };
+
+        let (result, messages) = run(&backend, &retry).await;
+        assert!(matches!(result, Err(ApiError::ModelLoading { .. })));
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        // The estimate shown never goes past the timeout
+        assert!(matches!(
+            messages[..],
+            [ApiMessage::ModelLoading { estimated_time }] if estimated_time == Duration::from_secs(1)
+        ));
+    }
+
+    #[tokio::test]
+    async fn retries_rate_limits_and_server_errors() {
+        let (backend, requests) = server(|n| match n {
+            0 => Reply::json(429, r#"{"error": "slow down"}"#).header("retry-after", "0"),
+            1 => Reply::json(502, "<html>Bad Gateway</html>"),
+            _ => Reply::stream(&[ANSWER]),
+        })
+        .await;
+
+        let (result, messages) = run(&backend, &retry(3)).await;
+        result.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert_eq!(retries(&messages), [(1, Some(429)), (2, Some(502))]);
+        assert!(matches!(
+            messages[0],
+            ApiMessage::Retrying { delay, .. } if delay == Duration::ZERO
+        ));
+        assert_eq!(deltas(&messages), "Ready");
+    }
+
+    #[tokio::test]
+    async fn stops_after_max_retries() {
+        let (backend, requests) = server(|_| Reply::json(500, r#"{"error": "boom"}"#)).await;
+        let (result, messages) = run(&backend, &retry(2)).await;
+        assert_eq!(result.unwrap_err().status_code(), Some(500));
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert_eq!(retries(&messages), [(1, Some(500)), (2, Some(500))]);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_a_rejected_request() {
+        let (backend, requests) =
+            server(|_| Reply::json(404, r#"{"error": "model not found"}"#)).await;
+        let (result, messages) = run(&backend, &retry(3)).await;
+        assert_eq!(result.unwrap_err().status_code(), Some(404));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert!(messages.is_empty());
+    }
+}
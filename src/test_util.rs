@@ -0,0 +1,186 @@
+//! Fixtures shared by the unit tests
+
+use crate::{app::ApiMessage, backend::ChatBackend, error::ApiError};
+use openai_api_rs::v1::chat_completion::ChatCompletionRequest;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+    sync::mpsc,
+};
+
+/// A directory under the system temp dir that is removed again when dropped
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// `name` has to be unique across all tests, as they run in parallel in one process
+    pub fn new(name: &str) -> Self {
+        let dir =
+            std::env::temp_dir().join(format!("hugging-code-test-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Writes `contents` to the file `name` in this directory and returns its path
+    pub fn file(&self, name: &str, contents: &str) -> PathBuf {
+        let path = self.0.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// What [`stub_server`] answers a request with
+pub struct Reply {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    /// Written one after the other with a pause in between, so each arrives on its own
+    chunks: Vec<String>,
+    /// Sends the body until the connection closes instead of announcing its length
+    streamed: bool,
+}
+
+impl Reply {
+    pub fn json(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            headers: vec![("content-type", "application/json".to_string())],
+            chunks: vec![body.to_string()],
+            streamed: false,
+        }
+    }
+
+    /// A `200` event stream made of `chunks`, which need not end on a line boundary
+    pub fn stream(chunks: &[&str]) -> Self {
+        Self {
+            status: 200,
+            headers: vec![("content-type", "text/event-stream".to_string())],
+            chunks: chunks.iter().map(|chunk| chunk.to_string()).collect(),
+            streamed: true,
+        }
+    }
+
+    pub fn header(mut self, name: &'static str, value: &str) -> Self {
+        self.headers.push((name, value.to_string()));
+        self
+    }
+}
+
+impl From<(u16, &str)> for Reply {
+    fn from((status, body): (u16, &str)) -> Self {
+        Self::json(status, body)
+    }
+}
+
+/// Answers every request with `route(method_and_path, body)` until the test ends,
+/// returning the server's base URL
+pub async fn stub_server<R: Into<Reply>>(
+    route: impl Fn(&str, &str) -> R + Send + Sync + 'static,
+) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let route = Arc::new(route);
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let route = route.clone();
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                let mut buf = [0; 4096];
+                // Read the head, then as much body as Content-Length announces
+                let head_end = loop {
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    if n == 0 {
+                        return;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                    if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break end + 4;
+                    }
+                };
+                let head = String::from_utf8_lossy(&request[..head_end]).to_string();
+                let length = head
+                    .lines()
+                    .filter_map(|line| line.split_once(':'))
+                    .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                    .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                while request.len() < head_end + length {
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+
+                let request_line = head.lines().next().unwrap_or_default();
+                let target = request_line
+                    .rsplit_once(' ')
+                    .map_or("", |(target, _)| target);
+                let body = String::from_utf8_lossy(&request[head_end..]);
+                let reply: Reply = route(target, &body).into();
+
+                let mut head = format!("HTTP/1.1 {} Stub\r\nconnection: close\r\n", reply.status);
+                for (name, value) in &reply.headers {
+                    head.push_str(&format!("{}: {}\r\n", name, value));
+                }
+                if !reply.streamed {
+                    let length: usize = reply.chunks.iter().map(String::len).sum();
+                    head.push_str(&format!("content-length: {}\r\n", length));
+                }
+                head.push_str("\r\n");
+                if socket.write_all(head.as_bytes()).await.is_err() {
+                    return;
+                }
+                for chunk in &reply.chunks {
+                    if socket.write_all(chunk.as_bytes()).await.is_err() {
+                        return;
+                    }
+                    let _ = socket.flush().await;
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                }
+                let _ = socket.shutdown().await;
+            });
+        }
+    });
+    url
+}
+
+/// Streams `req` from `backend`, returning the result and everything sent to the UI
+pub async fn stream(
+    backend: &dyn ChatBackend,
+    req: &ChatCompletionRequest,
+) -> (Result<(), ApiError>, Vec<ApiMessage>) {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let result = backend.stream(req, &tx).await;
+    drop(tx);
+    let mut messages = Vec::new();
+    while let Some(message) = rx.recv().await {
+        messages.push(message);
+    }
+    (result, messages)
+}
+
+/// The text of every [`ApiMessage::Delta`] in `messages`
+pub fn deltas(messages: &[ApiMessage]) -> String {
+    messages
+        .iter()
+        .filter_map(|message| match message {
+            ApiMessage::Delta(delta) => Some(delta.as_str()),
+            _ => None,
+        })
+        .collect()
+}
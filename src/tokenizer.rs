@@ -0,0 +1,132 @@
+use crate::{
+    config::{expand_home, ChatConfig},
+    types::Message,
+};
+use std::sync::Arc;
+use tokenizers::Tokenizer;
+
+/// Tokens a chat template adds around every message (role header and separators)
+pub const MESSAGE_OVERHEAD: usize = 4;
+
+/// Letters and digits per token in the estimate. Real BPE vocabularies average 4-5
+/// characters per token on English prose, so 4 errs on the side of sending too little.
+const WORD_CHARS_PER_TOKEN: usize = 4;
+
+/// Counts tokens in message text for context budgeting
+#[derive(Clone)]
+pub enum TokenCounter {
+    /// The model's own tokenizer, loaded from a Hugging Face `tokenizer.json`
+    Exact(Arc<Tokenizer>),
+    /// Character-class estimate for when no tokenizer is configured
+    Estimate,
+}
+
+impl TokenCounter {
+    /// Loads `config.tokenizer` when one is set, falling back to the estimate otherwise
+    pub fn from_config(config: &ChatConfig) -> Result<Self, String> {
+        match &config.tokenizer {
+            Some(path) => Tokenizer::from_file(expand_home(path))
+                .map(|tokenizer| TokenCounter::Exact(Arc::new(tokenizer)))
+                .map_err(|e| format!("Cannot load tokenizer {}: {}", path.display(), e)),
+            None => Ok(TokenCounter::Estimate),
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        matches!(self, TokenCounter::Exact(_))
+    }
+
+    pub fn count(&self, text: &str) -> usize {
+        match self {
+            TokenCounter::Exact(tokenizer) => tokenizer
+                .encode(text, false)
+                .map(|encoding| encoding.len())
+                .unwrap_or_else(|_| estimate(text)),
+            TokenCounter::Estimate => estimate(text),
+        }
+    }
+
+    /// Tokens `message` takes up in a request, template overhead included
+    pub fn count_message(&self, message: &Message) -> usize {
+        self.count(message.content.output()) + MESSAGE_OVERHEAD
+    }
+}
+
+/// Estimates BPE tokens from character classes instead of raw length, so code and
+/// non-Latin text (which tokenize far denser than prose) aren't undercounted:
+/// runs of letters and digits cost one token per [`WORD_CHARS_PER_TOKEN`] characters,
+/// every punctuation mark and newline one token, indentation one per four spaces and
+/// other characters (CJK, emoji) one per two UTF-8 bytes.
+fn estimate(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut word: usize = 0;
+    let mut spaces = 0;
+    for c in text.chars() {
+        if c.is_alphanumeric() && c.len_utf8() <= 2 {
+            word += 1;
+            tokens += spaces / 4;
+            spaces = 0;
+            continue;
+        }
+        tokens += word.div_ceil(WORD_CHARS_PER_TOKEN);
+        word = 0;
+        if c == ' ' {
+            spaces += 1;
+            continue;
+        }
+        tokens += spaces / 4;
+        spaces = 0;
+        tokens += if c.is_ascii() {
+            1
+        } else {
+            c.len_utf8().div_ceil(2)
+        };
+    }
+    tokens + word.div_ceil(WORD_CHARS_PER_TOKEN) + spaces / 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_prose_by_word_length() {
+        assert_eq!(estimate(""), 0);
+        // "hello" and "world" are two tokens each, the space between them is free
+        assert_eq!(estimate("hello world"), 4);
+        assert_eq!(estimate("a b c"), 3);
+    }
+
+    #[test]
+    fn counts_every_punctuation_mark_and_newline() {
+        // fn, main, (, ), {, }
+        assert_eq!(estimate("fn main() {}"), 6);
+        assert_eq!(estimate("a\nb"), 3);
+    }
+
+    #[test]
+    fn counts_indentation_per_four_spaces() {
+        assert_eq!(estimate("        x"), 3);
+        assert_eq!(estimate("   x"), 1);
+    }
+
+    #[test]
+    fn counts_wide_characters_by_their_utf8_length() {
+        assert_eq!(estimate("你好"), 4);
+        assert_eq!(estimate("🙂"), 2);
+        // Accented Latin letters are still part of a word
+        assert_eq!(estimate("café"), 1);
+    }
+
+    #[test]
+    fn adds_the_template_overhead_per_message() {
+        let message = Message::new(
+            crate::types::Role::User,
+            crate::types::MessageContent::Text("hello world".to_string()),
+        );
+        assert_eq!(
+            TokenCounter::Estimate.count_message(&message),
+            4 + MESSAGE_OVERHEAD
+        );
+    }
+}
@@ -1,6 +1,7 @@
+use crate::config::SamplingParams;
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
-use std::time::Instant;
+use std::{collections::BTreeMap, ops::AddAssign, time::Instant};
 
 /// Local MessageRole wrapper to enable comparison
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
@@ -17,6 +18,16 @@ pub enum MessageContent {
     WithThinking { thinking: String, output: String },
 }
 
+impl MessageContent {
+    /// The text sent back to the model; thinking is never part of the context
+    pub fn output(&self) -> &str {
+        match self {
+            MessageContent::Text(text) => text,
+            MessageContent::WithThinking { output, .. } => output,
+        }
+    }
+}
+
 /// UI Application state
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Message {
@@ -25,4 +36,188 @@ pub struct Message {
     #[serde(skip, default = "Instant::now")]
     pub timestamp: Instant,
     pub datetime: DateTime<Local>,
+    /// Model that produced an assistant message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Tokens billed for the request that produced an assistant message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    /// Time from sending the request to the last token of an assistant message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// Why generation stopped, as reported by the endpoint (`stop`, `length`, ...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<String>,
+    /// Set on a compaction note: how many earlier messages it stands in for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summarizes: Option<usize>,
+    /// Always sent, however little room the context has left
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    /// Never sent; kept in the transcript only
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub excluded: bool,
+    /// The other versions of this message, each followed by the conversation that grew
+    /// from it. With `App::messages` as the active path, this makes the conversation a
+    /// tree: editing a prompt or regenerating an answer adds a sibling here.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub siblings: Vec<Vec<Message>>,
+    /// Position of this version among all versions, for "< 2/3 >"
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub branch: usize,
+}
+
+impl Message {
+    pub fn new(role: Role, content: MessageContent) -> Self {
+        Self {
+            role,
+            content,
+            timestamp: Instant::now(),
+            datetime: Local::now(),
+            model: None,
+            usage: None,
+            latency_ms: None,
+            finish_reason: None,
+            summarizes: None,
+            pinned: false,
+            excluded: false,
+            siblings: Vec::new(),
+            branch: 0,
+        }
+    }
+
+    /// How many versions of this message there are, itself included
+    pub fn versions(&self) -> usize {
+        self.siblings.len() + 1
+    }
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// Token counts reported by the endpoint for one completion
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+}
+
+impl Usage {
+    pub fn total(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+    }
+}
+
+/// Version of the session file format written by this build. Files without a version
+/// (a `{params, messages}` object or a bare message list) are read as version 0; version 2
+/// added the session's usage totals.
+pub const SESSION_FORMAT_VERSION: u32 = 2;
+
+/// A saved conversation together with the settings it was held with
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Session {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Model in use when the session was last saved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Missing from bare message lists, which leave the current parameters alone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<SamplingParams>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<DateTime<Local>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated: Option<DateTime<Local>>,
+    /// Tokens billed per model over the session's lifetime, including answers on other
+    /// branches and compaction summaries
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub usage: BTreeMap<String, Usage>,
+    pub messages: Vec<Message>,
+}
+
+impl Session {
+    /// Fills in what older formats did not record from the messages themselves
+    pub fn migrate(&mut self) {
+        if self.version >= SESSION_FORMAT_VERSION {
+            return;
+        }
+        self.created = self.created.or(self.messages.first().map(|m| m.datetime));
+        self.updated = self.updated.or(self.messages.last().map(|m| m.datetime));
+        if self.model.is_none() {
+            self.model = self.messages.iter().rev().find_map(|m| m.model.clone());
+        }
+        // Summaries did not record theirs, but every answer on every branch did
+        if self.usage.is_empty() {
+            add_usage(&mut self.usage, &self.messages);
+        }
+        self.version = SESSION_FORMAT_VERSION;
+    }
+}
+
+fn add_usage(totals: &mut BTreeMap<String, Usage>, messages: &[Message]) {
+    for msg in messages {
+        if let Some(usage) = msg.usage {
+            let model = msg.model.clone().unwrap_or_else(|| "unknown".to_string());
+            *totals.entry(model).or_default() += usage;
+        }
+        for sibling in &msg.siblings {
+            add_usage(totals, sibling);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(model: &str, prompt_tokens: u64, completion_tokens: u64) -> Message {
+        let mut msg = Message::new(Role::Assistant, MessageContent::Text("answer".to_string()));
+        msg.model = Some(model.to_string());
+        msg.usage = Some(Usage {
+            prompt_tokens,
+            completion_tokens,
+        });
+        msg
+    }
+
+    #[test]
+    fn migration_totals_usage_over_every_branch() {
+        let question = || Message::new(Role::User, MessageContent::Text("question".to_string()));
+        let mut shown = answer("a", 10, 1);
+        shown.siblings = vec![vec![answer("a", 10, 2)], vec![answer("b", 20, 3)]];
+        let mut session = Session {
+            version: 1,
+            messages: vec![question(), shown, question(), answer("b", 30, 4)],
+            ..Session::default()
+        };
+        session.migrate();
+
+        assert_eq!(session.version, SESSION_FORMAT_VERSION);
+        assert_eq!(
+            session.usage["a"],
+            Usage {
+                prompt_tokens: 20,
+                completion_tokens: 3
+            }
+        );
+        assert_eq!(
+            session.usage["b"],
+            Usage {
+                prompt_tokens: 50,
+                completion_tokens: 7
+            }
+        );
+    }
 }
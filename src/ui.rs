@@ -10,7 +10,10 @@ use crate::{
     app::{App, InputMode, ScrollState, StatusType},
     markdown::MarkdownRenderer,
     types::{Message, MessageContent, Role},
-    widgets::{help::HelpWidget, loading::LoadingWidget},
+    widgets::{
+        context::ContextWidget, error::ErrorWidget, help::HelpWidget, loading::LoadingWidget,
+        model_picker::ModelPickerWidget, params::ParamsWidget, sessions::SessionsWidget,
+    },
 };
 
 pub fn draw(f: &mut Frame, app: &App) {
@@ -33,13 +36,52 @@ pub fn draw(f: &mut Frame, app: &App) {
     draw_status(f, app, chunks[3]);
 
     if app.show_help {
-        HelpWidget::draw(f, f.area());
+        app.help_max_scroll
+            .set(HelpWidget::draw(f, f.area(), app.help_scroll));
     }
 
-    if app.is_loading {
-        let loading_widget = LoadingWidget::new(app.loading_frame);
+    if app.is_loading && !app.is_streaming {
+        let mut loading_widget = LoadingWidget::new(app.loading_frame);
+        if let Some(model_loading) = &app.model_loading {
+            let seconds_left = model_loading
+                .ready_at
+                .saturating_duration_since(std::time::Instant::now())
+                .as_secs();
+            loading_widget =
+                loading_widget.with_model_loading(model_loading.progress(), seconds_left);
+        }
         loading_widget.draw(f, chunks[1]);
     }
+
+    if app.show_context {
+        let (messages, plan, draft) = app.draft_context_plan();
+        ContextWidget::draw(
+            f,
+            chunks[1],
+            &messages,
+            &plan,
+            draft,
+            app.token_counter.is_exact(),
+        );
+    }
+
+    if app.show_params {
+        ParamsWidget::draw(f, f.area(), &app.config.params);
+    }
+
+    if let Some(picker) = &app.model_picker {
+        ModelPickerWidget::draw(f, f.area(), picker, &app.config.model);
+    }
+
+    if let Some(browser) = &app.session_browser {
+        SessionsWidget::draw(f, f.area(), browser, app.session_id.as_deref());
+    }
+
+    if app.show_error_details {
+        if let Some(error) = &app.last_error {
+            ErrorWidget::draw(f, f.area(), error);
+        }
+    }
 }
 
 fn draw_header(f: &mut Frame, app: &App, area: Rect) {
@@ -61,6 +103,13 @@ fn draw_header(f: &mut Frame, app: &App, area: Rect) {
         ),
         Span::raw(" │ "),
         Span::styled(&app.config.model, Style::default().fg(Color::Yellow)),
+        Span::styled(
+            match &app.config.profile {
+                Some(profile) => format!(" ({} · {})", app.backend.name(), profile),
+                None => format!(" ({})", app.backend.name()),
+            },
+            Style::default().fg(Color::DarkGray),
+        ),
     ])]))
     .block(
         Block::default()
@@ -89,6 +138,11 @@ fn draw_messages(f: &mut Frame, app: &App, area: Rect) {
 
     for msg in messages_to_render.iter() {
         let (base_style, prefix, role_color) = match msg.role {
+            Role::System if msg.summarizes.is_some() => (
+                Style::default().fg(Color::Yellow).bg(Color::Black),
+                "📝 Summary",
+                Color::Yellow,
+            ),
             Role::User => (
                 Style::default().fg(Color::Green).bg(Color::Black),
                 "👤 You",
@@ -117,6 +171,50 @@ fn draw_messages(f: &mut Frame, app: &App, area: Rect) {
                 format!(" [{}]", time_str),
                 Style::default().fg(Color::DarkGray),
             ),
+            Span::styled(
+                msg.model
+                    .as_ref()
+                    .map(|model| format!(" {}", model))
+                    .unwrap_or_default(),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(
+                msg.latency_ms
+                    .map(|ms| format!(" {:.1}s", ms as f64 / 1000.0))
+                    .unwrap_or_default(),
+                Style::default().fg(Color::DarkGray),
+            ),
+            if msg.finish_reason.as_deref() == Some("length") {
+                Span::styled(" (truncated)", Style::default().fg(Color::Yellow))
+            } else {
+                Span::raw("")
+            },
+            Span::styled(
+                msg.summarizes
+                    .map(|n| format!(" of {} earlier messages", n))
+                    .unwrap_or_default(),
+                Style::default().fg(Color::DarkGray),
+            ),
+            if msg.siblings.is_empty() {
+                Span::raw("")
+            } else {
+                Span::styled(
+                    format!(" < {}/{} >", msg.branch + 1, msg.versions()),
+                    Style::default().fg(Color::Cyan),
+                )
+            },
+            if msg.pinned {
+                Span::styled(" 📌 pinned", Style::default().fg(Color::Yellow))
+            } else if msg.excluded {
+                Span::styled(
+                    " ⊘ excluded",
+                    Style::default()
+                        .fg(Color::DarkGray)
+                        .add_modifier(Modifier::ITALIC),
+                )
+            } else {
+                Span::raw("")
+            },
             Span::styled(
                 ":",
                 Style::default().fg(role_color).add_modifier(Modifier::BOLD),
@@ -182,7 +280,7 @@ fn draw_messages(f: &mut Frame, app: &App, area: Rect) {
                 let rendered = renderer.render(output, base_style);
                 for line in rendered.lines {
                     let mut indented_line = vec![Span::raw("  ")];
-                    indented_line.extend(line.spans.into_iter());
+                    indented_line.extend(line.spans);
                     all_lines.push(Line::from(indented_line));
                 }
             }
@@ -244,9 +342,14 @@ fn draw_input(f: &mut Frame, app: &App, area: Rect) {
     };
 
     let char_count = app.input.len();
-    let input_title = match app.input_mode {
-        InputMode::Normal => " Input (Press 'i' to edit) ",
-        InputMode::Editing => &format!(
+    let input_title = match (&app.input_mode, app.editing) {
+        (InputMode::Normal, _) => " Input (Press 'i' to edit) ",
+        (InputMode::Editing, Some(at)) => &format!(
+            " Edit message {} [Esc=cancel | Enter=SEND AS NEW BRANCH | {}ch] ",
+            at + 1,
+            char_count
+        ),
+        (InputMode::Editing, None) => &format!(
             " Input [Esc=cancel | Enter=SEND | Shift+Enter=newline | {}ch] ",
             char_count
         ),
@@ -270,6 +373,51 @@ fn draw_input(f: &mut Frame, app: &App, area: Rect) {
 }
 
 fn draw_status(f: &mut Frame, app: &App, area: Rect) {
+    let (usage, cost) = app.session_usage();
+    let area = if usage.total() > 0 {
+        let mut meter = format!(
+            " Σ {} in / {} out",
+            format_tokens(usage.prompt_tokens),
+            format_tokens(usage.completion_tokens)
+        );
+        if let Some(cost) = cost {
+            meter.push_str(&format!(" · ${:.4}", cost));
+        }
+        meter.push(' ');
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(meter.chars().count() as u16),
+            ])
+            .split(area);
+        f.render_widget(
+            Paragraph::new(meter).style(Style::default().fg(Color::DarkGray).bg(Color::Black)),
+            chunks[1],
+        );
+        chunks[0]
+    } else {
+        area
+    };
+
+    if let Some(retry) = &app.retry_status {
+        let remaining = retry
+            .resume_at
+            .saturating_duration_since(std::time::Instant::now())
+            .as_secs_f32()
+            .ceil();
+        let countdown = format!(
+            "⟳ {} - retrying in {}s (attempt {}/{}) - Esc to cancel",
+            retry.reason, remaining, retry.attempt, retry.max_retries
+        );
+        f.render_widget(Clear, area);
+        let status =
+            Paragraph::new(countdown).style(Style::default().fg(Color::Yellow).bg(Color::Black));
+        f.render_widget(status, area);
+        return;
+    }
+
     if let Some((message, status_type)) = &app.status_message {
         let status_style = match status_type {
             StatusType::Info => Style::default().fg(Color::Cyan).bg(Color::Black),
@@ -284,6 +432,15 @@ fn draw_status(f: &mut Frame, app: &App, area: Rect) {
     }
 }
 
+/// Compact token count for the status bar, e.g. `950` or `12.3k`
+fn format_tokens(tokens: u64) -> String {
+    if tokens < 1000 {
+        tokens.to_string()
+    } else {
+        format!("{:.1}k", tokens as f64 / 1000.0)
+    }
+}
+
 fn render_plain_text(text: &str, base_style: Style) -> Text<'static> {
     let mut lines = Vec::new();
     for line in text.split('\n') {
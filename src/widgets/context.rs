@@ -0,0 +1,165 @@
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::{
+    context::ContextPlan,
+    types::{Message, Role},
+};
+use std::borrow::Cow;
+
+pub struct ContextWidget;
+
+impl ContextWidget {
+    /// Lists every message with its token count and whether the next request sends it.
+    ///
+    /// `draft` is set when the last message is the prompt still being typed.
+    pub fn draw(
+        f: &mut Frame,
+        area: Rect,
+        messages: &[Cow<'_, Message>],
+        plan: &ContextPlan,
+        draft: bool,
+        exact: bool,
+    ) {
+        let popup_area = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Percentage(10),
+                Constraint::Percentage(80),
+                Constraint::Percentage(10),
+            ])
+            .split(area)[1];
+
+        let popup_area = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(10),
+                Constraint::Percentage(80),
+                Constraint::Percentage(10),
+            ])
+            .split(popup_area)[1];
+
+        let approx = if exact { "" } else { "~" };
+        let total_style = if plan.over_budget() {
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD)
+        };
+        let mut lines = vec![
+            Line::from(vec![
+                Span::raw(format!(
+                    "📤 Next request: {} of {} messages, ",
+                    plan.included.len(),
+                    messages.len()
+                )),
+                Span::styled(
+                    format!("{}{} / {} tokens", approx, plan.total, plan.budget),
+                    total_style,
+                ),
+            ]),
+            Line::from(Span::styled(
+                format!(
+                    "   {}{}",
+                    if exact {
+                        "counted with the model's tokenizer"
+                    } else {
+                        "estimated; set tokenizer for exact counts"
+                    },
+                    if draft {
+                        " · includes the prompt being typed"
+                    } else {
+                        ""
+                    }
+                ),
+                Style::default().fg(Color::DarkGray),
+            )),
+            Line::from(""),
+        ];
+
+        let sent_style = Style::default().fg(Color::Green);
+        let kept_style = Style::default().fg(Color::Yellow);
+        let left_out_style = Style::default().fg(Color::DarkGray);
+        let rows: Vec<Line> = messages
+            .iter()
+            .enumerate()
+            .map(|(i, msg)| {
+                let (status, style) = if draft && i + 1 == messages.len() {
+                    ("draft", Style::default().fg(Color::Cyan))
+                } else if msg.excluded {
+                    ("excluded", left_out_style.add_modifier(Modifier::ITALIC))
+                } else if plan.summarized.contains(&i) {
+                    ("summarised", left_out_style)
+                } else if plan.dropped.contains(&i) {
+                    ("dropped", left_out_style)
+                } else if msg.pinned {
+                    ("pinned", kept_style)
+                } else if msg.summarizes.is_some() {
+                    ("summary", kept_style)
+                } else {
+                    ("sent", sent_style)
+                };
+                let role = match msg.role {
+                    Role::System if msg.summarizes.is_some() => "Summary",
+                    Role::User => "You",
+                    Role::Assistant => "AI",
+                    Role::System => "System",
+                };
+                let preview: String = msg
+                    .content
+                    .output()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                Line::from(vec![
+                    Span::styled(
+                        format!(
+                            "{:>4}  {:<8}{:>7}  {:<11}",
+                            i + 1,
+                            role,
+                            plan.tokens[i],
+                            status
+                        ),
+                        style,
+                    ),
+                    Span::styled(preview, Style::default().fg(Color::Gray)),
+                ])
+            })
+            .collect();
+
+        // Newest messages matter most; once the list is taller than the popup, the
+        // oldest rows give way
+        let room = (popup_area.height.saturating_sub(2) as usize).saturating_sub(lines.len());
+        let hidden = if rows.len() > room {
+            rows.len() + 1 - room.max(1)
+        } else {
+            0
+        };
+        if hidden > 0 {
+            lines.push(Line::from(Span::styled(
+                format!("   … {} earlier messages", hidden),
+                left_out_style,
+            )));
+        }
+        lines.extend(rows.into_iter().skip(hidden));
+
+        let popup = Paragraph::new(Text::from(lines))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Cyan))
+                    .title(" Context (Esc to close) ")
+                    .title_alignment(Alignment::Center),
+            )
+            .style(Style::default().bg(Color::Black));
+
+        f.render_widget(Clear, popup_area);
+        f.render_widget(popup, popup_area);
+    }
+}
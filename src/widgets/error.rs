@@ -0,0 +1,106 @@
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::error::ApiError;
+
+pub struct ErrorWidget;
+
+impl ErrorWidget {
+    pub fn draw(f: &mut Frame, area: Rect, error: &ApiError) {
+        let popup_area = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Percentage(15),
+                Constraint::Percentage(70),
+                Constraint::Percentage(15),
+            ])
+            .split(area)[1];
+
+        let popup_area = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(10),
+                Constraint::Percentage(80),
+                Constraint::Percentage(10),
+            ])
+            .split(popup_area)[1];
+
+        let label_style = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+        let kind = match error {
+            ApiError::Status { .. } => "HTTP status",
+            ApiError::Provider { .. } => "Provider error",
+            ApiError::ModelLoading { .. } => "Model loading",
+            ApiError::Transport(_) => "Transport",
+            ApiError::Timeout => "Timeout",
+            ApiError::Decode { .. } => "Decode",
+        };
+
+        let mut lines = vec![Line::from(vec![
+            Span::styled("Kind:    ", label_style),
+            Span::raw(kind),
+        ])];
+        if let Some(code) = error.status_code() {
+            lines.push(Line::from(vec![
+                Span::styled("Status:  ", label_style),
+                Span::raw(code.to_string()),
+            ]));
+        }
+        if let ApiError::Provider {
+            error_type: Some(error_type),
+            ..
+        } = error
+        {
+            lines.push(Line::from(vec![
+                Span::styled("Type:    ", label_style),
+                Span::raw(error_type.clone()),
+            ]));
+        }
+        lines.push(Line::from(vec![
+            Span::styled("Details: ", label_style),
+            Span::raw(error.to_string()),
+        ]));
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled("Hint:", label_style)));
+        lines.push(Line::from(error.hint()));
+
+        if let Some(body) = error.raw_body() {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled("Raw response body:", label_style)));
+            let pretty = serde_json::from_str::<serde_json::Value>(body)
+                .ok()
+                .and_then(|json| serde_json::to_string_pretty(&json).ok())
+                .unwrap_or_else(|| body.to_string());
+            if pretty.trim().is_empty() {
+                lines.push(Line::from(Span::styled(
+                    "(empty)",
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+            for body_line in pretty.lines() {
+                lines.push(Line::from(Span::styled(
+                    body_line.to_string(),
+                    Style::default().fg(Color::Gray),
+                )));
+            }
+        }
+
+        let popup = Paragraph::new(Text::from(lines))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Red))
+                    .title(" API Error [e/Esc=close] ")
+                    .title_alignment(Alignment::Center),
+            )
+            .style(Style::default().bg(Color::Black))
+            .wrap(Wrap { trim: false });
+
+        f.render_widget(Clear, popup_area);
+        f.render_widget(popup, popup_area);
+    }
+}
@@ -1,79 +1,109 @@
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Rect},
     style::{Color, Style},
     text::{Line, Text},
-    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, Paragraph},
     Frame,
 };
 
 pub struct HelpWidget;
 
 impl HelpWidget {
-    pub fn draw(f: &mut Frame, area: Rect) {
-        let help_area = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Percentage(15),
-                Constraint::Length(20),
-                Constraint::Percentage(15),
-            ])
-            .split(area)[1];
+    /// Draws the help popup scrolled down by `scroll` lines, sized to its text when the
+    /// frame allows; returns the largest scroll that still shows a full popup
+    pub fn draw(f: &mut Frame, area: Rect, scroll: u16) -> u16 {
+        let lines = help_lines();
+        let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 4;
+        let height = lines.len() as u16 + 2;
+        let width = width.min(area.width);
+        let height = height.min(area.height);
+        let help_area = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
 
-        let help_area = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Percentage(20),
-                Constraint::Length(60),
-                Constraint::Percentage(20),
-            ])
-            .split(help_area)[1];
+        let max_scroll = (lines.len() as u16).saturating_sub(height.saturating_sub(2));
+        let scroll = scroll.min(max_scroll);
+        let title = if max_scroll > 0 {
+            format!(" Help {}/{} · ↑/↓ to scroll ", scroll, max_scroll)
+        } else {
+            " Help ".to_string()
+        };
 
-        let help_text = Text::from(vec![
-            Line::from("📖 Help & Commands"),
-            Line::from(""),
-            Line::from("🎮 Navigation:"),
-            Line::from("  i              - Enter input mode"),
-            Line::from("  Esc            - Exit input mode"),
-            Line::from("  Enter          - Send message"),
-            Line::from("  Shift+Enter    - New line in message (for multiline)"),
-            Line::from("  ↑/↓            - Scroll messages up/down"),
-            Line::from("  Home/End       - Jump to top/bottom"),
-            Line::from("  PageUp/PageDn  - Scroll page up/down"),
-            Line::from("  h              - Toggle this help"),
-            Line::from("  t              - Toggle thinking tokens visibility"),
-            Line::from("  q              - Quit application"),
-            Line::from(""),
-            Line::from("💬 Commands (type in input):"),
-            Line::from("  /help, /h      - Toggle help"),
-            Line::from("  /clear, /c     - Clear conversation"),
-            Line::from("  /stats, /s     - Show statistics & token estimate"),
-            Line::from("  /save [file]   - Save conversation (default: conversation.json)"),
-            Line::from("  /load [file]   - Load conversation (default: conversation.json)"),
-            Line::from(""),
-            Line::from("⌨️  Shortcuts:"),
-            Line::from("  Ctrl+S         - Quick save (while editing)"),
-            Line::from(""),
-            Line::from("✨ Features:"),
-            Line::from("  • Markdown rendering with timestamps"),
-            Line::from("  • Thinking tokens detection and display"),
-            Line::from("  • Smart context window management (last 20 msgs)"),
-            Line::from("  • Multiline input with Shift+Enter, send with Enter"),
-            Line::from("  • Conversation save/load as JSON"),
-            Line::from("  • Character counter & scroll position"),
-        ]);
-
-        let help_popup = Paragraph::new(help_text)
+        let help_popup = Paragraph::new(Text::from(lines))
             .block(
                 Block::default()
                     .borders(Borders::ALL)
                     .border_style(Style::default().fg(Color::Cyan))
-                    .title(" Help ")
+                    .title(title)
                     .title_alignment(Alignment::Center),
             )
             .style(Style::default().bg(Color::Black))
-            .wrap(Wrap { trim: true });
+            .scroll((scroll, 0));
 
         f.render_widget(Clear, help_area);
         f.render_widget(help_popup, help_area);
+        max_scroll
     }
 }
+
+fn help_lines() -> Vec<Line<'static>> {
+    vec![
+        Line::from("📖 Help & Commands"),
+        Line::from(""),
+        Line::from("🎮 Navigation:"),
+        Line::from("  i              - Enter input mode"),
+        Line::from("  Esc            - Exit input mode / cancel a pending request"),
+        Line::from("  Ctrl+C         - Cancel a pending request"),
+        Line::from("  Enter          - Send message"),
+        Line::from("  Shift+Enter    - New line in message (for multiline)"),
+        Line::from("  ↑/↓            - Scroll messages up/down"),
+        Line::from("  Home/End       - Jump to top/bottom"),
+        Line::from("  PageUp/PageDn  - Scroll page up/down"),
+        Line::from("  h              - Toggle this help (↑/↓ scroll it, Esc closes)"),
+        Line::from("  t              - Toggle thinking tokens visibility"),
+        Line::from("  p              - Pin message at top of view (newest at bottom)"),
+        Line::from("  x              - Exclude that message from the context"),
+        Line::from("  c              - Toggle the context inspector"),
+        Line::from("  E              - Edit the prompt at top of view as a new branch"),
+        Line::from("  ←/→            - Switch between versions of a message (< 2/3 >)"),
+        Line::from("  r              - Ask for another answer to the last prompt"),
+        Line::from("  e              - Show details of the last API error"),
+        Line::from("  q              - Quit application"),
+        Line::from(""),
+        Line::from("💬 Commands (type in input):"),
+        Line::from("  /help, /h      - Toggle help"),
+        Line::from("  /clear, /c     - Clear conversation"),
+        Line::from("  /stats, /s     - Show statistics & token usage"),
+        Line::from("  /error, /e     - Show details of the last API error"),
+        Line::from("  /model, /m     - Pick a model from the endpoint"),
+        Line::from("  /model <name>  - Switch to a model by name"),
+        Line::from("  /profile       - List configured profiles"),
+        Line::from("  /profile <p>   - Switch to profile <p>, keeping the chat"),
+        Line::from("  /params, /p    - Show sampling parameters"),
+        Line::from("  /context, /ctx - Show what the next request sends"),
+        Line::from("  /set <k> <v>   - Set a parameter (none = server default)"),
+        Line::from("  /compact       - Summarise older messages into a note"),
+        Line::from("  /compact on    - Auto-summarise when context is full (off: drop)"),
+        Line::from("  /retry, /r [n] - Ask for n more answers (e.g. /r 3 temperature=1.2)"),
+        Line::from("  /keep          - Keep only the version shown, drop the others"),
+        Line::from("  /sessions      - Browse, open, rename and delete saved sessions"),
+        Line::from("  /save [file]   - Save conversation (default: conversation.json)"),
+        Line::from("  /load [file]   - Load conversation (default: conversation.json)"),
+        Line::from("  /export md [f] - Export as Markdown (html: standalone page)"),
+        Line::from("  /import <file> - Import a chat-ui, ChatGPT or OpenAI JSON export"),
+        Line::from(""),
+        Line::from("⌨️  Shortcuts:"),
+        Line::from("  Ctrl+S         - Save the session now (while editing)"),
+        Line::from(""),
+        Line::from("✨ Features:"),
+        Line::from("  • Streaming responses rendered as they arrive"),
+        Line::from("  • Markdown rendering with timestamps"),
+        Line::from("  • Thinking tokens detection and display"),
+        Line::from("  • Multiline input with Shift+Enter, send with Enter"),
+        Line::from("  • Conversation save/load as JSON"),
+        Line::from("  • Character counter & scroll position"),
+    ]
+}
@@ -7,11 +7,21 @@ use ratatui::{
 
 pub struct LoadingWidget {
     pub frame: usize,
+    /// Cold-start progress (`0.0..=1.0`) and estimated seconds left
+    pub model_loading: Option<(f64, u64)>,
 }
 
 impl LoadingWidget {
     pub fn new(frame: usize) -> Self {
-        Self { frame }
+        Self {
+            frame,
+            model_loading: None,
+        }
+    }
+
+    pub fn with_model_loading(mut self, progress: f64, seconds_left: u64) -> Self {
+        self.model_loading = Some((progress, seconds_left));
+        self
     }
 
     pub fn draw(&self, f: &mut Frame, area: Rect) {
@@ -19,7 +29,23 @@ impl LoadingWidget {
 
         let loader_frames = ["⣾", "⣽", "⣻", "⢿", "⡿", "⣟", "⣯", "⣷"];
         let current_frame = loader_frames[self.frame % loader_frames.len()];
-        let loading_text = format!("{} Loading response...", current_frame);
+        let (loading_text, title) = match self.model_loading {
+            Some((progress, seconds_left)) => {
+                let filled = (progress * 24.0).round() as usize;
+                let bar = format!("{}{}", "█".repeat(filled), "░".repeat(24 - filled.min(24)));
+                (
+                    format!(
+                        "{} Model is loading...\n{} ~{}s\nEsc to cancel",
+                        current_frame, bar, seconds_left
+                    ),
+                    " Cold start ",
+                )
+            }
+            None => (
+                format!("{} Loading response...\n\nEsc to cancel", current_frame),
+                " AI is thinking... ",
+            ),
+        };
 
         let loading = Paragraph::new(loading_text)
             .style(
@@ -32,7 +58,7 @@ impl LoadingWidget {
                 Block::default()
                     .borders(Borders::ALL)
                     .border_style(Style::default().fg(Color::Yellow))
-                    .title(" AI is thinking... ")
+                    .title(title)
                     .title_style(Style::default().fg(Color::Yellow).bg(Color::Black))
                     .style(Style::default().bg(Color::Black)),
             );
@@ -1,2 +1,7 @@
+pub mod context;
+pub mod error;
 pub mod help;
 pub mod loading;
+pub mod model_picker;
+pub mod params;
+pub mod sessions;
@@ -0,0 +1,103 @@
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::app::ModelPicker;
+
+pub struct ModelPickerWidget;
+
+impl ModelPickerWidget {
+    pub fn draw(f: &mut Frame, area: Rect, picker: &ModelPicker, current_model: &str) {
+        let popup_area = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Percentage(20),
+                Constraint::Percentage(60),
+                Constraint::Percentage(20),
+            ])
+            .split(area)[1];
+
+        let popup_area = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(20),
+                Constraint::Percentage(60),
+                Constraint::Percentage(20),
+            ])
+            .split(popup_area)[1];
+
+        let mut lines = vec![
+            Line::from(vec![
+                Span::styled("Filter: ", Style::default().fg(Color::Cyan)),
+                Span::styled(picker.filter.clone(), Style::default().fg(Color::White)),
+                Span::styled("▏", Style::default().fg(Color::Cyan)),
+            ]),
+            Line::from(""),
+        ];
+
+        let filtered = picker.filtered();
+        let list_height = popup_area.height.saturating_sub(5) as usize;
+        match &picker.models {
+            None => lines.push(Line::from(Span::styled(
+                "⟳ Fetching models from the endpoint...",
+                Style::default().fg(Color::Yellow),
+            ))),
+            Some(_) if filtered.is_empty() && picker.filter.trim().is_empty() => {
+                lines.push(Line::from(Span::styled(
+                    "No models listed - type a model name and press Enter",
+                    Style::default().fg(Color::DarkGray),
+                )))
+            }
+            Some(_) if filtered.is_empty() => lines.push(Line::from(Span::styled(
+                format!("No match - Enter uses \"{}\"", picker.filter.trim()),
+                Style::default().fg(Color::DarkGray),
+            ))),
+            Some(_) => {
+                // Keep the selection in view once the list is longer than the popup
+                let first = (picker.selected + 1).saturating_sub(list_height.max(1));
+                for (i, model) in filtered.iter().enumerate().skip(first).take(list_height) {
+                    let marker = if *model == current_model {
+                        "● "
+                    } else {
+                        "  "
+                    };
+                    let style = if i == picker.selected {
+                        Style::default()
+                            .fg(Color::Black)
+                            .bg(Color::Cyan)
+                            .add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default().fg(Color::White)
+                    };
+                    lines.push(Line::from(Span::styled(
+                        format!("{}{}", marker, model),
+                        style,
+                    )));
+                }
+            }
+        }
+
+        let title = match &picker.models {
+            Some(models) => format!(" Models ({}/{}) ", filtered.len(), models.len()),
+            None => " Models ".to_string(),
+        };
+        let popup = Paragraph::new(Text::from(lines))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Cyan))
+                    .title(title)
+                    .title_alignment(Alignment::Center)
+                    .title_bottom(" ↑/↓ select │ Enter switch │ Esc close ")
+                    .style(Style::default().bg(Color::Black)),
+            )
+            .style(Style::default().bg(Color::Black));
+
+        f.render_widget(Clear, popup_area);
+        f.render_widget(popup, popup_area);
+    }
+}
@@ -0,0 +1,75 @@
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::config::SamplingParams;
+
+pub struct ParamsWidget;
+
+impl ParamsWidget {
+    pub fn draw(f: &mut Frame, area: Rect, params: &SamplingParams) {
+        let popup_area = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Percentage(20),
+                Constraint::Length(16),
+                Constraint::Percentage(20),
+            ])
+            .split(area)[1];
+
+        let popup_area = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(20),
+                Constraint::Length(60),
+                Constraint::Percentage(20),
+            ])
+            .split(popup_area)[1];
+
+        let key_style = Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD);
+        let mut lines = vec![Line::from("⚙️  Sampling parameters"), Line::from("")];
+        for key in SamplingParams::KEYS {
+            let value = match params.get(key) {
+                Some(value) => Span::styled(value, Style::default().fg(Color::White)),
+                None => Span::styled("server default", Style::default().fg(Color::DarkGray)),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {:<20}", key), key_style),
+                value,
+            ]));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            "  /set <key> <value>   change a value",
+            Style::default().fg(Color::DarkGray),
+        )));
+        lines.push(Line::from(Span::styled(
+            "  /set <key> none      use the server default",
+            Style::default().fg(Color::DarkGray),
+        )));
+        lines.push(Line::from(Span::styled(
+            "  /set stop a,b        comma-separated, \\n for newline",
+            Style::default().fg(Color::DarkGray),
+        )));
+
+        let popup = Paragraph::new(Text::from(lines))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Cyan))
+                    .title(" Parameters (Esc to close) ")
+                    .title_alignment(Alignment::Center),
+            )
+            .style(Style::default().bg(Color::Black))
+            .wrap(Wrap { trim: false });
+
+        f.render_widget(Clear, popup_area);
+        f.render_widget(popup, popup_area);
+    }
+}
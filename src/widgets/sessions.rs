@@ -0,0 +1,155 @@
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::{app::SessionBrowser, types::Role};
+
+pub struct SessionsWidget;
+
+impl SessionsWidget {
+    pub fn draw(f: &mut Frame, area: Rect, browser: &SessionBrowser, current: Option<&str>) {
+        let popup_area = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Percentage(10),
+                Constraint::Percentage(80),
+                Constraint::Percentage(10),
+            ])
+            .split(area)[1];
+
+        let popup_area = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(5),
+                Constraint::Percentage(90),
+                Constraint::Percentage(5),
+            ])
+            .split(popup_area)[1];
+
+        f.render_widget(Clear, popup_area);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan))
+            .title(" Sessions ")
+            .title_alignment(Alignment::Center)
+            .style(Style::default().bg(Color::Black));
+        let inner = block.inner(popup_area);
+        f.render_widget(block, popup_area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(inner);
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(rows[0]);
+
+        let mut lines = Vec::new();
+        if browser.sessions.is_empty() {
+            lines.push(Line::from(Span::styled(
+                "No saved sessions yet - they appear here after the first answer",
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+        // Keep the selection in view once the list is longer than the popup
+        let list_height = (columns[0].height as usize / 2).max(1);
+        let first = browser.selected.saturating_sub(list_height - 1);
+        for (i, info) in browser
+            .sessions
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(list_height)
+        {
+            let selected = i == browser.selected;
+            let marker = if current == Some(info.id.as_str()) {
+                "● "
+            } else {
+                "  "
+            };
+            let title = match &browser.renaming {
+                Some(title) if selected => format!("{}▏", title),
+                _ => info.title.clone(),
+            };
+            let title_style = if selected {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            lines.push(Line::from(vec![
+                Span::styled(marker, Style::default().fg(Color::Green)),
+                Span::styled(title, title_style),
+            ]));
+            let model = info
+                .model
+                .as_ref()
+                .map(|model| format!(" · {}", model))
+                .unwrap_or_default();
+            lines.push(Line::from(Span::styled(
+                format!(
+                    "  {} · {} messages{} · {}",
+                    info.updated.format("%Y-%m-%d %H:%M"),
+                    info.message_count,
+                    model,
+                    info.id
+                ),
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+        f.render_widget(Paragraph::new(Text::from(lines)), columns[0]);
+
+        let mut preview = Vec::new();
+        if let Some(info) = browser.selected() {
+            for (role, line) in &info.preview {
+                let (label, color) = match role {
+                    Role::User => ("You: ", Color::Green),
+                    Role::Assistant => ("AI: ", Color::Blue),
+                    Role::System => ("System: ", Color::Gray),
+                };
+                preview.push(Line::from(vec![
+                    Span::styled(
+                        label,
+                        Style::default().fg(color).add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(line.clone(), Style::default().fg(Color::Gray)),
+                ]));
+            }
+        }
+        f.render_widget(
+            Paragraph::new(Text::from(preview))
+                .block(
+                    Block::default()
+                        .borders(Borders::LEFT)
+                        .border_style(Style::default().fg(Color::DarkGray)),
+                )
+                .wrap(Wrap { trim: true }),
+            columns[1],
+        );
+
+        let footer = if browser.renaming.is_some() {
+            Span::styled(
+                "Type a title · Enter save · Esc cancel (empty = first prompt)",
+                Style::default().fg(Color::Yellow),
+            )
+        } else if browser.confirm_delete {
+            Span::styled(
+                "Press d again to delete this session, any other key to keep it",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::styled(
+                "↑/↓ select · Enter open · r rename · d delete · Esc close",
+                Style::default().fg(Color::DarkGray),
+            )
+        };
+        f.render_widget(Paragraph::new(Line::from(footer)), rows[1]);
+    }
+}